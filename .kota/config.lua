@@ -1,14 +1,20 @@
 kota.setup({
+  provider = "deepseek",
   model = "deepseek-chat",
   api_key = os.getenv("API_KEY"),
   api_base = "https://api.deepseek.com/v1",
   temperature = 0.7,
   
   tools = {
-    enabled = { "grep_find", "write_file", "read_file" },
+    -- Only these tools are exposed when set; leave unset to keep all of them
+    -- enabled = { "grep_find", "write_file", "read_file" },
     disabled = { "delete_file" },
   },
   
+  -- mcp_servers = {
+  --   git = { command = "uvx", args = { "mcp-server-git" } },
+  -- },
+
   -- custom_tools = {
   --   my_tool = require("plugins.my_tool"),
   -- },
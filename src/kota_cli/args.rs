@@ -0,0 +1,164 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::kota_code::runtime::{ApprovalMode, ConfigSource, KotaConfig};
+
+/// Output format for one-shot mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Print the final answer as plain text
+    #[default]
+    Text,
+    /// Print a JSON report with the answer, token usage and tool calls
+    Json,
+}
+
+/// Subcommand run instead of the agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CliCommand {
+    /// `kota config check`: validate the config files and exit
+    ConfigCheck,
+}
+
+/// Command line arguments of the `kota` binary
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliArgs {
+    pub command: Option<CliCommand>,
+    /// Prompt for one-shot mode; `-` reads the prompt from stdin
+    pub prompt: Option<String>,
+    pub output: OutputFormat,
+    /// Lua config file to load instead of `.kota/config.lua`
+    pub config: Option<PathBuf>,
+    /// Model overriding `model` from the config
+    pub model: Option<String>,
+    /// Session id to start or continue
+    pub session: Option<String>,
+    /// Continue the most recently updated session
+    pub resume: bool,
+    /// Directory where sessions are stored
+    pub sessions_dir: Option<PathBuf>,
+    /// Start the agent without any tools
+    pub no_tools: bool,
+    /// Approval mode overriding `approval.mode` from the config
+    pub approval: Option<ApprovalMode>,
+    /// Working directory to run in
+    pub cwd: Option<PathBuf>,
+    pub help: bool,
+    pub version: bool,
+}
+
+pub const USAGE: &str = "\
+Usage: kota [OPTIONS]
+       kota config check [OPTIONS]
+
+Starts the interactive REPL. With -p, or when stdin is not a terminal, runs a
+single prompt non-interactively and exits.
+
+Commands:
+  config check                Validate the global and project config files; exits
+                              non-zero if they contain errors
+
+Options:
+  -p, --prompt <PROMPT>       Run a single prompt and exit (`-` reads it from stdin)
+      --output <FORMAT>       One-shot output format: text (default) or json
+  -c, --config <PATH>         Project config file [default: nearest .kota/config.lua]
+  -m, --model <MODEL>         Model to use, overriding the config
+  -s, --session <ID>          Start or continue the session with this id
+  -r, --resume                Continue the most recently updated session
+      --sessions-dir <DIR>    Directory for session files [default: .chat_sessions]
+      --no-tools              Start the agent without any tools
+      --approval <MODE>       Approval mode: ask, auto-edit, auto or read-only
+      --cwd <DIR>             Run in this working directory
+  -h, --help                  Print help
+  -V, --version               Print version";
+
+impl CliArgs {
+    /// Parse arguments, including the binary name as the first item
+    pub fn parse_from<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut parsed = CliArgs::default();
+        let mut args = args.into_iter().map(Into::into).skip(1);
+
+        while let Some(arg) = args.next() {
+            // Support both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| -> Result<String> {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow::anyhow!("Missing value for {}", name))
+            };
+
+            match flag.as_str() {
+                "-p" | "--prompt" => parsed.prompt = Some(value("--prompt")?),
+                "--output" => {
+                    parsed.output = match value("--output")?.as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        other => {
+                            return Err(anyhow::anyhow!(
+                                "Invalid value for --output: {} (expected text or json)",
+                                other
+                            ))
+                        }
+                    }
+                }
+                "-c" | "--config" => parsed.config = Some(value("--config")?.into()),
+                "-m" | "--model" => parsed.model = Some(value("--model")?),
+                "-s" | "--session" => parsed.session = Some(value("--session")?),
+                "-r" | "--resume" => parsed.resume = true,
+                "--sessions-dir" => parsed.sessions_dir = Some(value("--sessions-dir")?.into()),
+                "--no-tools" => parsed.no_tools = true,
+                "--approval" => parsed.approval = Some(value("--approval")?.parse()?),
+                "--cwd" => parsed.cwd = Some(value("--cwd")?.into()),
+                "-h" | "--help" => parsed.help = true,
+                "-V" | "--version" => parsed.version = true,
+                "config" if parsed.command.is_none() => match args.next().as_deref() {
+                    Some("check") => parsed.command = Some(CliCommand::ConfigCheck),
+                    Some(other) => {
+                        return Err(anyhow::anyhow!("Unknown config command: {}", other))
+                    }
+                    None => return Err(anyhow::anyhow!("Missing config command (try: check)")),
+                },
+                other => return Err(anyhow::anyhow!("Unexpected argument: {}", other)),
+            }
+        }
+
+        if parsed.resume && parsed.session.is_some() {
+            return Err(anyhow::anyhow!(
+                "--resume and --session cannot be used together"
+            ));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Apply the overrides from the command line to a loaded config
+    pub fn apply_to(&self, config: &mut KotaConfig) {
+        if let Some(model) = &self.model {
+            config.model = model.clone();
+            config.set_source("model", &ConfigSource::Cli);
+        }
+        if let Some(mode) = self.approval {
+            config.approval.mode = mode;
+            config.set_source("approval.mode", &ConfigSource::Cli);
+        }
+        if self.no_tools {
+            config.disable_all_tools = true;
+            config.set_source("tools", &ConfigSource::Cli);
+        }
+    }
+
+    /// Parse the arguments of the current process
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args())
+    }
+}
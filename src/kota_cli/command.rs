@@ -1,4 +1,6 @@
+use crate::kota_code::checkpoint::{Checkpoint, CheckpointManager};
 use crate::kota_code::context::{ContextManager, SerializableMessage};
+use crate::kota_code::runtime::ConfigSource;
 use anyhow::Result;
 use colored::*;
 
@@ -30,6 +32,28 @@ impl KotaCli {
             "/skill-off" => {
                 self.deactivate_skill()?;
             }
+            "/reload" => {
+                if let Err(e) = self.reload().await {
+                    println!(
+                        "{} Reload failed, keeping the current setup: {}",
+                        "❌".red(),
+                        e
+                    );
+                }
+            }
+            "/checkpoints" => {
+                self.list_checkpoints()?;
+            }
+            "/undo" => {
+                self.undo_checkpoint()?;
+            }
+            _ if input.starts_with("/restore") => {
+                let id = input.strip_prefix("/restore").unwrap_or("").trim();
+                match id.parse::<usize>() {
+                    Ok(id) => self.restore_checkpoint(id)?,
+                    Err(_) => println!("{} Usage: /restore <checkpoint_id>", "❌".red()),
+                }
+            }
             _ if input.starts_with("/load ") => {
                 let session_id = input.strip_prefix("/load ").unwrap_or("").trim();
                 self.load_session(session_id)?;
@@ -73,14 +97,137 @@ impl KotaCli {
     }
 
     fn show_config(&self) -> Result<()> {
+        let source = |key: &str| format!("({})", self.config.source_of(key)).dimmed();
+
         println!("{}", "⚙️  Current Configuration:".bright_cyan());
-        println!("  {} {}", "API Base:".bright_white(), self.api_base);
-        println!("  {} {}", "Model:".bright_white(), self.model_name);
         println!(
-            "  {} {}",
+            "  {} {} {}",
+            "Provider:".bright_white(),
+            self.provider,
+            source("provider")
+        );
+        println!(
+            "  {} {} {}",
+            "API Base:".bright_white(),
+            self.api_base.as_deref().unwrap_or("(provider default)"),
+            source("api_base")
+        );
+        println!(
+            "  {} {} {}",
+            "Model:".bright_white(),
+            self.model_name,
+            source("model")
+        );
+        println!(
+            "  {} {} {}",
             "API Key:".bright_white(),
-            "*".repeat(self.api_key.len().min(8))
+            "*".repeat(self.api_key.len().min(8)),
+            source("api_key")
         );
+
+        let optional = [
+            (
+                "temperature",
+                self.config.temperature.map(|t| t.to_string()),
+            ),
+            ("max_tokens", self.config.max_tokens.map(|t| t.to_string())),
+            (
+                "context.max_messages",
+                Some(self.config.context_max_messages.to_string()),
+            ),
+            (
+                "context.token_budget",
+                self.config.context_token_budget.map(|t| t.to_string()),
+            ),
+            ("approval.mode", Some(self.config.approval.mode.to_string())),
+            ("workspace.root", self.config.workspace_root.clone()),
+            ("edit.fuzz", Some(self.config.edit_fuzz.to_string())),
+            (
+                "read.max_bytes",
+                Some(self.config.read_max_bytes.to_string()),
+            ),
+            (
+                "exec.timeout_secs",
+                Some(self.config.exec.timeout_secs.to_string()),
+            ),
+            (
+                "exec.max_output_bytes",
+                Some(self.config.exec.max_output_bytes.to_string()),
+            ),
+            (
+                "exec.env.allow",
+                (!self.config.exec.env_allow.is_empty())
+                    .then(|| self.config.exec.env_allow.join(", ")),
+            ),
+            (
+                "exec.env.deny",
+                (!self.config.exec.env_deny.is_empty())
+                    .then(|| self.config.exec.env_deny.join(", ")),
+            ),
+            (
+                "workspace.protected",
+                (!self.config.protected_paths.is_empty())
+                    .then(|| self.config.protected_paths.join(", ")),
+            ),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                println!(
+                    "  {} {} {}",
+                    format!("{}:", key).bright_white(),
+                    value,
+                    source(key)
+                );
+            }
+        }
+
+        // Tables merged across layers: tools, commands, hooks, mcp servers
+        let merged: Vec<_> = self
+            .config
+            .sources
+            .iter()
+            .filter(|(key, _)| {
+                key.starts_with("tools")
+                    || (key.starts_with("approval.") && *key != "approval.mode")
+                    || key.starts_with("commands.")
+                    || key.starts_with("hooks.")
+                    || key.starts_with("mcp_servers.")
+            })
+            .collect();
+        if !merged.is_empty() {
+            println!();
+            for (key, layer) in merged {
+                println!(
+                    "  {} {}",
+                    key.bright_white(),
+                    format!("({})", layer).dimmed()
+                );
+            }
+        }
+
+        let session_allowed = self.approval.session_allowed();
+        if !session_allowed.is_empty() {
+            println!(
+                "  {} {}",
+                "Allowed this session:".bright_white(),
+                session_allowed.join(", ")
+            );
+        }
+
+        println!();
+        println!("{}", "📂 Config layers:".bright_cyan());
+        println!("  {}", ConfigSource::Default);
+        for layer in &self.config.layers {
+            println!("  {}", layer);
+        }
+        if self
+            .config
+            .sources
+            .values()
+            .any(|layer| *layer == ConfigSource::Cli)
+        {
+            println!("  {}", ConfigSource::Cli);
+        }
         println!();
         Ok(())
     }
@@ -110,6 +257,22 @@ impl KotaCli {
             "  {} - Load specific session",
             "/load <session_id>".bright_green()
         );
+        println!(
+            "  {} - Reload config, skills and Lua tools",
+            "/reload".bright_green()
+        );
+        println!(
+            "  {} - List file checkpoints of this session",
+            "/checkpoints".bright_green()
+        );
+        println!(
+            "  {} - Revert the files changed in the last turn",
+            "/undo".bright_green()
+        );
+        println!(
+            "  {} - Revert the files changed since a checkpoint",
+            "/restore <id>".bright_green()
+        );
         println!("  {} - List all sessions", "/sessions".bright_green());
         println!(
             "  {} - Delete a specific session",
@@ -246,6 +409,10 @@ impl KotaCli {
     }
 
     fn load_session(&mut self, session_id: &str) -> Result<()> {
+        if let Err(e) = ContextManager::validate_session_id(session_id) {
+            println!("{} {}", "❌".red(), e);
+            return Ok(());
+        }
         let context = self
             .agent_instance
             .context_mut()
@@ -260,8 +427,19 @@ impl KotaCli {
             );
         }
 
-        // 切换到新会话
+        // 切换到新会话, 检查点跟随会话
         context.switch_session(session_id.to_string());
+        if let Err(e) = self
+            .shared
+            .checkpoints
+            .switch_session(context.storage_dir(), session_id)
+        {
+            println!(
+                "{} Warning: Failed to load checkpoints: {}",
+                "⚠️".yellow(),
+                e
+            );
+        }
 
         match context.load() {
             Ok(true) => {
@@ -296,6 +474,10 @@ impl KotaCli {
     }
 
     fn delete_session(&mut self, session_id: &str) -> Result<()> {
+        if let Err(e) = ContextManager::validate_session_id(session_id) {
+            println!("{} {}", "❌".red(), e);
+            return Ok(());
+        }
         let context = self
             .agent_instance
             .context()
@@ -308,7 +490,15 @@ impl KotaCli {
         }
 
         // 创建临时上下文管理器来删除指定会话
-        let temp_context = ContextManager::new("./.chat_sessions", session_id.to_string())?;
+        let temp_context = ContextManager::new(context.storage_dir(), session_id.to_string())?;
+
+        if let Err(e) = CheckpointManager::delete_session(context.storage_dir(), session_id) {
+            println!(
+                "{} Warning: Failed to delete checkpoints: {}",
+                "⚠️".yellow(),
+                e
+            );
+        }
 
         match temp_context.delete_session() {
             Ok(true) => {
@@ -338,6 +528,83 @@ impl KotaCli {
         Ok(())
     }
 
+    fn list_checkpoints(&self) -> Result<()> {
+        let checkpoints = self.shared.checkpoints.list()?;
+        if checkpoints.is_empty() {
+            println!("{} No checkpoints in current session", "🕘".bright_blue());
+            println!();
+            return Ok(());
+        }
+
+        println!("{} Checkpoints (oldest first):", "🕘".bright_blue());
+        println!();
+        for checkpoint in &checkpoints {
+            let prompt = checkpoint.prompt.lines().next().unwrap_or("");
+            let prompt = if prompt.chars().count() > 60 {
+                format!("{}...", prompt.chars().take(60).collect::<String>())
+            } else {
+                prompt.to_string()
+            };
+            println!(
+                "{}. {} - {} files",
+                checkpoint.id.to_string().bright_white(),
+                prompt.bright_cyan(),
+                checkpoint.files.len().to_string().bright_yellow()
+            );
+            println!("   Created: {}", checkpoint.created_at.dimmed());
+            for file in &checkpoint.files {
+                println!("   {}", file.path.display().to_string().dimmed());
+            }
+        }
+
+        println!();
+        println!(
+            "{} Use '/undo' to revert the last turn or '/restore <id>' to revert back to a checkpoint",
+            "💡".bright_blue()
+        );
+        println!();
+        Ok(())
+    }
+
+    fn undo_checkpoint(&self) -> Result<()> {
+        match self.shared.checkpoints.undo() {
+            Ok(Some(checkpoint)) => Self::print_restored(&[checkpoint]),
+            Ok(None) => println!("{} Nothing to undo", "🕘".bright_blue()),
+            Err(e) => println!("{} Failed to undo: {}", "❌".red(), e),
+        }
+        println!();
+        Ok(())
+    }
+
+    fn restore_checkpoint(&self, id: usize) -> Result<()> {
+        match self.shared.checkpoints.restore(id) {
+            Ok(restored) => Self::print_restored(&restored),
+            Err(e) => {
+                println!("{} Failed to restore checkpoint {}: {}", "❌".red(), id, e);
+                println!(
+                    "{} Use '/checkpoints' to see available checkpoints",
+                    "💡".bright_blue()
+                );
+            }
+        }
+        println!();
+        Ok(())
+    }
+
+    fn print_restored(restored: &[Checkpoint]) {
+        for checkpoint in restored {
+            println!(
+                "{} Reverted checkpoint {} ({} files)",
+                "✅".bright_green(),
+                checkpoint.id.to_string().bright_cyan(),
+                checkpoint.files.len()
+            );
+            for file in &checkpoint.files {
+                println!("   {}", file.path.display().to_string().dimmed());
+            }
+        }
+    }
+
     fn list_skills(&mut self) -> Result<()> {
         let skill_manager = self
             .agent_instance
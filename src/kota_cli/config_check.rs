@@ -0,0 +1,49 @@
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+use crate::kota_code::runtime::KotaConfig;
+
+/// Validate every config layer and print the diagnostics (`kota config check`)
+///
+/// # Arguments
+///
+/// * `project_config` - Config file given with `--config`, replacing the project layer
+///
+/// # Returns
+///
+/// Returns `true` if no file contains errors; warnings do not fail the check
+pub fn check_config(project_config: Option<&Path>) -> Result<bool> {
+    let files = KotaConfig::config_files(project_config)?;
+    if files.is_empty() {
+        println!(
+            "{} No config files found (looked for the global config and .kota/config.lua)",
+            "⚠️ ".yellow()
+        );
+        return Ok(true);
+    }
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for (path, source) in files {
+        let diagnostics = KotaConfig::check_lua_file(&path);
+        if diagnostics.is_empty() {
+            println!("{} {} ({})", "✅".green(), path.display(), source);
+            continue;
+        }
+
+        for diagnostic in diagnostics {
+            if diagnostic.is_error() {
+                errors += 1;
+                println!("{}", diagnostic.to_string().red());
+            } else {
+                warnings += 1;
+                println!("{}", diagnostic.to_string().yellow());
+            }
+        }
+    }
+
+    println!();
+    println!("{} error(s), {} warning(s)", errors, warnings);
+    Ok(errors == 0)
+}
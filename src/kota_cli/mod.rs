@@ -1,17 +1,30 @@
-use crate::kota_code::agent::{AgentBuilder, AgentInstance};
+use crate::kota_code::agent::{AgentBuilder, AgentInstance, Provider};
+use crate::kota_code::checkpoint::CheckpointManager;
 use crate::kota_code::context::ContextManager;
+use crate::kota_code::mcp::McpManager;
+use crate::kota_code::runtime::{ApprovalGate, KotaConfig, TerminalApprover, ToolFilter};
 use crate::kota_code::skills::SkillManager;
+use crate::kota_code::tools::{CommandInterrupt, ProcessManager, Workspace};
+use crate::kota_code::PlanManager;
 use anyhow::Result;
 use colored::*;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
+use std::sync::Arc;
 
+pub mod args;
 mod command;
 pub mod command_registry;
+pub mod config_check;
+pub mod oneshot;
+mod reload;
 mod render;
 mod tab;
 
+pub use args::{CliArgs, CliCommand, OutputFormat};
 pub use command_registry::{parse_command_input, CommandRegistry};
+pub use oneshot::OneShotReport;
+pub use reload::ConfigWatcher;
 use tab::KotaHelper;
 
 const LOGO: &str = r#"
@@ -26,42 +39,146 @@ const LOGO: &str = r#"
 
 pub struct KotaCli {
     pub agent_instance: AgentInstance,
-    pub api_base: String,
+    pub api_base: Option<String>,
+    pub provider: Provider,
     pub model_name: String,
     pub api_key: String,
     pub command_registry: Option<CommandRegistry>,
+    pub mcp_manager: McpManager,
+    /// Effective configuration, including the layer each value came from
+    pub config: KotaConfig,
+    /// Command line arguments re-applied on `/reload`
+    pub cli_args: CliArgs,
+    /// Shared with the rebuilt agent so plans, processes and checkpoints survive `/reload`
+    shared: SharedState,
+    /// Approval policy; tools allowed for the session survive `/reload`
+    approval: ApprovalGate,
+    /// Polls config, skill and tool files when `watch = true`
+    watcher: Option<ConfigWatcher>,
+}
+
+/// Managers handed to every agent the REPL builds
+#[derive(Clone, Default)]
+struct SharedState {
+    plan_manager: PlanManager,
+    /// Background processes keep running across reloads
+    process_manager: ProcessManager,
+    /// File snapshots for `/undo`, stored next to the session
+    checkpoints: CheckpointManager,
+    /// Cancels the running `exec_cmd` command on Ctrl-C
+    interrupt: CommandInterrupt,
 }
 
 impl KotaCli {
-    pub fn new(
-        api_key: String,
-        api_base: String,
-        model_name: String,
+    pub async fn new(
+        config: &KotaConfig,
         context: ContextManager,
         skill_manager: SkillManager,
         command_registry: Option<CommandRegistry>,
+        mcp_manager: McpManager,
     ) -> Result<Self> {
-        let agent_instance = AgentBuilder::new(api_key.clone(), model_name.clone())?
-            .with_context(context)
-            .with_skill_manager(skill_manager)
-            .build()?;
+        let shared = SharedState {
+            checkpoints: CheckpointManager::open(context.storage_dir(), context.session_id())?,
+            ..Default::default()
+        };
+        let approval = ApprovalGate::new(
+            config.approval.clone(),
+            Arc::new(TerminalApprover::default()),
+        );
+        let mut agent_instance =
+            Self::build_agent(config, skill_manager, &mcp_manager, &shared, &approval).await?;
+        agent_instance.context = Some(context);
+
+        let provider = agent_instance.agent.provider();
 
         Ok(Self {
             agent_instance,
-            api_base,
-            model_name,
-            api_key,
+            api_base: config.api_base.clone(),
+            provider,
+            model_name: config.model.clone(),
+            api_key: config.api_key.clone(),
             command_registry,
+            mcp_manager,
+            config: config.clone(),
+            cli_args: CliArgs::default(),
+            shared,
+            approval,
+            watcher: None,
         })
     }
 
+    /// Set the command line arguments to re-apply when the config is reloaded
+    ///
+    /// # Arguments
+    ///
+    /// * `cli_args` - Parsed arguments; `config` should be an absolute path
+    pub fn with_cli_args(mut self, cli_args: CliArgs) -> Self {
+        self.cli_args = cli_args;
+        self
+    }
+
+    /// Build an agent from the config, without a context manager
+    async fn build_agent(
+        config: &KotaConfig,
+        skill_manager: SkillManager,
+        mcp_manager: &McpManager,
+        shared: &SharedState,
+        approval: &ApprovalGate,
+    ) -> Result<AgentInstance> {
+        let mut builder = AgentBuilder::new(config.api_key.clone(), config.model.clone())?;
+        if let Some(provider) = config.provider {
+            builder = builder.with_provider(provider);
+        }
+        if let Some(api_base) = &config.api_base {
+            builder = builder.with_api_base(api_base);
+        }
+        if let Some(temperature) = config.temperature {
+            builder = builder.with_temperature(temperature);
+        }
+        if let Some(max_tokens) = config.max_tokens {
+            builder = builder.with_max_tokens(max_tokens);
+        }
+
+        let root = config.workspace_root.as_deref().unwrap_or(".");
+        let workspace = Workspace::new(root)
+            .and_then(|ws| ws.with_protected(config.protected_paths.iter().cloned()))
+            .map_err(|e| anyhow::anyhow!("Invalid workspace '{}': {}", root, e))?;
+
+        builder
+            .with_workspace(workspace)
+            .with_plan_manager(shared.plan_manager.clone())
+            .with_process_manager(shared.process_manager.clone())
+            .with_checkpoints(shared.checkpoints.clone())
+            .with_approval(approval.clone())
+            .with_exec_options(config.exec.clone())
+            .with_patch_fuzz(config.edit_fuzz)
+            .with_read_max_bytes(config.read_max_bytes)
+            .with_command_interrupt(shared.interrupt.clone())
+            .with_skill_manager(skill_manager)
+            .with_hooks(config.hooks.clone())
+            .with_tool_filter(if config.disable_all_tools {
+                ToolFilter::none()
+            } else {
+                ToolFilter::new(config.enabled_tools.clone(), config.disabled_tools.clone())
+            })
+            .with_mcp_manager(mcp_manager)
+            .await
+            .build()
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         println!("{}", LOGO);
         self.show_welcome()?;
         self.show_tips()?;
+        self.update_watcher();
+        self.handle_interrupts();
 
         let result = self.run_input_loop().await;
 
+        // Shut down MCP server and background processes
+        let _ = self.mcp_manager.close_all().await;
+        self.agent_instance.process_manager().kill_all().await;
+
         match result {
             Ok(_) => println!("\n{}", "👋 Goodbye!".bright_cyan()),
             Err(e) => {
@@ -73,11 +190,30 @@ impl KotaCli {
         Ok(())
     }
 
+    /// Let Ctrl-C cancel a running command instead of exiting
+    ///
+    /// While the prompt is shown the line editor handles Ctrl-C itself. At any
+    /// other time without a running command, Ctrl-C still exits.
+    pub fn handle_interrupts(&self) {
+        let interrupt = self.shared.interrupt.clone();
+        let processes = self.shared.process_manager.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if !interrupt.interrupt() {
+                    println!("\n{}", "^C".dimmed());
+                    processes.kill_all().await;
+                    std::process::exit(130);
+                }
+            }
+        });
+    }
+
     async fn run_input_loop(&mut self) -> Result<()> {
         let mut rl = Editor::new()?;
         rl.set_helper(Some(KotaHelper::default()));
 
         loop {
+            self.reload_if_changed().await;
             self.print_separator()?;
             let readline = rl.readline("❯ ");
 
@@ -88,6 +224,9 @@ impl KotaCli {
                         continue;
                     }
 
+                    // Files may have changed while waiting for input
+                    self.reload_if_changed().await;
+
                     // 添加到历史记录
                     let _ = rl.add_history_entry(input);
 
@@ -0,0 +1,128 @@
+use crate::kota_code::runtime::ToolCallRecord;
+use anyhow::Result;
+use colored::*;
+use rig::completion::Usage;
+use serde::Serialize;
+use std::io::Write;
+
+use super::args::OutputFormat;
+use super::KotaCli;
+
+/// Result of a one-shot run, printed with `--output json`
+#[derive(Debug, Serialize)]
+pub struct OneShotReport {
+    /// `false` if the provider failed or any tool call returned an error
+    pub success: bool,
+    pub session_id: Option<String>,
+    /// Final text of the agent
+    pub response: String,
+    pub usage: Usage,
+    pub tool_calls: Vec<ToolCallRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl KotaCli {
+    /// Run a single prompt with the full tool loop, without entering the REPL
+    ///
+    /// The streamed transcript is printed as usual; only the final result is
+    /// written to `out`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the provider failed or any tool call failed
+    pub async fn run_once(
+        &mut self,
+        prompt: &str,
+        output: OutputFormat,
+        out: &mut dyn Write,
+    ) -> Result<bool> {
+        self.handle_interrupts();
+        let result = self.agent_instance.chat(prompt).await;
+        println!();
+
+        // Shut down MCP server and background processes
+        let _ = self.mcp_manager.close_all().await;
+        self.agent_instance.process_manager().kill_all().await;
+
+        let tool_calls = self.agent_instance.tool_log().take();
+        let (response, usage, error) = match result {
+            Ok(resp) => (resp.response().to_string(), resp.usage(), None),
+            Err(e) => (String::new(), Usage::new(), Some(format!("{:#}", e))),
+        };
+        let report = OneShotReport {
+            success: error.is_none() && tool_calls.iter().all(|call| call.success),
+            session_id: self
+                .agent_instance
+                .context()
+                .map(|c| c.session_id().to_string()),
+            response,
+            usage,
+            tool_calls,
+            error,
+        };
+
+        match output {
+            OutputFormat::Json => {
+                writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+            }
+            OutputFormat::Text => {
+                if let Some(error) = &report.error {
+                    eprintln!("{} Failed to get AI response: {}", "❌".red(), error);
+                } else {
+                    writeln!(out, "{}", report.response)?;
+                }
+                for call in report.tool_calls.iter().filter(|call| !call.success) {
+                    eprintln!(
+                        "{} Tool call {} failed: {}",
+                        "⚠️".yellow(),
+                        call.name,
+                        call.error.as_deref().unwrap_or("unknown error")
+                    );
+                }
+            }
+        }
+        out.flush()?;
+
+        Ok(report.success)
+    }
+}
+
+/// Send everything printed to stdout to stderr from now on
+///
+/// The interactive transcript (streamed text, tool status lines) is printed with
+/// `println!` throughout the agent. In one-shot mode it is moved to stderr so
+/// stdout only carries the final answer or JSON report.
+///
+/// # Returns
+///
+/// Returns a writer to the original stdout
+#[cfg(unix)]
+pub fn redirect_stdout_to_stderr() -> std::io::Result<Box<dyn Write>> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    std::io::stdout().flush()?;
+    let stdout_fd = std::io::stdout().as_raw_fd();
+    let stderr_fd = std::io::stderr().as_raw_fd();
+
+    // SAFETY: dup/dup2 on the process' own standard descriptors; the duplicated
+    // descriptor is owned by the returned File.
+    unsafe {
+        let original = libc::dup(stdout_fd);
+        if original < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::dup2(stderr_fd, stdout_fd) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Box::new(std::fs::File::from_raw_fd(original)))
+    }
+}
+
+/// Send everything printed to stdout to stderr from now on
+///
+/// Not supported on this platform: the transcript stays on stdout.
+#[cfg(not(unix))]
+pub fn redirect_stdout_to_stderr() -> std::io::Result<Box<dyn Write>> {
+    Ok(Box::new(std::io::stdout()))
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use super::{CommandRegistry, KotaCli};
+use crate::kota_code::runtime::KotaConfig;
+use crate::kota_code::SkillManager;
+
+/// How often the watcher checks the files for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Directories reloaded with the config
+const WATCHED_DIRS: [&str; 2] = [".kota/skills", ".kota/tools"];
+
+/// Size and modification time of every watched file
+type Fingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+/// Background thread that polls the config files, skills and Lua tools
+///
+/// Changes are not applied from the thread: it only raises a flag that the REPL
+/// checks before reading and before handling each input.
+pub struct ConfigWatcher {
+    changed: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// Start watching the config layers for `project_config` (see
+    /// [`KotaConfig::config_files`]) and the `.kota/skills` and `.kota/tools` directories
+    pub fn start(project_config: Option<PathBuf>) -> Self {
+        let changed = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Take the baseline before returning so later edits are never missed
+        let mut last = Self::fingerprint(project_config.as_deref());
+        let handle = {
+            let changed = changed.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(POLL_INTERVAL);
+                    let current = Self::fingerprint(project_config.as_deref());
+                    if current != last {
+                        changed.store(true, Ordering::Relaxed);
+                        last = current;
+                    }
+                }
+            })
+        };
+
+        Self {
+            changed,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Check whether anything changed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    fn fingerprint(project_config: Option<&Path>) -> Fingerprint {
+        let mut files: Vec<PathBuf> = KotaConfig::config_files(project_config)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        for dir in WATCHED_DIRS {
+            Self::collect_files(Path::new(dir), &mut files);
+        }
+        files.sort();
+
+        files
+            .into_iter()
+            .map(|path| {
+                let metadata = std::fs::metadata(&path).ok();
+                let len = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+                let modified = metadata.and_then(|m| m.modified().ok());
+                (path, len, modified)
+            })
+            .collect()
+    }
+
+    fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                Self::collect_files(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl KotaCli {
+    /// Reload the config, custom commands, skills and Lua tools, then rebuild the agent
+    ///
+    /// The current session (`ContextManager`), plan and active skill are kept. If the
+    /// new config is invalid nothing is changed.
+    pub async fn reload(&mut self) -> Result<()> {
+        let mut config = KotaConfig::load_layered(self.cli_args.config.as_deref())?;
+        self.cli_args.apply_to(&mut config);
+        for warning in &config.warnings {
+            println!("{} {}", "⚠️ ".yellow(), warning);
+        }
+
+        let command_registry = if config.commands.is_empty() {
+            None
+        } else {
+            Some(CommandRegistry::new(&config)?)
+        };
+
+        let mut skill_manager = SkillManager::new();
+        if let Some(active) = self
+            .agent_instance
+            .skill_manager()
+            .and_then(|manager| manager.get_active_skill())
+        {
+            // The skill may have been removed from disk
+            let _ = skill_manager.activate_skill(&active.name);
+        }
+
+        let approval = self.approval.with_config(config.approval.clone());
+        let mut agent_instance = Self::build_agent(
+            &config,
+            skill_manager,
+            &self.mcp_manager,
+            &self.shared,
+            &approval,
+        )
+        .await?;
+
+        if config.mcp_servers != self.config.mcp_servers {
+            println!(
+                "{} Changes to mcp_servers take effect after restarting kota",
+                "💡".bright_blue()
+            );
+        }
+
+        // Keep the conversation: move the context manager over to the new agent
+        agent_instance.context = self.agent_instance.context.take();
+        self.agent_instance = agent_instance;
+        self.provider = self.agent_instance.agent.provider();
+        self.api_base = config.api_base.clone();
+        self.model_name = config.model.clone();
+        self.api_key = config.api_key.clone();
+        self.command_registry = command_registry;
+        self.approval = approval;
+        self.config = config;
+        self.update_watcher();
+
+        let skill_count = self
+            .agent_instance
+            .skill_manager()
+            .map(|manager| manager.list_skills().len())
+            .unwrap_or(0);
+        let command_count = self
+            .command_registry
+            .as_ref()
+            .map(|registry| registry.list_commands().len())
+            .unwrap_or(0);
+        println!(
+            "{} Reloaded configuration: model {}, {} skills, {} custom commands",
+            "🔄".bright_cyan(),
+            self.model_name.bright_yellow(),
+            skill_count.to_string().bright_yellow(),
+            command_count.to_string().bright_yellow()
+        );
+        Ok(())
+    }
+
+    /// Start or stop the file watcher to match the `watch` option
+    pub(super) fn update_watcher(&mut self) {
+        match (self.config.watch, self.watcher.is_some()) {
+            (true, false) => {
+                self.watcher = Some(ConfigWatcher::start(self.cli_args.config.clone()));
+            }
+            (false, true) => self.watcher = None,
+            _ => {}
+        }
+    }
+
+    /// Reload if the watcher saw changes, reporting errors without exiting
+    pub(super) async fn reload_if_changed(&mut self) {
+        let changed = self
+            .watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.take_changed());
+        if !changed {
+            return;
+        }
+
+        println!(
+            "{} Detected changes in config, skills or tools",
+            "👀".bright_cyan()
+        );
+        if let Err(e) = self.reload().await {
+            println!(
+                "{} Reload failed, keeping the current setup: {}",
+                "❌".red(),
+                e
+            );
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use colored::*;
-use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::{Highlighter, MatchingBracketHighlighter};
 use rustyline::hint::{Hinter, HistoryHinter};
@@ -9,7 +9,6 @@ use std::borrow::Cow::{self, Borrowed, Owned};
 use std::collections::HashSet;
 
 pub struct KotaHelper {
-    completer: FilenameCompleter,
     highlighter: MatchingBracketHighlighter,
     validator: MatchingBracketValidator,
     hinter: HistoryHinter,
@@ -30,9 +29,12 @@ impl Default for KotaHelper {
         commands.insert("/load".to_string());
         commands.insert("/sessions".to_string());
         commands.insert("/delete".to_string());
+        commands.insert("/reload".to_string());
+        commands.insert("/checkpoints".to_string());
+        commands.insert("/undo".to_string());
+        commands.insert("/restore".to_string());
 
         Self {
-            completer: FilenameCompleter::new(),
             highlighter: MatchingBracketHighlighter::new(),
             validator: MatchingBracketValidator::new(),
             hinter: HistoryHinter {},
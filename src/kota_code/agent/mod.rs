@@ -2,34 +2,63 @@ use anyhow::Result;
 use rig::{
     agent::Agent,
     client::CompletionClient,
-    providers::{
-        anthropic, cohere,
-        deepseek::{self, DEEPSEEK_CHAT},
-        ollama, openai,
-    },
+    completion::{message::AssistantContent, CompletionModel, Message},
+    providers::{anthropic, cohere, deepseek, ollama, openai},
     streaming::StreamingPrompt,
+    tool::ToolDyn,
 };
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 
+use super::checkpoint::CheckpointManager;
 use super::context::ContextManager;
+use super::mcp::{tool::McpTool, McpManager};
 use super::plan::PlanManager;
-use super::runtime::{dyn_tools_loader::LuaToolLoader, ToolRegistry};
+use super::runtime::{
+    dyn_tools_loader::LuaToolLoader, ApprovalGate, ApprovalTool, HookedTool, LoggedTool,
+    ToolCallLog, ToolFilter, ToolHooks, ToolRegistry,
+};
 use super::skills::SkillManager;
+use stream::stream_to_stdout;
+pub use stream::{InterruptedTurn, StreamedTurn, TurnRecorder};
+
+mod stream;
+
+use super::tools::patch::DEFAULT_FUZZ;
+use super::tools::read_file::DEFAULT_MAX_READ_BYTES;
 use super::tools::{
-    WrappedCreateDirectoryTool, WrappedDeleteFileTool, WrappedEditFileTool,
-    WrappedExecuteBashCommandTool, WrappedGrepSearchTool, WrappedReadFileTool,
-    WrappedScanCodebaseTool, WrappedUpdatePlanTool, WrappedWriteFileTool,
+    CommandInterrupt, ExecOptions, ProcessManager, Workspace, WrappedCreateDirectoryTool,
+    WrappedDeleteFileTool, WrappedEditFileTool, WrappedExecuteBashCommandTool,
+    WrappedGrepSearchTool, WrappedProcessTool, WrappedReadFileTool, WrappedScanCodebaseTool,
+    WrappedShellSessionTool, WrappedUpdatePlanTool, WrappedWriteFileTool,
 };
 
+/// Default maximum number of tokens the model may generate per completion
+pub const DEFAULT_MAX_TOKENS: u64 = 4096;
+
+macro_rules! build_client {
+    ($client:ty, $api_key:expr, $api_base:expr) => {{
+        let builder = <$client>::builder().api_key($api_key);
+        match $api_base {
+            Some(api_base) => builder.base_url(api_base).build(),
+            None => builder.build(),
+        }
+    }};
+}
+
 macro_rules! build_agent {
-    ($client_expr:expr, $model_name:expr, $preamble:expr, $dyn_tools:expr, $variant:ident) => {{
+    ($client_expr:expr, $model_name:expr, $preamble:expr, $dyn_tools:expr, $settings:expr, $variant:ident) => {{
         let client = $client_expr?;
-        let agent = client
+        let mut agent = client
             .agent($model_name)
             .preamble(&$preamble)
-            .max_tokens(4096)
-            .tools($dyn_tools)
-            .build();
-        AgentType::$variant(agent)
+            .max_tokens($settings.max_tokens)
+            .tools($dyn_tools);
+        if let Some(temperature) = $settings.temperature {
+            agent = agent.temperature(temperature);
+        }
+        AgentType::$variant(agent.build())
     }};
 }
 
@@ -44,8 +73,31 @@ macro_rules! impl_stream_chat {
     };
 }
 
+/// System prompt used when compacting old conversation into a summary
+const SUMMARY_PREAMBLE: &str = "You compress coding-assistant conversations. \
+Write a concise summary that preserves the user's goals, decisions made, files read or \
+modified (with paths), commands run and their outcomes, and any open tasks. \
+Reply with the summary only.";
+
+/// Maximum tokens for a generated summary
+const SUMMARY_MAX_TOKENS: u64 = 1024;
+
+macro_rules! impl_summarize {
+    ($agent:expr, $prompt:expr) => {
+        $agent
+            .model
+            .completion_request(Message::user($prompt.clone()))
+            .preamble(SUMMARY_PREAMBLE.to_string())
+            .max_tokens(SUMMARY_MAX_TOKENS)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?
+            .choice
+    };
+}
+
 /// Supported LLM providers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Provider {
     /// OpenAI (GPT-4, GPT-3.5, etc.)
     OpenAI,
@@ -57,6 +109,47 @@ pub enum Provider {
     DeepSeek,
     /// Local Ollama models
     Ollama,
+    /// Any server speaking the OpenAI Chat Completions API (gateways, llama.cpp, vLLM, ...)
+    OpenAICompatible,
+}
+
+impl Provider {
+    /// Name of the provider as written in `provider = "..."` in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai",
+            Provider::Anthropic => "anthropic",
+            Provider::Cohere => "cohere",
+            Provider::DeepSeek => "deepseek",
+            Provider::Ollama => "ollama",
+            Provider::OpenAICompatible => "openai_compatible",
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "openai" => Ok(Provider::OpenAI),
+            "anthropic" | "claude" => Ok(Provider::Anthropic),
+            "cohere" => Ok(Provider::Cohere),
+            "deepseek" => Ok(Provider::DeepSeek),
+            "ollama" => Ok(Provider::Ollama),
+            "openai_compatible" => Ok(Provider::OpenAICompatible),
+            _ => Err(anyhow::anyhow!(
+                "Unknown provider: {}. Supported providers: openai, anthropic, cohere, deepseek, ollama, openai_compatible",
+                s
+            )),
+        }
+    }
 }
 
 /// Agent enum to handle different provider types
@@ -74,6 +167,22 @@ pub enum AgentType {
     DeepSeek(Agent<deepseek::CompletionModel>),
     /// Ollama local agent
     Ollama(Agent<ollama::CompletionModel>),
+    /// OpenAI-compatible Chat Completions agent
+    OpenAICompatible(Agent<openai::completion::CompletionModel>),
+}
+
+impl AgentType {
+    /// Get the provider this agent talks to
+    pub fn provider(&self) -> Provider {
+        match self {
+            AgentType::OpenAI(_) => Provider::OpenAI,
+            AgentType::Anthropic(_) => Provider::Anthropic,
+            AgentType::Cohere(_) => Provider::Cohere,
+            AgentType::DeepSeek(_) => Provider::DeepSeek,
+            AgentType::Ollama(_) => Provider::Ollama,
+            AgentType::OpenAICompatible(_) => Provider::OpenAICompatible,
+        }
+    }
 }
 
 /// Complete agent instance with context and skill management
@@ -85,6 +194,11 @@ pub struct AgentInstance {
     pub context: Option<ContextManager>,
     pub skill_manager: Option<SkillManager>,
     pub tool_registry: ToolRegistry,
+    pub tool_log: ToolCallLog,
+    /// Background processes started by the `process` tool
+    pub process_manager: ProcessManager,
+    /// Snapshots of the files changed in each turn
+    pub checkpoints: CheckpointManager,
 }
 
 impl AgentInstance {
@@ -117,6 +231,21 @@ impl AgentInstance {
     pub fn tool_registry_mut(&mut self) -> &mut ToolRegistry {
         &mut self.tool_registry
     }
+
+    /// Get the log of tool calls made by this agent
+    pub fn tool_log(&self) -> &ToolCallLog {
+        &self.tool_log
+    }
+
+    /// Get the background processes started by this agent
+    pub fn process_manager(&self) -> &ProcessManager {
+        &self.process_manager
+    }
+
+    /// Get the checkpoints of the files changed by this agent
+    pub fn checkpoints(&self) -> &CheckpointManager {
+        &self.checkpoints
+    }
 }
 
 impl AgentInstance {
@@ -130,13 +259,14 @@ impl AgentInstance {
     ///
     /// # Returns
     ///
-    /// Returns a completion response after streaming to stdout
+    /// Returns the final response and every message produced during the turn,
+    /// including intermediate tool calls and tool results
     pub async fn stream_chat<H>(
         &self,
         input: &str,
         hook: H,
         history: Vec<rig::completion::Message>,
-    ) -> Result<rig::agent::FinalResponse>
+    ) -> Result<StreamedTurn>
     where
         H: rig::agent::StreamingPromptHook<openai::responses_api::ResponsesCompletionModel>
             + Clone
@@ -147,37 +277,32 @@ impl AgentInstance {
         H: rig::agent::StreamingPromptHook<cohere::CompletionModel> + Clone + 'static,
         H: rig::agent::StreamingPromptHook<deepseek::CompletionModel> + Clone + 'static,
         H: rig::agent::StreamingPromptHook<ollama::CompletionModel> + Clone + 'static,
+        H: rig::agent::StreamingPromptHook<openai::completion::CompletionModel> + Clone + 'static,
     {
         match &self.agent {
             AgentType::OpenAI(agent) => {
                 let mut stream = impl_stream_chat!(agent, input, hook.clone(), history.clone());
-                rig::agent::stream_to_stdout(&mut stream)
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e))
+                stream_to_stdout(&mut stream).await
             }
             AgentType::Anthropic(agent) => {
                 let mut stream = impl_stream_chat!(agent, input, hook.clone(), history.clone());
-                rig::agent::stream_to_stdout(&mut stream)
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e))
+                stream_to_stdout(&mut stream).await
             }
             AgentType::Cohere(agent) => {
                 let mut stream = impl_stream_chat!(agent, input, hook.clone(), history.clone());
-                rig::agent::stream_to_stdout(&mut stream)
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e))
+                stream_to_stdout(&mut stream).await
             }
             AgentType::DeepSeek(agent) => {
                 let mut stream = impl_stream_chat!(agent, input, hook.clone(), history.clone());
-                rig::agent::stream_to_stdout(&mut stream)
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e))
+                stream_to_stdout(&mut stream).await
             }
             AgentType::Ollama(agent) => {
                 let mut stream = impl_stream_chat!(agent, input, hook.clone(), history.clone());
-                rig::agent::stream_to_stdout(&mut stream)
-                    .await
-                    .map_err(|e| anyhow::anyhow!(e))
+                stream_to_stdout(&mut stream).await
+            }
+            AgentType::OpenAICompatible(agent) => {
+                let mut stream = impl_stream_chat!(agent, input, hook.clone(), history.clone());
+                stream_to_stdout(&mut stream).await
             }
         }
     }
@@ -188,10 +313,14 @@ impl AgentInstance {
     /// - Creates session hook automatically
     /// - Retrieves conversation history from context
     /// - Adds user message to context
+    /// - Starts a new checkpoint for the files changed in this turn
     /// - Streams the chat response
     /// - Saves assistant response to context
     /// - Auto-saves context to disk
     ///
+    /// If the turn fails, the user message and the tool calls that completed
+    /// are still saved, since they may already have changed files.
+    ///
     /// # Arguments
     ///
     /// * `input` - The user input message
@@ -220,12 +349,6 @@ impl AgentInstance {
     /// ```
     pub async fn chat(&mut self, input: &str) -> Result<rig::agent::FinalResponse> {
         use super::runtime::SessionIdHook;
-        use rig::completion::Message;
-
-        // 添加用户消息到上下文
-        if let Some(context) = self.context_mut() {
-            context.add_message(Message::user(input));
-        }
 
         // 创建会话钩子
         let session_id = self
@@ -234,11 +357,8 @@ impl AgentInstance {
             .unwrap_or_else(|| "unknown".to_string());
         let hook = SessionIdHook::new(session_id);
 
-        // 获取历史消息
-        let mut history = self
-            .context()
-            .map(|c| c.get_messages().to_vec())
-            .unwrap_or_default();
+        // 获取历史消息 (本轮用户输入作为 prompt 单独发送, 不放进历史)
+        let mut history = self.context().map(|c| c.history()).unwrap_or_default();
 
         // 如果有激活的 skill，在历史消息前添加 skill instructions
         if let Some(skill_manager) = &self.skill_manager {
@@ -251,19 +371,109 @@ impl AgentInstance {
             }
         }
 
+        // 本轮修改的文件归入新的检查点
+        self.checkpoints.begin_turn(input);
+
         // 执行流式聊天
-        let response = self.stream_chat(input, hook, history).await?;
+        let turn = match self.stream_chat(input, hook, history).await {
+            Ok(turn) => turn,
+            Err(e) => {
+                // 中途失败时保留用户输入和已完成的工具调用, 文件可能已被修改
+                let messages = e
+                    .downcast_ref::<InterruptedTurn>()
+                    .map(|turn| turn.messages.clone())
+                    .unwrap_or_default();
+                if let Err(save_error) = self.record_turn(input, messages) {
+                    eprintln!(
+                        "Warning: Failed to save the interrupted turn: {:#}",
+                        save_error
+                    );
+                }
+                return Err(e);
+            }
+        };
+        self.record_turn(input, turn.messages)?;
+
+        // 超出 token 预算时压缩最早的对话; 失败不影响本轮结果
+        if let Err(e) = self.compact_context().await {
+            eprintln!("Warning: Failed to compact conversation history: {:#}", e);
+        }
 
-        // 保存助手响应到上下文
+        let response = turn.response;
+        Ok(response)
+    }
+
+    /// Save the user input and the messages of its turn to the context
+    fn record_turn(&mut self, input: &str, messages: Vec<Message>) -> Result<()> {
+        // 保存用户输入以及整个多轮工具调用过程到上下文
         if let Some(context) = self.context_mut() {
-            let response_content = response.response();
-            context.add_message(Message::assistant(response_content));
+            context.add_message(Message::user(input));
+            for message in messages {
+                context.add_message(message);
+            }
 
             // 自动保存上下文
             context.save()?;
         }
+        Ok(())
+    }
 
-        Ok(response)
+    /// Summarize the oldest messages when the context exceeds its token budget
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if messages were compacted into the session summary
+    pub async fn compact_context(&mut self) -> Result<bool> {
+        let Some(context) = self.context() else {
+            return Ok(false);
+        };
+        let Some(count) = context.compaction_span() else {
+            return Ok(false);
+        };
+        let transcript = context.compaction_transcript(count);
+
+        let summary = self.summarize(&transcript).await?;
+        if summary.trim().is_empty() {
+            return Err(anyhow::anyhow!("Model returned an empty summary"));
+        }
+
+        if let Some(context) = self.context_mut() {
+            context.apply_summary(count, summary);
+            context.save()?;
+            println!(
+                "\n🗜️  Compacted {} earlier messages into a summary (~{} tokens in context)",
+                count,
+                context.estimated_tokens()
+            );
+        }
+        Ok(true)
+    }
+
+    /// Ask the model for a summary of a conversation transcript, without tools
+    async fn summarize(&self, transcript: &str) -> Result<String> {
+        let prompt = format!(
+            "Summarize the following conversation so it can replace it in your context.\n\n{}",
+            transcript
+        );
+
+        let choice = match &self.agent {
+            AgentType::OpenAI(agent) => impl_summarize!(agent, prompt),
+            AgentType::Anthropic(agent) => impl_summarize!(agent, prompt),
+            AgentType::Cohere(agent) => impl_summarize!(agent, prompt),
+            AgentType::DeepSeek(agent) => impl_summarize!(agent, prompt),
+            AgentType::Ollama(agent) => impl_summarize!(agent, prompt),
+            AgentType::OpenAICompatible(agent) => impl_summarize!(agent, prompt),
+        };
+
+        let text = choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        Ok(text)
     }
 }
 
@@ -284,13 +494,27 @@ impl AgentInstance {
 /// }
 /// ```
 pub struct AgentBuilder {
-    provider: Provider,
+    provider: Option<Provider>,
     api_key: String,
     model_name: String,
     plan_manager: PlanManager,
+    process_manager: ProcessManager,
+    checkpoints: CheckpointManager,
     context: Option<ContextManager>,
     skill_manager: Option<SkillManager>,
     tool_registry: ToolRegistry,
+    hooks: ToolHooks,
+    approval: Option<ApprovalGate>,
+    workspace: Option<Workspace>,
+    exec_options: ExecOptions,
+    patch_fuzz: usize,
+    read_max_bytes: usize,
+    command_interrupt: CommandInterrupt,
+    mcp_tools: Vec<McpTool>,
+    tool_filter: ToolFilter,
+    api_base: Option<String>,
+    temperature: Option<f64>,
+    max_tokens: u64,
 }
 
 impl AgentBuilder {
@@ -303,20 +527,45 @@ impl AgentBuilder {
     ///
     /// # Returns
     ///
-    /// Returns a builder that can be configured and built into an agent
+    /// Returns a builder that can be configured and built into an agent. Unless
+    /// [`with_provider`](Self::with_provider) is called, the provider is guessed
+    /// from the model name when building.
     pub fn new(api_key: String, model_name: String) -> Result<Self> {
-        let provider = Self::get_provider_from_model(&model_name)?;
         Ok(Self {
-            provider,
+            provider: None,
             api_key,
             model_name,
             plan_manager: PlanManager::new(),
+            process_manager: ProcessManager::new(),
+            checkpoints: CheckpointManager::new(),
             context: None,
             skill_manager: None,
             tool_registry: ToolRegistry::new(),
+            hooks: ToolHooks::default(),
+            approval: None,
+            workspace: None,
+            exec_options: ExecOptions::default(),
+            patch_fuzz: DEFAULT_FUZZ,
+            read_max_bytes: DEFAULT_MAX_READ_BYTES,
+            command_interrupt: CommandInterrupt::new(),
+            mcp_tools: Vec::new(),
+            tool_filter: ToolFilter::default(),
+            api_base: None,
+            temperature: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
         })
     }
 
+    /// Set the LLM provider explicitly instead of guessing it from the model name
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - The provider serving `model_name`
+    pub fn with_provider(mut self, provider: Provider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
     /// Set a custom plan manager for task management
     ///
     /// # Arguments
@@ -327,6 +576,30 @@ impl AgentBuilder {
         self
     }
 
+    /// Set the manager tracking the background processes of the `process` tool
+    ///
+    /// Share one manager to keep processes running when the agent is rebuilt.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - A ProcessManager; its processes are killed with [`ProcessManager::kill_all`]
+    pub fn with_process_manager(mut self, manager: ProcessManager) -> Self {
+        self.process_manager = manager;
+        self
+    }
+
+    /// Set the checkpoints the file tools snapshot files into before changing them
+    ///
+    /// Without this call the checkpoints are kept in memory only.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoints` - A CheckpointManager, usually opened in the session directory
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointManager) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+
     /// Set a context manager for conversation history
     ///
     /// # Arguments
@@ -347,6 +620,137 @@ impl AgentBuilder {
         self
     }
 
+    /// Set tool lifecycle hooks that run around every tool call
+    ///
+    /// # Arguments
+    ///
+    /// * `hooks` - `before_execute` / `after_execute` hooks, usually from `KotaConfig::hooks`
+    pub fn with_hooks(mut self, hooks: ToolHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Require approval for file edits, deletes and commands
+    ///
+    /// Without a gate every tool runs immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `gate` - Approval policy and prompt, usually built from `KotaConfig::approval`
+    pub fn with_approval(mut self, gate: ApprovalGate) -> Self {
+        self.approval = Some(gate);
+        self
+    }
+
+    /// Confine the file tools to a workspace root
+    ///
+    /// Relative paths are resolved against the root, and paths leaving it or
+    /// writing to a protected glob fail with `FileToolError::OutsideWorkspace`.
+    /// Without a workspace the file tools accept any path.
+    ///
+    /// # Arguments
+    ///
+    /// * `workspace` - Root directory and protected paths, usually from `KotaConfig::workspace_root`
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Set the default timeout, output limit and environment policy of `exec_cmd`
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Limits for every command, usually `KotaConfig::exec`
+    pub fn with_exec_options(mut self, options: ExecOptions) -> Self {
+        self.exec_options = options;
+        self
+    }
+
+    /// Set how many context lines an `edit_file` hunk may ignore at each end (default: 2)
+    ///
+    /// # Arguments
+    ///
+    /// * `fuzz` - Context lines dropped from each end of a hunk that does not match, usually `KotaConfig::edit_fuzz`
+    pub fn with_patch_fuzz(mut self, fuzz: usize) -> Self {
+        self.patch_fuzz = fuzz;
+        self
+    }
+
+    /// Set how many bytes of numbered content one `read_file` call returns (default: 50000)
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes` - Bytes after which a read is cut off, usually `KotaConfig::read_max_bytes`
+    pub fn with_read_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.read_max_bytes = max_bytes;
+        self
+    }
+
+    /// Share a handle that cancels the commands `exec_cmd` is running
+    ///
+    /// # Arguments
+    ///
+    /// * `interrupt` - Handle the caller triggers, e.g. on Ctrl-C
+    pub fn with_command_interrupt(mut self, interrupt: CommandInterrupt) -> Self {
+        self.command_interrupt = interrupt;
+        self
+    }
+
+    /// Expose the tools of every server connected to an MCP manager
+    ///
+    /// Tools are named `server__tool`. Servers must be added to the manager
+    /// before calling this method.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - An McpManager with connected servers
+    pub async fn with_mcp_manager(mut self, manager: &McpManager) -> Self {
+        self.mcp_tools = manager.agent_tools().await;
+        self
+    }
+
+    /// Restrict which tools are exposed to the model
+    ///
+    /// The filter applies to built-in, Lua and MCP tools by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Allow-list and deny-list of tool names, usually from `tools` in config
+    pub fn with_tool_filter(mut self, filter: ToolFilter) -> Self {
+        self.tool_filter = filter;
+        self
+    }
+
+    /// Send requests to a custom endpoint instead of the provider default
+    ///
+    /// # Arguments
+    ///
+    /// * `api_base` - Base URL of the API, e.g. an OpenAI-compatible gateway or a local server
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = Some(api_base.into());
+        self
+    }
+
+    /// Set the sampling temperature
+    ///
+    /// # Arguments
+    ///
+    /// * `temperature` - Sampling temperature, usually between 0.0 and 2.0
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the maximum number of tokens per completion (default: 4096)
+    ///
+    /// # Arguments
+    ///
+    /// * `max_tokens` - Maximum number of tokens the model may generate
+    pub fn with_max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
     /// Build the agent with the configured settings
     ///
     /// # Returns
@@ -355,6 +759,8 @@ impl AgentBuilder {
     pub fn build(mut self) -> Result<AgentInstance> {
         // Register built-in tools first
         self.register_builtin_tools();
+        let process_manager = self.process_manager.clone();
+        let checkpoints = self.checkpoints.clone();
 
         // Load Lua tools from .kota/tools/mod.lua
         let lua_tools = LuaToolLoader::load_tools().unwrap_or_else(|e| {
@@ -367,54 +773,91 @@ impl AgentBuilder {
             self.tool_registry.add(Box::new(tool));
         }
 
+        // Register MCP server tools to the registry
+        for tool in std::mem::take(&mut self.mcp_tools) {
+            self.tool_registry.add(Box::new(tool));
+        }
+
+        // Drop tools excluded by tools.enabled / tools.disabled
+        let unknown = self.tool_filter.unknown_names(&self.tool_registry.names());
+        if !unknown.is_empty() {
+            eprintln!(
+                "Warning: Unknown tools in tools.enabled/tools.disabled: {}",
+                unknown.join(", ")
+            );
+        }
+        self.tool_registry.apply_filter(&self.tool_filter);
+
+        let provider = match self.provider {
+            Some(provider) => provider,
+            None => Self::get_provider_from_model(&self.model_name)?,
+        };
+
+        let tool_log = ToolCallLog::new();
+        let tools = self.take_tools(&tool_log);
         let preamble = self.get_preamble();
 
-        let agent = match self.provider {
+        let agent = match provider {
             Provider::OpenAI => {
                 build_agent!(
-                    openai::Client::new(&self.api_key),
+                    build_client!(openai::Client, &self.api_key, &self.api_base),
                     &self.model_name,
                     preamble,
-                    self.tool_registry.take_all(),
+                    tools,
+                    self,
                     OpenAI
                 )
             }
             Provider::Anthropic => {
                 build_agent!(
-                    anthropic::Client::new(&self.api_key),
+                    build_client!(anthropic::Client, &self.api_key, &self.api_base),
                     &self.model_name,
                     preamble,
-                    self.tool_registry.take_all(),
+                    tools,
+                    self,
                     Anthropic
                 )
             }
             Provider::Cohere => {
                 build_agent!(
-                    cohere::Client::new(&self.api_key),
+                    build_client!(cohere::Client, &self.api_key, &self.api_base),
                     &self.model_name,
                     preamble,
-                    self.tool_registry.take_all(),
+                    tools,
+                    self,
                     Cohere
                 )
             }
             Provider::DeepSeek => {
                 build_agent!(
-                    deepseek::Client::new(&self.api_key),
-                    DEEPSEEK_CHAT,
+                    build_client!(deepseek::Client, &self.api_key, &self.api_base),
+                    &self.model_name,
                     preamble,
-                    self.tool_registry.take_all(),
+                    tools,
+                    self,
                     DeepSeek
                 )
             }
             Provider::Ollama => {
                 build_agent!(
-                    ollama::Client::new(rig::client::Nothing),
+                    build_client!(ollama::Client, rig::client::Nothing, &self.api_base),
                     &self.model_name,
                     preamble,
-                    self.tool_registry.take_all(),
+                    tools,
+                    self,
                     Ollama
                 )
             }
+            Provider::OpenAICompatible => {
+                build_agent!(
+                    build_client!(openai::CompletionsClient, &self.api_key, &self.api_base),
+                    &self.model_name,
+                    preamble,
+                    tools,
+                    self,
+                    OpenAICompatible
+                )
+            }
         };
 
         Ok(AgentInstance {
@@ -422,23 +865,78 @@ impl AgentBuilder {
             context: self.context,
             skill_manager: self.skill_manager,
             tool_registry: self.tool_registry,
+            tool_log,
+            process_manager,
+            checkpoints,
         })
     }
 
+    /// Take all registered tools, wrapping them with the approval gate and the
+    /// configured hooks and recording every call in `tool_log`
+    fn take_tools(&mut self, tool_log: &ToolCallLog) -> Vec<Box<dyn ToolDyn>> {
+        let hooks = (!self.hooks.is_empty()).then(|| Arc::new(self.hooks.clone()));
+        // Path patterns match against the root the file tools resolve paths in
+        let approval = self.approval.as_ref().map(|gate| match &self.workspace {
+            Some(workspace) => gate.with_workspace(workspace.clone()),
+            None => gate.clone(),
+        });
+
+        self.tool_registry
+            .take_all()
+            .into_iter()
+            .map(|tool| match &approval {
+                Some(gate) if gate.config().is_gated(&tool.name()) => {
+                    Box::new(ApprovalTool::new(tool, gate.clone())) as Box<dyn ToolDyn>
+                }
+                _ => tool,
+            })
+            .map(|tool| match &hooks {
+                Some(hooks) => Box::new(HookedTool::new(tool, hooks.clone())) as Box<dyn ToolDyn>,
+                None => tool,
+            })
+            .map(|tool| Box::new(LoggedTool::new(tool, tool_log.clone())) as Box<dyn ToolDyn>)
+            .collect()
+    }
+
     /// Register all built-in tools to the tool registry
     fn register_builtin_tools(&mut self) {
-        self.tool_registry.add(Box::new(WrappedReadFileTool::new()));
-        self.tool_registry
-            .add(Box::new(WrappedWriteFileTool::new()));
-        self.tool_registry.add(Box::new(WrappedEditFileTool::new()));
-        self.tool_registry
-            .add(Box::new(WrappedDeleteFileTool::new()));
-        self.tool_registry
-            .add(Box::new(WrappedExecuteBashCommandTool::new()));
+        let mut read_file = WrappedReadFileTool::new().with_max_bytes(self.read_max_bytes);
+        let mut write_file = WrappedWriteFileTool::new().with_checkpoints(self.checkpoints.clone());
+        let mut edit_file = WrappedEditFileTool::new()
+            .with_checkpoints(self.checkpoints.clone())
+            .with_fuzz(self.patch_fuzz);
+        let mut delete_file =
+            WrappedDeleteFileTool::new().with_checkpoints(self.checkpoints.clone());
+        let mut create_directory = WrappedCreateDirectoryTool::new();
+        let mut process = WrappedProcessTool::new(self.process_manager.clone())
+            .with_options(self.exec_options.clone());
+        let mut exec_cmd = WrappedExecuteBashCommandTool::new()
+            .with_options(self.exec_options.clone())
+            .with_interrupt(self.command_interrupt.clone());
+        if let Some(workspace) = &self.workspace {
+            read_file = read_file.with_workspace(workspace.clone());
+            write_file = write_file.with_workspace(workspace.clone());
+            edit_file = edit_file.with_workspace(workspace.clone());
+            delete_file = delete_file.with_workspace(workspace.clone());
+            create_directory = create_directory.with_workspace(workspace.clone());
+            exec_cmd = exec_cmd.with_workspace(workspace.clone());
+            process = process.with_workspace(workspace.clone());
+        }
+
+        self.tool_registry.add(Box::new(read_file));
+        self.tool_registry.add(Box::new(write_file));
+        self.tool_registry.add(Box::new(edit_file));
+        self.tool_registry.add(Box::new(delete_file));
+        self.tool_registry.add(Box::new(exec_cmd));
+        self.tool_registry.add(Box::new(process));
+        self.tool_registry.add(Box::new(
+            WrappedShellSessionTool::new()
+                .with_options(self.exec_options.clone())
+                .with_interrupt(self.command_interrupt.clone()),
+        ));
         self.tool_registry
             .add(Box::new(WrappedScanCodebaseTool::new()));
-        self.tool_registry
-            .add(Box::new(WrappedCreateDirectoryTool::new()));
+        self.tool_registry.add(Box::new(create_directory));
         self.tool_registry
             .add(Box::new(WrappedGrepSearchTool::new()));
         self.tool_registry.add(Box::new(WrappedUpdatePlanTool::new(
@@ -446,10 +944,19 @@ impl AgentBuilder {
         )));
     }
 
+    /// Guess the provider from well-known model name prefixes
+    ///
+    /// Only used when no provider was set with [`with_provider`](Self::with_provider).
     fn get_provider_from_model(model_name: &str) -> Result<Provider> {
         match model_name.to_lowercase().as_str() {
             // OpenAI models
-            name if name.starts_with("gpt-") || name.starts_with("o1-") => Ok(Provider::OpenAI),
+            name if name.starts_with("gpt-")
+                || name.starts_with("o1")
+                || name.starts_with("o3")
+                || name.starts_with("o4") =>
+            {
+                Ok(Provider::OpenAI)
+            }
 
             // Anthropic models
             name if name.starts_with("claude-") => Ok(Provider::Anthropic),
@@ -462,7 +969,8 @@ impl AgentBuilder {
             "ollama" | "local" => Ok(Provider::Ollama),
 
             _ => Err(anyhow::anyhow!(
-                "Unknown model: {}. Please specify a supported model.",
+                "Cannot infer the provider for model: {}. Please set `provider` in config \
+                (openai, anthropic, cohere, deepseek, ollama, openai_compatible).",
                 model_name
             )),
         }
@@ -0,0 +1,196 @@
+//! Streaming output and conversation recording for multi-turn agent runs
+
+use anyhow::Result;
+use futures::StreamExt;
+use rig::agent::{FinalResponse, MultiTurnStreamItem, StreamingResult};
+use rig::completion::message::{AssistantContent, Reasoning, ToolCall, ToolResult, UserContent};
+use rig::completion::Message;
+use rig::streaming::{StreamedAssistantContent, StreamedUserContent};
+use rig::OneOrMany;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+
+/// Result of one streamed user turn
+pub struct StreamedTurn {
+    /// Final text and aggregated token usage
+    pub response: FinalResponse,
+    /// Messages produced during the turn, in order: assistant messages with
+    /// reasoning, text and tool calls, followed by the tool results sent back.
+    /// The user prompt itself is not included.
+    pub messages: Vec<Message>,
+}
+
+/// A turn that failed after part of it was streamed, e.g. on a provider error
+///
+/// Carries the messages recorded before the failure, so the caller can keep
+/// the tool calls that already ran in history.
+#[derive(Debug)]
+pub struct InterruptedTurn {
+    /// Messages from [`TurnRecorder::finish_interrupted`]
+    pub messages: Vec<Message>,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for InterruptedTurn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{:#}", self.error)
+        } else {
+            write!(f, "{}", self.error)
+        }
+    }
+}
+
+impl std::error::Error for InterruptedTurn {}
+
+/// Rebuilds the conversation from the items of a multi-turn stream
+///
+/// Every model round trip becomes one assistant message, followed by one user
+/// message per tool result, which is the shape providers expect in history.
+#[derive(Default)]
+pub struct TurnRecorder {
+    messages: Vec<Message>,
+    reasoning: Option<Reasoning>,
+    text: String,
+    tool_calls: Vec<ToolCall>,
+    tool_results: Vec<ToolResult>,
+}
+
+impl TurnRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a single stream item
+    pub fn record<R>(&mut self, item: &MultiTurnStreamItem<R>) {
+        match item {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text)) => {
+                self.start_round_trip();
+                self.text.push_str(&text.text);
+            }
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Reasoning(
+                reasoning,
+            )) => {
+                self.start_round_trip();
+                match &mut self.reasoning {
+                    Some(existing) => existing.reasoning.extend(reasoning.reasoning.clone()),
+                    None => self.reasoning = Some(reasoning.clone()),
+                }
+            }
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall {
+                tool_call,
+                ..
+            }) => {
+                self.start_round_trip();
+                self.tool_calls.push(tool_call.clone());
+            }
+            MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult {
+                tool_result,
+                ..
+            }) => {
+                self.tool_results.push(tool_result.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Finish recording and return the messages of the turn
+    pub fn finish(mut self) -> Vec<Message> {
+        self.flush();
+        self.messages
+    }
+
+    /// Finish recording a turn that stopped with an error
+    ///
+    /// Tool calls whose result never arrived are dropped, since providers
+    /// reject a history with unanswered tool calls.
+    pub fn finish_interrupted(mut self) -> Vec<Message> {
+        let answered: HashSet<&str> = self
+            .tool_results
+            .iter()
+            .map(|result| result.id.as_str())
+            .collect();
+        let tool_calls = std::mem::take(&mut self.tool_calls)
+            .into_iter()
+            .filter(|call| answered.contains(call.id.as_str()))
+            .collect();
+        self.tool_calls = tool_calls;
+        self.finish()
+    }
+
+    /// Assistant output after tool results belongs to the next model round trip
+    fn start_round_trip(&mut self) {
+        if !self.tool_results.is_empty() {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut content = Vec::new();
+        if let Some(reasoning) = self.reasoning.take() {
+            content.push(AssistantContent::Reasoning(reasoning));
+        }
+        if !self.text.is_empty() {
+            content.push(AssistantContent::text(std::mem::take(&mut self.text)));
+        }
+        content.extend(self.tool_calls.drain(..).map(AssistantContent::ToolCall));
+
+        if let Ok(content) = OneOrMany::many(content) {
+            self.messages.push(Message::Assistant { id: None, content });
+        }
+
+        for tool_result in self.tool_results.drain(..) {
+            self.messages.push(Message::User {
+                content: OneOrMany::one(UserContent::ToolResult(tool_result)),
+            });
+        }
+    }
+}
+
+/// Print a multi-turn stream to stdout while recording the conversation
+///
+/// Behaves like [`rig::agent::stream_to_stdout`], but stops at the first stream
+/// error instead of swallowing it. The error is an [`InterruptedTurn`] holding
+/// what was recorded until then.
+pub async fn stream_to_stdout<R>(stream: &mut StreamingResult<R>) -> Result<StreamedTurn> {
+    let mut response = FinalResponse::empty();
+    let mut recorder = TurnRecorder::new();
+
+    print!("Response: ");
+    while let Some(item) = stream.next().await {
+        let item = match item {
+            Ok(item) => item,
+            Err(e) => {
+                return Err(InterruptedTurn {
+                    messages: recorder.finish_interrupted(),
+                    error: anyhow::anyhow!(e),
+                }
+                .into())
+            }
+        };
+        recorder.record(&item);
+
+        match item {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text)) => {
+                print!("{}", text.text);
+                std::io::stdout().flush()?;
+            }
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Reasoning(
+                reasoning,
+            )) => {
+                print!("{}", reasoning.reasoning.join("\n"));
+                std::io::stdout().flush()?;
+            }
+            MultiTurnStreamItem::FinalResponse(res) => {
+                response = res;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(StreamedTurn {
+        response,
+        messages: recorder.finish(),
+    })
+}
@@ -0,0 +1,307 @@
+use super::context::ContextManager;
+use super::tools::FileToolError;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// 一个文件在某轮对话首次被修改前的状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// 文件路径 (工具实际写入的路径)
+    pub path: PathBuf,
+    /// 保存原内容的 blob 文件名; `None` 表示修改前文件不存在
+    pub blob: Option<String>,
+}
+
+/// 一轮用户输入期间被工具修改的所有文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub id: usize,
+    /// 触发这轮修改的用户输入
+    pub prompt: String,
+    pub created_at: String,
+    pub files: Vec<FileSnapshot>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckpointIndex {
+    next_id: usize,
+    checkpoints: Vec<Checkpoint>,
+}
+
+#[derive(Debug, Default)]
+struct CheckpointState {
+    /// `None` 时只保存在内存中
+    dir: Option<PathBuf>,
+    index: CheckpointIndex,
+    /// 下一次快照所属轮次的用户输入
+    pending_prompt: Option<String>,
+    /// 本轮是否已经创建了检查点
+    turn_open: bool,
+    /// 内存模式下的 blob 内容
+    blobs: Vec<(String, Vec<u8>)>,
+}
+
+/// 文件检查点管理器
+///
+/// 文件工具在修改文件前调用 [`snapshot`](Self::snapshot), 同一轮对话中的快照归入
+/// 同一个检查点, 之后可以用 [`undo`](Self::undo) 或 [`restore`](Self::restore) 还原。
+/// 克隆共享同一份状态。
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointManager {
+    state: Arc<Mutex<CheckpointState>>,
+}
+
+impl CheckpointManager {
+    /// 创建只保存在内存中的检查点管理器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 打开会话的检查点, 保存在 `storage_dir/checkpoints/<session_id>/`
+    ///
+    /// # Arguments
+    ///
+    /// * `storage_dir` - 会话目录, 通常与 `ContextManager` 相同
+    /// * `session_id` - 会话 ID
+    pub fn open<P: AsRef<Path>>(storage_dir: P, session_id: &str) -> Result<Self> {
+        let manager = Self::new();
+        manager.set_dir(Some(Self::session_dir(storage_dir.as_ref(), session_id)?))?;
+        Ok(manager)
+    }
+
+    /// 切换到另一个会话的检查点 (例如 `/load` 之后)
+    pub fn switch_session<P: AsRef<Path>>(&self, storage_dir: P, session_id: &str) -> Result<()> {
+        self.set_dir(Some(Self::session_dir(storage_dir.as_ref(), session_id)?))
+    }
+
+    /// 删除会话的所有检查点
+    ///
+    /// # Returns
+    ///
+    /// 如果存在检查点目录返回 `true`
+    pub fn delete_session<P: AsRef<Path>>(storage_dir: P, session_id: &str) -> Result<bool> {
+        let dir = Self::session_dir(storage_dir.as_ref(), session_id)?;
+        if !dir.exists() {
+            return Ok(false);
+        }
+        // 递归删除前确认目录确实位于 checkpoints 目录之下 (防止符号链接逃逸)
+        let root = storage_dir.as_ref().join("checkpoints").canonicalize()?;
+        let resolved = dir.canonicalize()?;
+        if resolved == root || !resolved.starts_with(&root) {
+            anyhow::bail!("Refusing to delete {:?}: outside {:?}", resolved, root);
+        }
+        fs::remove_dir_all(&dir)
+            .with_context(|| format!("Failed to delete checkpoints: {:?}", dir))?;
+        Ok(true)
+    }
+
+    // 检查点放在子目录中, 避免被 `ContextManager::list_sessions` 当作会话文件
+    fn session_dir(storage_dir: &Path, session_id: &str) -> Result<PathBuf> {
+        ContextManager::validate_session_id(session_id)?;
+        Ok(storage_dir.join("checkpoints").join(session_id))
+    }
+
+    // 持有锁的线程 panic 后状态可能只改了一半, 返回错误而不是让调用方也 panic
+    fn lock(&self) -> std::io::Result<MutexGuard<'_, CheckpointState>> {
+        self.state
+            .lock()
+            .map_err(|_| std::io::Error::other("checkpoint state is unavailable after a panic"))
+    }
+
+    fn set_dir(&self, dir: Option<PathBuf>) -> Result<()> {
+        let index = match &dir {
+            Some(dir) => Self::load_index(dir)?,
+            None => CheckpointIndex::default(),
+        };
+        let mut state = self.lock()?;
+        *state = CheckpointState {
+            dir,
+            index,
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    fn load_index(dir: &Path) -> Result<CheckpointIndex> {
+        let path = dir.join("index.json");
+        if !path.exists() {
+            return Ok(CheckpointIndex::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read checkpoints: {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse checkpoints: {:?}", path))
+    }
+
+    /// 开始新一轮对话, 之后的快照归入新的检查点
+    ///
+    /// 检查点在第一次快照时才创建, 没有修改文件的轮次不会留下检查点。
+    pub fn begin_turn(&self, prompt: &str) {
+        if let Ok(mut state) = self.lock() {
+            state.pending_prompt = Some(prompt.to_string());
+            state.turn_open = false;
+        }
+    }
+
+    /// 在工具修改文件前保存其当前内容
+    ///
+    /// 同一轮中每个文件只保存第一次修改前的内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - 将被写入、编辑或删除的文件
+    pub fn snapshot<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        // 相对路径按当前目录展开, 还原时不受之后切换目录影响
+        let path = std::path::absolute(path)?;
+        let mut state = self.lock()?;
+
+        if !state.turn_open {
+            let id = state.index.next_id.max(1);
+            let prompt = state.pending_prompt.take().unwrap_or_default();
+            state.index.next_id = id + 1;
+            state.index.checkpoints.push(Checkpoint {
+                id,
+                prompt,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                files: Vec::new(),
+            });
+            state.turn_open = true;
+        }
+
+        let checkpoint = state.index.checkpoints.last().unwrap();
+        if checkpoint.files.iter().any(|file| file.path == path) {
+            return Ok(());
+        }
+        let blob_name = format!("{}-{}", checkpoint.id, checkpoint.files.len());
+
+        let blob = match fs::read(&path) {
+            Ok(content) => {
+                match &state.dir {
+                    Some(dir) => {
+                        fs::create_dir_all(dir)?;
+                        fs::write(dir.join(&blob_name), content)?;
+                    }
+                    None => state.blobs.push((blob_name.clone(), content)),
+                }
+                Some(blob_name)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let checkpoint = state.index.checkpoints.last_mut().unwrap();
+        checkpoint.files.push(FileSnapshot { path, blob });
+        Self::save_index(&state)
+    }
+
+    fn save_index(state: &CheckpointState) -> std::io::Result<()> {
+        let Some(dir) = &state.dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        let content = serde_json::to_string_pretty(&state.index)?;
+        fs::write(dir.join("index.json"), content)
+    }
+
+    /// 列出所有检查点, 从旧到新
+    pub fn list(&self) -> Result<Vec<Checkpoint>> {
+        Ok(self.lock()?.index.checkpoints.clone())
+    }
+
+    /// 还原最近一个检查点并删除它
+    ///
+    /// # Returns
+    ///
+    /// 被还原的检查点; 没有检查点时返回 `None`
+    pub fn undo(&self) -> Result<Option<Checkpoint>> {
+        let Some(id) = self.list()?.last().map(|checkpoint| checkpoint.id) else {
+            return Ok(None);
+        };
+        Ok(self.restore(id)?.pop())
+    }
+
+    /// 把文件还原到检查点 `id` 之前的状态
+    ///
+    /// 从最新的检查点开始依次还原, `id` 及之后的检查点都会被删除。
+    ///
+    /// # Returns
+    ///
+    /// 被还原的检查点, 从新到旧
+    pub fn restore(&self, id: usize) -> Result<Vec<Checkpoint>> {
+        let mut state = self.lock()?;
+        let Some(position) = state.index.checkpoints.iter().position(|c| c.id == id) else {
+            return Err(anyhow::anyhow!("Checkpoint {} not found", id));
+        };
+
+        let mut restored = Vec::new();
+        while state.index.checkpoints.len() > position {
+            let checkpoint = state.index.checkpoints.last().unwrap().clone();
+            for file in checkpoint.files.iter().rev() {
+                Self::restore_file(&state, file)?;
+            }
+            for file in &checkpoint.files {
+                Self::remove_blob(&mut state, file);
+            }
+            state.index.checkpoints.pop();
+            // 保存每一步, 中途失败时索引和文件保持一致
+            Self::save_index(&state)?;
+            restored.push(checkpoint);
+        }
+        state.turn_open = false;
+        Ok(restored)
+    }
+
+    fn restore_file(state: &CheckpointState, file: &FileSnapshot) -> Result<()> {
+        let Some(blob) = &file.blob else {
+            // 修改前不存在的文件直接删除
+            if file.path.is_file() {
+                fs::remove_file(&file.path)
+                    .with_context(|| format!("Failed to remove {:?}", file.path))?;
+            }
+            return Ok(());
+        };
+
+        let content = match &state.dir {
+            Some(dir) => fs::read(dir.join(blob))
+                .with_context(|| format!("Missing snapshot of {:?}", file.path))?,
+            None => state
+                .blobs
+                .iter()
+                .find(|(name, _)| name == blob)
+                .map(|(_, content)| content.clone())
+                .ok_or_else(|| anyhow::anyhow!("Missing snapshot of {:?}", file.path))?,
+        };
+        if let Some(parent) = file.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(&file.path, content).with_context(|| format!("Failed to restore {:?}", file.path))
+    }
+
+    fn remove_blob(state: &mut CheckpointState, file: &FileSnapshot) {
+        let Some(blob) = &file.blob else {
+            return;
+        };
+        match &state.dir {
+            Some(dir) => {
+                let _ = fs::remove_file(dir.join(blob));
+            }
+            None => state.blobs.retain(|(name, _)| name != blob),
+        }
+    }
+}
+
+/// 文件工具在修改 `path` 前保存快照; 没有检查点管理器时什么也不做
+pub(crate) fn snapshot_before_write(
+    checkpoints: Option<&CheckpointManager>,
+    path: &str,
+) -> Result<(), FileToolError> {
+    if let Some(checkpoints) = checkpoints {
+        checkpoints.snapshot(path)?;
+    }
+    Ok(())
+}
@@ -1,5 +1,10 @@
 use anyhow::{Context, Result};
+use rig::completion::message::{
+    AssistantContent, Audio, Document, Image, Reasoning, ToolCall, ToolFunction, ToolResult,
+    ToolResultContent, UserContent, Video,
+};
 use rig::completion::Message;
+use rig::OneOrMany;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -11,6 +16,19 @@ pub struct ContextManager {
     session_id: String,
     messages: Vec<Message>,
     max_messages: usize,
+    token_budget: Option<usize>,
+    summary: Option<ContextSummary>,
+}
+
+/// 被压缩的早期对话的摘要
+///
+/// 由模型生成, 在发送历史时作为第一条消息, 并保存在会话文件的 `summary` 字段中
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContextSummary {
+    pub text: String,
+    /// 到目前为止被压缩进摘要的消息总数
+    pub summarized_messages: usize,
+    pub created_at: String,
 }
 
 /// 会话元数据
@@ -22,14 +40,429 @@ pub struct SessionMetadata {
     pub message_count: usize,
 }
 
+/// 当前会话文件格式版本
+///
+/// - 1: 每条消息压平为 `role` + 一个字符串 (无 `version` 字段)
+/// - 2: 无损保存所有 `UserContent`/`AssistantContent`, 包括工具调用和工具结果
+/// - 3: 增加 `summary` 字段, 保存被压缩的早期对话
+pub const SESSION_FORMAT_VERSION: u32 = 3;
+
+/// 单条消息的固定开销 (角色、分隔符等)
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// 图片等媒体内容的估算 token 数
+const MEDIA_TOKENS: usize = 1000;
+
+/// 压缩后保留的历史占 token 预算的比例 (分母)
+const COMPACTION_TARGET_DIVISOR: usize = 2;
+
+/// 渲染压缩用的对话记录时, 每段工具输出保留的最大字符数
+const TRANSCRIPT_TOOL_OUTPUT_CHARS: usize = 2000;
+
 /// 持久化的会话数据
 #[derive(Debug, Serialize, Deserialize)]
 struct SessionData {
+    pub version: u32,
     pub metadata: SessionMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ContextSummary>,
+    pub messages: Vec<StoredMessage>,
+}
+
+/// 版本 1 的会话文件, 仅用于迁移
+#[derive(Debug, Deserialize)]
+struct LegacySessionData {
     pub messages: Vec<SerializableMessage>,
 }
 
+/// 只读取元数据, 兼容所有版本
+#[derive(Debug, Deserialize)]
+struct SessionHeader {
+    pub metadata: SessionMetadata,
+}
+
+/// 会话文件中的消息 (格式版本 2)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum StoredMessage {
+    User {
+        content: Vec<StoredUserContent>,
+    },
+    Assistant {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        content: Vec<StoredAssistantContent>,
+    },
+}
+
+/// 用户消息内容
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoredUserContent {
+    Text {
+        text: String,
+    },
+    ToolResult {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        content: Vec<StoredToolResultContent>,
+    },
+    Image {
+        image: Image,
+    },
+    Audio {
+        audio: Audio,
+    },
+    Video {
+        video: Video,
+    },
+    Document {
+        document: Document,
+    },
+}
+
+/// 工具结果内容
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoredToolResultContent {
+    Text { text: String },
+    Image { image: Image },
+}
+
+/// 助手消息内容
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoredAssistantContent {
+    Text {
+        text: String,
+    },
+    ToolCall {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
+        name: String,
+        arguments: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        additional_params: Option<serde_json::Value>,
+    },
+    Reasoning {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        reasoning: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    Image {
+        image: Image,
+    },
+}
+
+impl From<&Message> for StoredMessage {
+    fn from(msg: &Message) -> Self {
+        match msg {
+            Message::User { content } => StoredMessage::User {
+                content: content.iter().map(StoredUserContent::from).collect(),
+            },
+            Message::Assistant { id, content } => StoredMessage::Assistant {
+                id: id.clone(),
+                content: content.iter().map(StoredAssistantContent::from).collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<StoredMessage> for Message {
+    type Error = anyhow::Error;
+
+    fn try_from(msg: StoredMessage) -> Result<Self> {
+        match msg {
+            StoredMessage::User { content } => Ok(Message::User {
+                content: OneOrMany::many(content.into_iter().map(UserContent::from))
+                    .map_err(|_| anyhow::anyhow!("User message has no content"))?,
+            }),
+            StoredMessage::Assistant { id, content } => Ok(Message::Assistant {
+                id,
+                content: OneOrMany::many(content.into_iter().map(AssistantContent::from))
+                    .map_err(|_| anyhow::anyhow!("Assistant message has no content"))?,
+            }),
+        }
+    }
+}
+
+impl From<&UserContent> for StoredUserContent {
+    fn from(content: &UserContent) -> Self {
+        match content {
+            UserContent::Text(text) => StoredUserContent::Text {
+                text: text.text.clone(),
+            },
+            UserContent::ToolResult(result) => StoredUserContent::ToolResult {
+                id: result.id.clone(),
+                call_id: result.call_id.clone(),
+                content: result
+                    .content
+                    .iter()
+                    .map(|c| match c {
+                        ToolResultContent::Text(text) => StoredToolResultContent::Text {
+                            text: text.text.clone(),
+                        },
+                        ToolResultContent::Image(image) => StoredToolResultContent::Image {
+                            image: image.clone(),
+                        },
+                    })
+                    .collect(),
+            },
+            UserContent::Image(image) => StoredUserContent::Image {
+                image: image.clone(),
+            },
+            UserContent::Audio(audio) => StoredUserContent::Audio {
+                audio: audio.clone(),
+            },
+            UserContent::Video(video) => StoredUserContent::Video {
+                video: video.clone(),
+            },
+            UserContent::Document(document) => StoredUserContent::Document {
+                document: document.clone(),
+            },
+        }
+    }
+}
+
+impl From<StoredUserContent> for UserContent {
+    fn from(content: StoredUserContent) -> Self {
+        match content {
+            StoredUserContent::Text { text } => UserContent::text(text),
+            StoredUserContent::ToolResult {
+                id,
+                call_id,
+                content,
+            } => {
+                let content = content
+                    .into_iter()
+                    .map(|c| match c {
+                        StoredToolResultContent::Text { text } => ToolResultContent::text(text),
+                        StoredToolResultContent::Image { mut image } => {
+                            drop_empty_params(&mut image.additional_params);
+                            ToolResultContent::Image(image)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                // 工具结果至少需要一项内容, 空结果按空文本恢复
+                let content = OneOrMany::many(content)
+                    .unwrap_or_else(|_| OneOrMany::one(ToolResultContent::text("")));
+                UserContent::ToolResult(ToolResult {
+                    id,
+                    call_id,
+                    content,
+                })
+            }
+            StoredUserContent::Image { mut image } => {
+                drop_empty_params(&mut image.additional_params);
+                UserContent::Image(image)
+            }
+            StoredUserContent::Audio { mut audio } => {
+                drop_empty_params(&mut audio.additional_params);
+                UserContent::Audio(audio)
+            }
+            StoredUserContent::Video { mut video } => {
+                drop_empty_params(&mut video.additional_params);
+                UserContent::Video(video)
+            }
+            StoredUserContent::Document { mut document } => {
+                drop_empty_params(&mut document.additional_params);
+                UserContent::Document(document)
+            }
+        }
+    }
+}
+
+impl From<&AssistantContent> for StoredAssistantContent {
+    fn from(content: &AssistantContent) -> Self {
+        match content {
+            AssistantContent::Text(text) => StoredAssistantContent::Text {
+                text: text.text.clone(),
+            },
+            AssistantContent::ToolCall(call) => StoredAssistantContent::ToolCall {
+                id: call.id.clone(),
+                call_id: call.call_id.clone(),
+                name: call.function.name.clone(),
+                arguments: call.function.arguments.clone(),
+                signature: call.signature.clone(),
+                additional_params: call.additional_params.clone(),
+            },
+            AssistantContent::Reasoning(reasoning) => StoredAssistantContent::Reasoning {
+                id: reasoning.id.clone(),
+                reasoning: reasoning.reasoning.clone(),
+                signature: reasoning.signature.clone(),
+            },
+            AssistantContent::Image(image) => StoredAssistantContent::Image {
+                image: image.clone(),
+            },
+        }
+    }
+}
+
+impl From<StoredAssistantContent> for AssistantContent {
+    fn from(content: StoredAssistantContent) -> Self {
+        match content {
+            StoredAssistantContent::Text { text } => AssistantContent::text(text),
+            StoredAssistantContent::ToolCall {
+                id,
+                call_id,
+                name,
+                arguments,
+                signature,
+                additional_params,
+            } => AssistantContent::ToolCall(ToolCall {
+                id,
+                call_id,
+                function: ToolFunction::new(name, arguments),
+                signature,
+                additional_params,
+            }),
+            StoredAssistantContent::Reasoning {
+                id,
+                reasoning,
+                signature,
+            } => AssistantContent::Reasoning(
+                Reasoning::multi(reasoning)
+                    .optional_id(id)
+                    .with_signature(signature),
+            ),
+            StoredAssistantContent::Image { mut image } => {
+                drop_empty_params(&mut image.additional_params);
+                AssistantContent::Image(image)
+            }
+        }
+    }
+}
+
+/// 是否为用户输入的消息 (而不是回传给模型的工具结果)
+fn is_user_prompt(message: &Message) -> bool {
+    match message {
+        Message::User { content } => !content
+            .iter()
+            .any(|c| matches!(c, UserContent::ToolResult(_))),
+        Message::Assistant { .. } => false,
+    }
+}
+
+/// 估算文本的 token 数 (约 4 个字符一个 token)
+fn estimate_text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// 估算单条消息的 token 数
+///
+/// 只是近似值, 用于决定何时压缩历史, 不依赖具体模型的分词器
+pub fn estimate_tokens(message: &Message) -> usize {
+    let content_tokens: usize = match message {
+        Message::User { content } => content
+            .iter()
+            .map(|c| match c {
+                UserContent::Text(text) => estimate_text_tokens(&text.text),
+                UserContent::ToolResult(result) => result
+                    .content
+                    .iter()
+                    .map(|c| match c {
+                        ToolResultContent::Text(text) => estimate_text_tokens(&text.text),
+                        ToolResultContent::Image(_) => MEDIA_TOKENS,
+                    })
+                    .sum(),
+                _ => MEDIA_TOKENS,
+            })
+            .sum(),
+        Message::Assistant { content, .. } => content
+            .iter()
+            .map(|c| match c {
+                AssistantContent::Text(text) => estimate_text_tokens(&text.text),
+                AssistantContent::ToolCall(call) => {
+                    estimate_text_tokens(&call.function.name)
+                        + estimate_text_tokens(&call.function.arguments.to_string())
+                }
+                AssistantContent::Reasoning(reasoning) => reasoning
+                    .reasoning
+                    .iter()
+                    .map(|r| estimate_text_tokens(r))
+                    .sum(),
+                AssistantContent::Image(_) => MEDIA_TOKENS,
+            })
+            .sum(),
+    };
+    content_tokens + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// 把消息渲染成纯文本对话记录, 用于让模型生成摘要
+///
+/// 工具输出会被截断, 以免摘要请求本身超出上下文
+pub fn render_transcript(messages: &[Message]) -> String {
+    let mut lines = Vec::new();
+    for message in messages {
+        match message {
+            Message::User { content } => {
+                for c in content.iter() {
+                    match c {
+                        UserContent::Text(text) => lines.push(format!("USER: {}", text.text)),
+                        UserContent::ToolResult(result) => {
+                            let output = result
+                                .content
+                                .iter()
+                                .map(|c| match c {
+                                    ToolResultContent::Text(text) => text.text.clone(),
+                                    ToolResultContent::Image(_) => "[image]".to_string(),
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n");
+                            lines.push(format!(
+                                "TOOL RESULT: {}",
+                                truncate_chars(&output, TRANSCRIPT_TOOL_OUTPUT_CHARS)
+                            ));
+                        }
+                        _ => lines.push("USER: [attachment]".to_string()),
+                    }
+                }
+            }
+            Message::Assistant { content, .. } => {
+                for c in content.iter() {
+                    match c {
+                        AssistantContent::Text(text) => {
+                            lines.push(format!("ASSISTANT: {}", text.text))
+                        }
+                        AssistantContent::ToolCall(call) => lines.push(format!(
+                            "TOOL CALL: {}({})",
+                            call.function.name, call.function.arguments
+                        )),
+                        AssistantContent::Reasoning(_) => {}
+                        AssistantContent::Image(_) => lines.push("ASSISTANT: [image]".to_string()),
+                    }
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(max_chars).collect();
+    format!("{}... [truncated]", truncated)
+}
+
+/// rig 的媒体类型用 `#[serde(flatten)]` 保存 `additional_params`,
+/// 反序列化时没有额外字段会得到 `Some({})`, 这里还原为 `None`
+fn drop_empty_params(params: &mut Option<serde_json::Value>) {
+    if matches!(params, Some(serde_json::Value::Object(map)) if map.is_empty()) {
+        *params = None;
+    }
+}
+
 /// 可序列化的消息类型
+///
+/// 版本 1 会话文件中的消息格式, 现在用于迁移旧会话和在 `/history` 中显示消息摘要
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SerializableMessage {
     pub role: String,
@@ -44,7 +477,9 @@ impl From<&Message> for SerializableMessage {
                 content: content
                     .iter()
                     .map(|c| match c {
-                        rig::completion::message::UserContent::Text(text) => text.text.clone(),
+                        UserContent::Text(text) => text.text.clone(),
+                        UserContent::ToolResult(result) => format!("[tool result: {}]", result.id),
+                        UserContent::Image(_) => "[image]".to_string(),
                         _ => "[non-text content]".to_string(),
                     })
                     .collect::<Vec<_>>()
@@ -55,16 +490,12 @@ impl From<&Message> for SerializableMessage {
                 content: content
                     .iter()
                     .map(|c| match c {
-                        rig::completion::message::AssistantContent::Text(text) => text.text.clone(),
-                        rig::completion::message::AssistantContent::ToolCall(_) => {
-                            "[tool call]".to_string()
-                        }
-                        rig::completion::message::AssistantContent::Reasoning(_) => {
-                            "[reasoning]".to_string()
-                        }
-                        rig::completion::message::AssistantContent::Image(_) => {
-                            "[image]".to_string()
+                        AssistantContent::Text(text) => text.text.clone(),
+                        AssistantContent::ToolCall(call) => {
+                            format!("[tool call: {}]", call.function.name)
                         }
+                        AssistantContent::Reasoning(_) => "[reasoning]".to_string(),
+                        AssistantContent::Image(_) => "[image]".to_string(),
                     })
                     .collect::<Vec<_>>()
                     .join(" "),
@@ -85,6 +516,7 @@ impl From<SerializableMessage> for Message {
 
 impl ContextManager {
     pub fn new<P: AsRef<Path>>(storage_dir: P, session_id: String) -> Result<Self> {
+        Self::validate_session_id(&session_id)?;
         let storage_dir = storage_dir.as_ref().to_path_buf();
         if !storage_dir.exists() {
             fs::create_dir_all(&storage_dir).with_context(|| {
@@ -96,6 +528,8 @@ impl ContextManager {
             session_id,
             messages: Vec::new(),
             max_messages: 100,
+            token_budget: None,
+            summary: None,
         })
     }
 
@@ -106,10 +540,38 @@ impl ContextManager {
         }
     }
 
+    /// 启用 token 预算模式
+    ///
+    /// 启用后不再按 `max_messages` 删除消息; 超出预算时由调用方通过
+    /// [`compaction_span`](Self::compaction_span) 和 [`apply_summary`](Self::apply_summary)
+    /// 把最早的一段对话压缩为摘要
+    pub fn with_token_budget(self, token_budget: usize) -> Self {
+        Self {
+            token_budget: Some(token_budget),
+            ..self
+        }
+    }
+
+    pub fn token_budget(&self) -> Option<usize> {
+        self.token_budget
+    }
+
     pub fn add_message(&mut self, message: Message) {
         self.messages.push(message);
-        if self.messages.len() > self.max_messages {
-            self.messages.remove(0);
+        if self.token_budget.is_none() && self.messages.len() > self.max_messages {
+            let excess = self.messages.len() - self.max_messages;
+
+            // 截断后不能以工具结果或助手消息开头, 否则工具调用和结果无法配对;
+            // 如果当前这一轮本身就超过上限, 则完整保留这一轮
+            let start = self
+                .messages
+                .iter()
+                .skip(excess)
+                .position(is_user_prompt)
+                .map(|position| position + excess)
+                .or_else(|| self.messages.iter().rposition(is_user_prompt))
+                .unwrap_or(0);
+            self.messages.drain(..start);
         }
     }
 
@@ -123,6 +585,82 @@ impl ContextManager {
 
     pub fn clear(&mut self) {
         self.messages.clear();
+        self.summary = None;
+    }
+
+    pub fn summary(&self) -> Option<&ContextSummary> {
+        self.summary.as_ref()
+    }
+
+    /// 发送给模型的历史: 摘要 (如果有) 加上保留的消息
+    pub fn history(&self) -> Vec<Message> {
+        let mut history = Vec::with_capacity(self.messages.len() + 1);
+        if let Some(summary) = &self.summary {
+            history.push(Self::summary_message(summary));
+        }
+        history.extend(self.messages.iter().cloned());
+        history
+    }
+
+    fn summary_message(summary: &ContextSummary) -> Message {
+        Message::user(format!(
+            "[Summary of the earlier conversation]\n{}",
+            summary.text
+        ))
+    }
+
+    /// 估算当前历史 (包括摘要) 的 token 数
+    pub fn estimated_tokens(&self) -> usize {
+        self.history().iter().map(estimate_tokens).sum()
+    }
+
+    /// 需要压缩的最早消息数量
+    ///
+    /// 超出 token 预算时, 返回最短的前缀长度, 使剩余历史回到预算的一半以内;
+    /// 前缀总是在用户输入处截断, 保证工具调用和结果成对保留。
+    /// 未设置预算、未超出预算或无法截断 (只有当前一轮) 时返回 `None`
+    pub fn compaction_span(&self) -> Option<usize> {
+        let budget = self.token_budget?;
+        if self.estimated_tokens() <= budget {
+            return None;
+        }
+
+        let target = budget / COMPACTION_TARGET_DIVISOR;
+        let mut remaining: usize = self.messages.iter().map(estimate_tokens).sum();
+        let mut last_boundary = None;
+        for (i, message) in self.messages.iter().enumerate() {
+            if i > 0 && is_user_prompt(message) {
+                last_boundary = Some(i);
+                if remaining <= target {
+                    break;
+                }
+            }
+            remaining -= estimate_tokens(message);
+        }
+        last_boundary
+    }
+
+    /// 需要压缩的内容: 之前的摘要加上最早的 `count` 条消息
+    pub fn compaction_transcript(&self, count: usize) -> String {
+        let count = count.min(self.messages.len());
+        let transcript = render_transcript(&self.messages[..count]);
+        match &self.summary {
+            Some(summary) => format!("EARLIER SUMMARY: {}\n{}", summary.text, transcript),
+            None => transcript,
+        }
+    }
+
+    /// 用摘要替换最早的 `count` 条消息
+    pub fn apply_summary(&mut self, count: usize, text: String) {
+        let count = count.min(self.messages.len());
+        self.messages.drain(..count);
+        let summarized_messages =
+            self.summary.as_ref().map_or(0, |s| s.summarized_messages) + count;
+        self.summary = Some(ContextSummary {
+            text,
+            summarized_messages,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        });
     }
 
     pub fn save(&self) -> Result<()> {
@@ -134,14 +672,11 @@ impl ContextManager {
             last_updated: now,
             message_count: self.messages.len(),
         };
-        let serializable_messages: Vec<SerializableMessage> = self
-            .messages
-            .iter()
-            .map(SerializableMessage::from)
-            .collect();
         let session_data = SessionData {
+            version: SESSION_FORMAT_VERSION,
             metadata,
-            messages: serializable_messages,
+            summary: self.summary.clone(),
+            messages: self.messages.iter().map(StoredMessage::from).collect(),
         };
         let json_data = serde_json::to_string_pretty(&session_data)
             .context("Failed to serialize session data")?;
@@ -157,16 +692,49 @@ impl ContextManager {
         }
         let json_data = fs::read_to_string(&file_path)
             .with_context(|| format!("Failed to read session file: {:?}", file_path))?;
-        let session_data: SessionData =
-            serde_json::from_str(&json_data).context("Failed to deserialize session data")?;
-        self.messages = session_data
-            .messages
-            .into_iter()
-            .map(Message::from)
-            .collect();
+        let (messages, summary) = Self::parse_session(&json_data)?;
+        self.messages = messages;
+        self.summary = summary;
         Ok(true)
     }
 
+    /// 解析任意版本的会话文件
+    ///
+    /// 旧版本的文件会被迁移到内存中的完整消息, 下一次 `save` 时以当前版本写回
+    fn parse_session(json_data: &str) -> Result<(Vec<Message>, Option<ContextSummary>)> {
+        let value: serde_json::Value =
+            serde_json::from_str(json_data).context("Failed to deserialize session data")?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(1);
+
+        match version {
+            1 => {
+                let session_data: LegacySessionData =
+                    serde_json::from_value(value).context("Failed to deserialize session data")?;
+                let messages = session_data
+                    .messages
+                    .into_iter()
+                    .map(Message::from)
+                    .collect();
+                Ok((messages, None))
+            }
+            2 | 3 => {
+                let session_data: SessionData =
+                    serde_json::from_value(value).context("Failed to deserialize session data")?;
+                let messages = session_data
+                    .messages
+                    .into_iter()
+                    .map(Message::try_from)
+                    .collect::<Result<_>>()?;
+                Ok((messages, session_data.summary))
+            }
+            v => Err(anyhow::anyhow!(
+                "Unsupported session format version {} (this build supports up to {})",
+                v,
+                SESSION_FORMAT_VERSION
+            )),
+        }
+    }
+
     pub fn list_sessions(&self) -> Result<Vec<SessionMetadata>> {
         let mut sessions = Vec::new();
         if !self.storage_dir.exists() {
@@ -177,7 +745,7 @@ impl ContextManager {
             let path = entry.path();
             if path.extension().and_then(|s| s.to_str()) == Some("json") {
                 if let Ok(json_data) = fs::read_to_string(&path) {
-                    if let Ok(session_data) = serde_json::from_str::<SessionData>(&json_data) {
+                    if let Ok(session_data) = serde_json::from_str::<SessionHeader>(&json_data) {
                         sessions.push(session_data.metadata);
                     }
                 }
@@ -202,12 +770,35 @@ impl ContextManager {
         self.storage_dir.join(format!("{}.json", self.session_id))
     }
 
+    pub fn storage_dir(&self) -> &Path {
+        &self.storage_dir
+    }
+
     pub fn session_id(&self) -> &str {
         &self.session_id
     }
 
+    /// 检查会话 ID 能否安全地用作文件名
+    ///
+    /// 会话 ID 会拼接到会话目录下的路径中, 因此不能为空、不能是 `.`/`..`,
+    /// 也不能包含路径分隔符。
+    pub fn validate_session_id(session_id: &str) -> Result<()> {
+        if session_id.is_empty()
+            || session_id == "."
+            || session_id == ".."
+            || session_id.contains(['/', '\\'])
+        {
+            anyhow::bail!(
+                "Invalid session id '{}': it must be a plain name without path separators",
+                session_id
+            );
+        }
+        Ok(())
+    }
+
     pub fn switch_session(&mut self, new_session_id: String) {
         self.session_id = new_session_id;
         self.messages.clear();
+        self.summary = None;
     }
 }
@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use rmcp::{
-    model::{CallToolRequestParams, InitializeResult, Tool},
+    model::{CallToolRequestParams, CallToolResult, InitializeResult, Tool},
     service::{RoleClient, RunningService},
     transport::{ConfigureCommandExt, TokioChildProcess},
     ServiceExt,
@@ -9,6 +9,8 @@ use serde_json::Value;
 use std::borrow::Cow;
 use tokio::process::Command;
 
+use crate::kota_code::runtime::McpServerConfig;
+
 /// MCP client connection wrapper
 pub struct McpClient {
     /// The running service for communicating with the MCP server
@@ -33,13 +35,29 @@ impl McpClient {
     /// let client = McpClient::new("uvx", vec!["mcp-server-git"]).await?;
     /// ```
     pub async fn new(command: &str, args: Vec<String>) -> Result<Self> {
+        Self::from_config(&McpServerConfig {
+            command: command.to_string(),
+            args,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Create a new MCP client from a server definition in the config
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Command, arguments, environment and working directory of the server
+    pub async fn from_config(config: &McpServerConfig) -> Result<Self> {
         // Create command
-        let cmd = Command::new(command);
+        let cmd = Command::new(&config.command);
 
-        // Configure the command with arguments
+        // Configure the command with arguments, environment and working directory
         let transport = TokioChildProcess::new(cmd.configure(|c| {
-            for arg in args {
-                c.arg(arg);
+            c.args(&config.args);
+            c.envs(&config.env);
+            if let Some(cwd) = &config.cwd {
+                c.current_dir(cwd);
             }
         }))
         .context("Failed to create child process transport")?;
@@ -87,8 +105,22 @@ impl McpClient {
     ///
     /// Returns the result from the tool execution
     pub async fn call_tool(&self, tool_name: &str, arguments: Option<Value>) -> Result<Value> {
-        let result = self
-            .service
+        let result = self.call_tool_result(tool_name, arguments).await?;
+        Ok(serde_json::to_value(result)?)
+    }
+
+    /// Call a tool on the MCP server and return the typed result
+    ///
+    /// # Arguments
+    ///
+    /// * `tool_name` - Name of the tool to call
+    /// * `arguments` - JSON arguments for the tool
+    pub async fn call_tool_result(
+        &self,
+        tool_name: &str,
+        arguments: Option<Value>,
+    ) -> Result<CallToolResult> {
+        self.service
             .call_tool(CallToolRequestParams {
                 meta: None,
                 name: Cow::Owned(tool_name.to_string()),
@@ -96,9 +128,7 @@ impl McpClient {
                 task: None,
             })
             .await
-            .context(format!("Failed to call tool: {}", tool_name))?;
-
-        Ok(serde_json::to_value(result)?)
+            .context(format!("Failed to call tool: {}", tool_name))
     }
 
     /// Refresh the list of available tools
@@ -4,17 +4,23 @@
 //! It supports connecting to MCP servers via child processes and calling tools.
 
 pub mod client;
+pub mod tool;
 
 use anyhow::{Context, Result};
-use rmcp::model::{InitializeResult, Tool};
+use rmcp::model::{CallToolResult, InitializeResult, Tool};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::kota_code::mcp::client::McpClient;
+use crate::kota_code::mcp::tool::McpTool;
+use crate::kota_code::runtime::McpServerConfig;
 
 /// MCP Manager for managing multiple MCP client connections
+///
+/// Cloning the manager is cheap and shares the same set of connections.
+#[derive(Clone)]
 pub struct McpManager {
     /// Map of server name to MCP client
     clients: Arc<RwLock<HashMap<String, McpClient>>>,
@@ -52,6 +58,23 @@ impl McpManager {
         Ok(())
     }
 
+    /// Add a new MCP server connection from a server definition in the config
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Unique name for this server connection
+    /// * `config` - Command, arguments, environment and working directory of the server
+    pub async fn add_server_from_config(&self, name: &str, config: &McpServerConfig) -> Result<()> {
+        let client = McpClient::from_config(config)
+            .await
+            .context(format!("Failed to add MCP server: {}", name))?;
+
+        let mut clients = self.clients.write().await;
+        clients.insert(name.to_string(), client);
+
+        Ok(())
+    }
+
     /// Remove an MCP server connection
     pub async fn remove_server(&self, name: &str) -> Result<()> {
         let mut clients = self.clients.write().await;
@@ -119,6 +142,41 @@ impl McpManager {
         client.call_tool(tool_name, arguments).await
     }
 
+    /// Call a tool on a specific server and return the typed result
+    pub async fn call_tool_result(
+        &self,
+        server_name: &str,
+        tool_name: &str,
+        arguments: Option<Value>,
+    ) -> Result<CallToolResult> {
+        let clients = self.clients.read().await;
+
+        let client = clients
+            .get(server_name)
+            .context(format!("Server not found: {}", server_name))?;
+
+        client.call_tool_result(tool_name, arguments).await
+    }
+
+    /// Build agent tools for every tool of every connected server
+    ///
+    /// Each tool is exposed to the model as `server__tool`.
+    pub async fn agent_tools(&self) -> Vec<McpTool> {
+        let clients = self.clients.read().await;
+
+        let mut server_names: Vec<&String> = clients.keys().collect();
+        server_names.sort();
+
+        let mut tools = Vec::new();
+        for server_name in server_names {
+            for tool in clients[server_name].tools() {
+                tools.push(McpTool::new(server_name, tool.clone(), self.clone()));
+            }
+        }
+
+        tools
+    }
+
     /// Refresh tools list for a specific server
     pub async fn refresh_server_tools(&self, server_name: &str) -> Result<()> {
         let mut clients = self.clients.write().await;
@@ -0,0 +1,199 @@
+//! Adapter exposing MCP server tools as agent tools
+
+use rig::{
+    completion::ToolDefinition,
+    tool::{ToolDyn, ToolError},
+};
+use rmcp::model::{CallToolResult, RawContent, Tool};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::McpManager;
+
+/// Separator between the server name and the tool name in exposed tool names
+pub const TOOL_NAME_SEPARATOR: &str = "__";
+
+#[derive(Debug, Error)]
+pub enum McpToolError {
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+    #[error("MCP call failed: {0}")]
+    CallFailed(String),
+    #[error("MCP tool returned an error: {0}")]
+    ToolFailed(String),
+}
+
+/// A tool from a connected MCP server, callable by the agent
+///
+/// The tool is exposed as `server__tool` so tools with the same name on different
+/// servers do not collide with each other or with the built-in tools.
+#[derive(Clone)]
+pub struct McpTool {
+    server_name: String,
+    tool: Tool,
+    manager: McpManager,
+}
+
+impl McpTool {
+    pub fn new(server_name: &str, tool: Tool, manager: McpManager) -> Self {
+        Self {
+            server_name: server_name.to_string(),
+            tool,
+            manager,
+        }
+    }
+
+    /// Name of the server providing this tool
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// Name of the tool on the MCP server
+    pub fn remote_name(&self) -> &str {
+        &self.tool.name
+    }
+
+    /// Namespaced tool name, restricted to the characters LLM providers accept
+    fn namespaced_name(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.server_name, TOOL_NAME_SEPARATOR, self.tool.name
+        )
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+    }
+
+    /// Flatten an MCP result into the text the model sees
+    fn render_content(result: &CallToolResult) -> String {
+        let parts: Vec<String> = result
+            .content
+            .iter()
+            .map(|content| match &content.raw {
+                RawContent::Text(text) => text.text.clone(),
+                other => serde_json::to_string(other).unwrap_or_default(),
+            })
+            .collect();
+
+        if parts.is_empty() {
+            if let Some(structured) = &result.structured_content {
+                return structured.to_string();
+            }
+        }
+
+        parts.join("\n")
+    }
+}
+
+impl ToolDyn for McpTool {
+    fn name(&self) -> String {
+        self.namespaced_name()
+    }
+
+    fn definition<'a>(
+        &'a self,
+        _prompt: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, ToolDefinition> {
+        Box::pin(async move {
+            let description = self
+                .tool
+                .description
+                .as_deref()
+                .unwrap_or_default()
+                .to_string();
+
+            ToolDefinition {
+                name: self.namespaced_name(),
+                description: format!("[MCP: {}] {}", self.server_name, description),
+                parameters: self.tool.schema_as_json_value(),
+            }
+        })
+    }
+
+    fn call<'a>(
+        &'a self,
+        args: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let arguments: Option<Value> = if args.trim().is_empty() {
+                None
+            } else {
+                Some(serde_json::from_str(&args).map_err(|e| {
+                    ToolError::ToolCallError(Box::new(McpToolError::InvalidArguments(
+                        e.to_string(),
+                    )))
+                })?)
+            };
+
+            let result = self
+                .manager
+                .call_tool_result(&self.server_name, &self.tool.name, arguments)
+                .await
+                .map_err(|e| {
+                    let message = format!("{:#}", e);
+                    ToolError::ToolCallError(Box::new(McpToolError::CallFailed(message)))
+                })?;
+
+            let content = Self::render_content(&result);
+            if result.is_error == Some(true) {
+                return Err(ToolError::ToolCallError(Box::new(
+                    McpToolError::ToolFailed(content),
+                )));
+            }
+
+            Ok(content)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn test_tool(name: &'static str) -> Tool {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "repo_path": { "type": "string" } }
+        });
+        Tool::new(
+            name,
+            "Show the working tree status",
+            Arc::new(schema.as_object().unwrap().clone()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_namespaced_definition() {
+        let tool = McpTool::new("git", test_tool("git_status"), McpManager::new());
+        assert_eq!(tool.name(), "git__git_status");
+        assert_eq!(tool.server_name(), "git");
+        assert_eq!(tool.remote_name(), "git_status");
+
+        let definition = tool.definition(String::new()).await;
+        assert_eq!(definition.name, "git__git_status");
+        assert!(definition
+            .description
+            .contains("Show the working tree status"));
+        assert!(definition.parameters["properties"]["repo_path"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_name_sanitized() {
+        let tool = McpTool::new("my server", test_tool("read.file"), McpManager::new());
+        assert_eq!(tool.name(), "my_server__read_file");
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_call_unknown_server() {
+        let tool = McpTool::new("git", test_tool("git_status"), McpManager::new());
+        let result = tool.call("{}".to_string()).await;
+        assert!(result.unwrap_err().to_string().contains("Server not found"));
+    }
+}
@@ -42,10 +42,12 @@
 //! - **Task Management**: Plan mode with task dependencies and status tracking
 //! - **Skills System**: Specialized agent behaviors (code review, refactoring, debugging, documentation)
 //! - **Context Management**: Persistent conversation history with session support
+//! - **Checkpoints**: Undo the file changes of a conversation turn
 //! - **Extensible**: Easy to add custom tools and behaviors
 
 // Core modules
 pub mod agent;
+pub mod checkpoint;
 pub mod context;
 pub mod mcp;
 pub mod plan;
@@ -55,24 +57,30 @@ pub mod tools;
 
 // Re-export commonly used types for convenience
 pub use agent::{create_agent, AgentBuilder, AgentInstance, AgentType, Provider};
+pub use checkpoint::{Checkpoint, CheckpointManager};
 pub use context::{ContextManager, SerializableMessage, SessionMetadata};
-pub use mcp::{client::McpClient, McpManager};
+pub use mcp::{client::McpClient, tool::McpTool, McpManager};
 pub use plan::{Plan, PlanManager, Task, TaskStatus};
-pub use runtime::{CommandDef, KotaConfig, SessionIdHook, ToolRegistry};
+pub use runtime::{
+    CommandDef, ConfigSource, KotaConfig, SessionIdHook, ToolFilter, ToolHooks, ToolRegistry,
+};
 pub use skills::{Skill, SkillManager};
 pub use tools::{
-    FileToolError, WrappedCreateDirectoryTool, WrappedDeleteFileTool, WrappedEditFileTool,
-    WrappedExecuteBashCommandTool, WrappedGrepSearchTool, WrappedReadFileTool,
+    FileToolError, Workspace, WrappedCreateDirectoryTool, WrappedDeleteFileTool,
+    WrappedEditFileTool, WrappedExecuteBashCommandTool, WrappedGrepSearchTool, WrappedReadFileTool,
     WrappedScanCodebaseTool, WrappedUpdatePlanTool, WrappedWriteFileTool,
 };
 
 /// Prelude module for convenient imports
 pub mod prelude {
     pub use super::agent::{create_agent, AgentBuilder, AgentInstance, AgentType, Provider};
+    pub use super::checkpoint::{Checkpoint, CheckpointManager};
     pub use super::context::{ContextManager, SerializableMessage, SessionMetadata};
-    pub use super::mcp::{client::McpClient, McpManager};
+    pub use super::mcp::{client::McpClient, tool::McpTool, McpManager};
     pub use super::plan::{Plan, PlanManager, Task, TaskStatus};
-    pub use super::runtime::{CommandDef, KotaConfig, SessionIdHook, ToolRegistry};
+    pub use super::runtime::{
+        CommandDef, KotaConfig, SessionIdHook, ToolFilter, ToolHooks, ToolRegistry,
+    };
     pub use super::skills::{Skill, SkillManager};
-    pub use super::tools::FileToolError;
+    pub use super::tools::{FileToolError, Workspace};
 }
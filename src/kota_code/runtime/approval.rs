@@ -0,0 +1,633 @@
+use anyhow::Result;
+use colored::*;
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use rig::completion::ToolDefinition;
+use rig::tool::{ToolDyn, ToolError};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+use crate::kota_code::tools::edit_file::EditFileTool;
+use crate::kota_code::tools::patch::{self, FilePatch};
+use crate::kota_code::tools::workspace::{Access, Workspace};
+
+/// How tool calls that change files or run commands are approved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApprovalMode {
+    /// Ask before every call
+    #[default]
+    Ask,
+    /// Run file writes and edits, ask for deletes and commands
+    AutoEdit,
+    /// Run everything without asking
+    Auto,
+    /// Refuse every call
+    ReadOnly,
+}
+
+impl ApprovalMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalMode::Ask => "ask",
+            ApprovalMode::AutoEdit => "auto-edit",
+            ApprovalMode::Auto => "auto",
+            ApprovalMode::ReadOnly => "read-only",
+        }
+    }
+
+    /// Decide what to do with a call of the given kind
+    fn decide(&self, kind: ToolKind) -> Decision {
+        match (self, kind) {
+            (ApprovalMode::Auto, _) | (ApprovalMode::AutoEdit, ToolKind::Edit) => Decision::Allow,
+            (ApprovalMode::ReadOnly, _) => Decision::Deny,
+            _ => Decision::Ask,
+        }
+    }
+}
+
+impl fmt::Display for ApprovalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ApprovalMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "ask" => Ok(ApprovalMode::Ask),
+            "auto-edit" => Ok(ApprovalMode::AutoEdit),
+            "auto" => Ok(ApprovalMode::Auto),
+            "read-only" | "readonly" => Ok(ApprovalMode::ReadOnly),
+            _ => Err(anyhow::anyhow!(
+                "Unknown approval mode: {} (expected ask, auto-edit, auto or read-only)",
+                s
+            )),
+        }
+    }
+}
+
+/// What a tool call does, used to apply [`ApprovalMode::AutoEdit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    /// Creates or modifies files
+    Edit,
+    /// Deletes files
+    Delete,
+    /// Runs commands, or any other tool listed in `approval.tools`
+    Exec,
+}
+
+/// Built-in tools that always go through approval, and the argument naming their target
+const GATED_TOOLS: &[(&str, ToolKind, &str)] = &[
+    ("write_file", ToolKind::Edit, "file_path"),
+    ("edit_file", ToolKind::Edit, "file_path"),
+    ("delete_file", ToolKind::Delete, "file_path"),
+    ("exec_cmd", ToolKind::Exec, "command"),
+    ("shell_session", ToolKind::Exec, "command"),
+    ("process", ToolKind::Exec, "command"),
+];
+
+/// Actions of built-in tools that only look at or stop something already
+/// approved, and run without asking: tool, argument and its allowed values
+const EXEMPT_ACTIONS: &[(&str, &str, &[&str])] =
+    &[("process", "action", &["read_output", "status", "kill"])];
+
+/// Outcome of the approval policy for one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Ask,
+    Deny,
+}
+
+/// Approval settings from the `approval` table of the config
+///
+/// The mode for a call is taken from the most specific (longest) matching
+/// `paths` or `commands` pattern, then from `tools`, then from `mode`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApprovalConfig {
+    pub mode: ApprovalMode,
+    /// Mode per tool name
+    pub tools: HashMap<String, ApprovalMode>,
+    /// Mode per file path glob, for file tools (e.g. `docs/**`)
+    pub paths: Vec<(String, ApprovalMode)>,
+    /// Mode per command glob, for `exec_cmd`, `shell_session` and `process` (e.g. `cargo test*`)
+    pub commands: Vec<(String, ApprovalMode)>,
+}
+
+impl ApprovalConfig {
+    /// Whether calls to this tool go through approval
+    pub fn is_gated(&self, tool: &str) -> bool {
+        Self::gated_tool(tool).is_some() || self.tools.contains_key(tool)
+    }
+
+    fn gated_tool(tool: &str) -> Option<(ToolKind, &'static str)> {
+        GATED_TOOLS
+            .iter()
+            .find(|(name, _, _)| *name == tool)
+            .map(|(_, kind, target)| (*kind, *target))
+    }
+
+    /// Whether a call is one of the non-mutating actions in [`EXEMPT_ACTIONS`]
+    ///
+    /// Such calls, like `process` reading output, run without asking in every mode.
+    pub fn is_exempt(tool: &str, args: &str) -> bool {
+        let json: JsonValue = serde_json::from_str(args).unwrap_or(JsonValue::Null);
+        EXEMPT_ACTIONS.iter().any(|(name, key, actions)| {
+            *name == tool
+                && json
+                    .get(*key)
+                    .and_then(JsonValue::as_str)
+                    .is_some_and(|action| actions.contains(&action))
+        })
+    }
+
+    /// The file path or command a call acts on, for prompts and pattern matching
+    pub fn target(tool: &str, args: &str) -> String {
+        let targets = Self::targets(tool, args);
+        if targets.is_empty() {
+            return args.to_string();
+        }
+        targets.join("\n")
+    }
+
+    /// Every file path or command a call acts on
+    ///
+    /// An `edit_file` patch may change several files, named in its headers. An
+    /// `exec_cmd` command or started `process` includes its `cwd` and `env`
+    /// arguments, see [`command_line`](Self::command_line). Input sent to a
+    /// `process` is a command typed into it, so it is the target of `send_input`.
+    pub fn targets(tool: &str, args: &str) -> Vec<String> {
+        let json: JsonValue = serde_json::from_str(args).unwrap_or(JsonValue::Null);
+        let Some((_, key)) = Self::gated_tool(tool) else {
+            return Vec::new();
+        };
+        let target = json.get(key).and_then(JsonValue::as_str);
+        if tool == "process" && json.get("action").and_then(JsonValue::as_str) == Some("send_input")
+        {
+            return json
+                .get("input")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+                .into_iter()
+                .collect();
+        }
+        if tool == "exec_cmd" || tool == "process" {
+            return target
+                .map(|command| Self::command_line(&json, command))
+                .into_iter()
+                .collect();
+        }
+        if tool != "edit_file" {
+            return target.map(str::to_string).into_iter().collect();
+        }
+
+        let file_path = target.unwrap_or("");
+        let patch = json.get("patch").and_then(JsonValue::as_str).unwrap_or("");
+        patch::parse(patch)
+            .and_then(|files| EditFileTool::targets(file_path, files))
+            .map(|targets| targets.into_iter().map(|(path, _)| path).collect())
+            .unwrap_or_else(|_| {
+                (!file_path.is_empty())
+                    .then(|| file_path.to_string())
+                    .into_iter()
+                    .collect()
+            })
+    }
+
+    /// A command as the shell line it amounts to, e.g. `cd sub && FOO=1 make`
+    ///
+    /// `approval.commands` patterns match the whole line, so a pattern like
+    /// `make*` does not allow `make` in another directory or with extra variables.
+    fn command_line(json: &JsonValue, command: &str) -> String {
+        let mut line = String::new();
+        if let Some(cwd) = json.get("cwd").and_then(JsonValue::as_str) {
+            line.push_str(&format!("cd {} && ", Self::shell_quote(cwd)));
+        }
+        if let Some(env) = json.get("env").and_then(JsonValue::as_object) {
+            for (name, value) in env {
+                let value = match value {
+                    JsonValue::String(value) => value.clone(),
+                    other => other.to_string(),
+                };
+                line.push_str(&format!("{}={} ", name, Self::shell_quote(&value)));
+            }
+        }
+        line.push_str(command);
+        line
+    }
+
+    fn shell_quote(value: &str) -> String {
+        let plain = !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-./:=,+@%".contains(c));
+        if plain {
+            value.to_string()
+        } else {
+            format!("'{}'", value.replace('\'', "'\\''"))
+        }
+    }
+
+    /// What a call does
+    ///
+    /// An `edit_file` patch that deletes a file (`+++ /dev/null`) is a delete, so
+    /// [`ApprovalMode::AutoEdit`] asks for it as it does for `delete_file`.
+    pub fn kind(tool: &str, args: &str) -> ToolKind {
+        let kind = Self::gated_tool(tool)
+            .map(|(kind, _)| kind)
+            .unwrap_or(ToolKind::Exec);
+        if tool != "edit_file" {
+            return kind;
+        }
+        let json: JsonValue = serde_json::from_str(args).unwrap_or(JsonValue::Null);
+        let patch = json.get("patch").and_then(JsonValue::as_str).unwrap_or("");
+        if patch::parse(patch).is_ok_and(|files| files.iter().any(FilePatch::is_deletion)) {
+            return ToolKind::Delete;
+        }
+        kind
+    }
+
+    /// Decide whether a call may run
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name
+    /// * `target` - File path or command from [`target`](Self::target)
+    pub fn decide(&self, tool: &str, target: &str) -> Decision {
+        let kind = Self::gated_tool(tool)
+            .map(|(kind, _)| kind)
+            .unwrap_or(ToolKind::Exec);
+        self.decide_as(tool, kind, target, tool == "shell_session", None)
+    }
+
+    /// Whether a call types into a shell that keeps state between calls
+    ///
+    /// `shell_session` and input sent to a `process` run in a shell where earlier
+    /// calls may have defined aliases and functions, changed `PATH` or `cd`-ed
+    /// elsewhere, so `alias ls='rm -rf ~'` followed by an allowed `ls*` would run
+    /// anything. Their command text says nothing reliable about what runs.
+    fn is_stateful(tool: &str, args: &str) -> bool {
+        tool == "shell_session"
+            || (tool == "process"
+                && serde_json::from_str::<JsonValue>(args).is_ok_and(|json| {
+                    json.get("action").and_then(JsonValue::as_str) == Some("send_input")
+                }))
+    }
+
+    /// Decide a call whose target argument is missing
+    ///
+    /// A built-in tool called without its file path or command cannot be matched
+    /// against patterns, so it is asked about, or refused in read-only mode.
+    fn decide_without_target(&self, tool: &str, args: &str) -> Decision {
+        if Self::gated_tool(tool).is_none() {
+            return self.decide(tool, args);
+        }
+        match self.tools.get(tool).copied().unwrap_or(self.mode) {
+            ApprovalMode::ReadOnly => Decision::Deny,
+            _ => Decision::Ask,
+        }
+    }
+
+    /// Decide a call of a given kind
+    ///
+    /// For a `stateful` call (see [`is_stateful`](Self::is_stateful)) command
+    /// patterns can only make the decision stricter, never allow it. File paths
+    /// are matched relative to `workspace`, or to the working directory without one.
+    fn decide_as(
+        &self,
+        tool: &str,
+        kind: ToolKind,
+        target: &str,
+        stateful: bool,
+        workspace: Option<&Workspace>,
+    ) -> Decision {
+        let (patterns, candidate) = match kind {
+            ToolKind::Exec if Self::gated_tool(tool).is_some() => {
+                (&self.commands, Some(target.trim().to_string()))
+            }
+            ToolKind::Exec => (&Vec::new(), None),
+            ToolKind::Edit | ToolKind::Delete => {
+                (&self.paths, Self::normalize_path(target, workspace))
+            }
+        };
+
+        let pattern_mode = patterns
+            .iter()
+            .filter(|(pattern, _)| {
+                candidate.as_ref().is_some_and(|candidate| {
+                    Self::matcher(pattern, kind != ToolKind::Exec)
+                        .is_some_and(|matcher| matcher.is_match(candidate))
+                })
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, mode)| *mode)
+            .filter(|mode| !stateful || mode.decide(kind) != Decision::Allow);
+
+        pattern_mode
+            .or_else(|| self.tools.get(tool).copied())
+            .unwrap_or(self.mode)
+            .decide(kind)
+    }
+
+    /// Check that a glob pattern is valid
+    pub fn check_pattern(pattern: &str) -> std::result::Result<(), String> {
+        Glob::new(pattern).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn matcher(pattern: &str, is_path: bool) -> Option<GlobMatcher> {
+        GlobBuilder::new(pattern)
+            .literal_separator(is_path)
+            .build()
+            .ok()
+            .map(|glob| glob.compile_matcher())
+    }
+
+    /// Make a path relative to the workspace root so patterns like `src/**` match
+    ///
+    /// The path is resolved the way the file tools resolve it, so `..` and
+    /// symlinks cannot make `docs/../src/main.rs` match `docs/**`. A path outside
+    /// the root gives `None`, which no pattern matches.
+    fn normalize_path(path: &str, workspace: Option<&Workspace>) -> Option<String> {
+        let cwd;
+        let workspace = match workspace {
+            Some(workspace) => workspace,
+            None => {
+                cwd = Workspace::new(".").ok()?;
+                &cwd
+            }
+        };
+        let resolved = workspace.resolve(path, Access::Read).ok()?;
+        let relative = resolved.strip_prefix(workspace.root()).ok()?;
+        Some(relative.to_string_lossy().into_owned())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ApprovalError {
+    #[error("Tool call '{tool}' on '{target}' was denied by the user{}", reason.as_ref().map(|r| format!(": {}", r)).unwrap_or_default())]
+    DeniedByUser {
+        tool: String,
+        target: String,
+        reason: Option<String>,
+    },
+    #[error("Tool call '{tool}' on '{target}' is not allowed: approval mode is read-only")]
+    ReadOnly { tool: String, target: String },
+    #[error(
+        "Tool call '{tool}' on '{target}' needs approval, but there is no terminal to ask the user"
+    )]
+    NotInteractive { tool: String, target: String },
+}
+
+/// Answer to an approval prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalAnswer {
+    /// Run this call only
+    AllowOnce,
+    /// Run this and every later call of the same tool on the same target in this session
+    AllowSession,
+    /// Do not run the call, with an optional reason for the model
+    Deny(Option<String>),
+}
+
+/// Asks the user whether a tool call may run
+pub trait Approver: Send + Sync {
+    /// Returns `None` if nobody can be asked
+    fn ask(&self, tool: &str, target: &str) -> Option<ApprovalAnswer>;
+}
+
+/// Prompts on the terminal; declines to answer when stdin is not a terminal
+#[derive(Default)]
+pub struct TerminalApprover {
+    // Parallel tool calls must not prompt at the same time
+    lock: Mutex<()>,
+}
+
+impl Approver for TerminalApprover {
+    fn ask(&self, tool: &str, target: &str) -> Option<ApprovalAnswer> {
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        println!("\n{} {}({})", "●".yellow(), "Approve".yellow(), tool);
+        for line in target.lines() {
+            println!("  │ {}", line);
+        }
+        // "Always" is remembered for this tool and target only
+        let scope = match ApprovalConfig::gated_tool(tool) {
+            Some((ToolKind::Exec, _)) => "this command",
+            Some(_) => "these files",
+            None => "these arguments",
+        };
+        print!(
+            "  └─ {} once, {} always for {} this session, {} deny (n <reason> to explain): ",
+            "[y]".bright_green(),
+            "[a]".bright_green(),
+            scope,
+            "[n]".red()
+        );
+        let _ = std::io::stdout().flush();
+
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line).ok()? == 0 {
+            return Some(ApprovalAnswer::Deny(None));
+        }
+        let line = line.trim();
+        let (choice, reason) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        Some(match choice.to_lowercase().as_str() {
+            "y" | "yes" => ApprovalAnswer::AllowOnce,
+            "a" | "always" => ApprovalAnswer::AllowSession,
+            _ => {
+                ApprovalAnswer::Deny((!reason.trim().is_empty()).then(|| reason.trim().to_string()))
+            }
+        })
+    }
+}
+
+/// Approval policy plus the calls allowed for the rest of the session
+///
+/// Cloning shares the session allowances, so they survive rebuilding the agent.
+#[derive(Clone)]
+pub struct ApprovalGate {
+    config: Arc<ApprovalConfig>,
+    /// Tool and target of calls answered with "always"
+    session_allowed: Arc<Mutex<HashSet<(String, String)>>>,
+    approver: Arc<dyn Approver>,
+    workspace: Option<Workspace>,
+}
+
+impl ApprovalGate {
+    pub fn new(config: ApprovalConfig, approver: Arc<dyn Approver>) -> Self {
+        Self {
+            config: Arc::new(config),
+            session_allowed: Arc::new(Mutex::new(HashSet::new())),
+            approver,
+            workspace: None,
+        }
+    }
+
+    /// Match `approval.paths` patterns relative to the workspace root the file
+    /// tools resolve paths against, instead of the working directory
+    pub fn with_workspace(&self, workspace: Workspace) -> Self {
+        Self {
+            workspace: Some(workspace),
+            ..self.clone()
+        }
+    }
+
+    /// Same session allowances and approver with a new policy (after `/reload`)
+    pub fn with_config(&self, config: ApprovalConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            ..self.clone()
+        }
+    }
+
+    pub fn config(&self) -> &ApprovalConfig {
+        &self.config
+    }
+
+    /// Calls the user allowed for the rest of the session, as `tool(target)`
+    pub fn session_allowed(&self) -> Vec<String> {
+        let mut calls: Vec<String> = self
+            .session_allowed
+            .lock()
+            .map(|allowed| {
+                allowed
+                    .iter()
+                    .map(|(tool, target)| format!("{}({})", tool, target.replace('\n', ", ")))
+                    .collect()
+            })
+            .unwrap_or_default();
+        calls.sort();
+        calls
+    }
+
+    /// Check a tool call, asking the user if the policy requires it
+    pub async fn check(&self, tool: &str, args: &str) -> Result<(), ApprovalError> {
+        if ApprovalConfig::is_exempt(tool, args) {
+            return Ok(());
+        }
+        let target = ApprovalConfig::target(tool, args);
+        let targets = ApprovalConfig::targets(tool, args);
+
+        // The strictest decision over all targets applies
+        let kind = ApprovalConfig::kind(tool, args);
+        let stateful = ApprovalConfig::is_stateful(tool, args);
+        let decisions: Vec<Decision> = if targets.is_empty() {
+            vec![self.config.decide_without_target(tool, &target)]
+        } else {
+            targets
+                .iter()
+                .map(|target| {
+                    self.config
+                        .decide_as(tool, kind, target, stateful, self.workspace.as_ref())
+                })
+                .collect()
+        };
+        let decision = if decisions.contains(&Decision::Deny) {
+            Decision::Deny
+        } else if decisions.contains(&Decision::Ask) {
+            Decision::Ask
+        } else {
+            Decision::Allow
+        };
+
+        match decision {
+            Decision::Allow => return Ok(()),
+            Decision::Deny => {
+                return Err(ApprovalError::ReadOnly {
+                    tool: tool.to_string(),
+                    target,
+                })
+            }
+            Decision::Ask => {}
+        }
+
+        // "Always" covers the same tool on the same file paths or command only
+        let key = (tool.to_string(), target.clone());
+        if self
+            .session_allowed
+            .lock()
+            .is_ok_and(|allowed| allowed.contains(&key))
+        {
+            return Ok(());
+        }
+
+        // Reading the answer blocks, keep it off the async worker threads
+        let answer = {
+            let approver = self.approver.clone();
+            let (tool, target) = (tool.to_string(), target.clone());
+            tokio::task::spawn_blocking(move || approver.ask(&tool, &target))
+                .await
+                .unwrap_or(None)
+        };
+
+        match answer {
+            Some(ApprovalAnswer::AllowOnce) => Ok(()),
+            Some(ApprovalAnswer::AllowSession) => {
+                if let Ok(mut allowed) = self.session_allowed.lock() {
+                    allowed.insert(key);
+                }
+                Ok(())
+            }
+            Some(ApprovalAnswer::Deny(reason)) => Err(ApprovalError::DeniedByUser {
+                tool: tool.to_string(),
+                target,
+                reason,
+            }),
+            None => Err(ApprovalError::NotInteractive {
+                tool: tool.to_string(),
+                target,
+            }),
+        }
+    }
+}
+
+/// Tool wrapper that asks for approval before running the inner tool
+pub struct ApprovalTool {
+    inner: Box<dyn ToolDyn>,
+    gate: ApprovalGate,
+}
+
+impl ApprovalTool {
+    pub fn new(inner: Box<dyn ToolDyn>, gate: ApprovalGate) -> Self {
+        Self { inner, gate }
+    }
+}
+
+impl ToolDyn for ApprovalTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(
+        &'a self,
+        prompt: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
+
+    fn call<'a>(
+        &'a self,
+        args: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let tool = self.inner.name();
+            if let Err(e) = self.gate.check(&tool, &args).await {
+                println!("\n{} {}({})", "●".red(), "Denied".red(), tool);
+                println!("  └─ {}", e.to_string().dimmed());
+                println!();
+                return Err(ToolError::ToolCallError(Box::new(e)));
+            }
+            self.inner.call(args).await
+        })
+    }
+}
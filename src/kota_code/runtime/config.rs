@@ -1,7 +1,17 @@
 use anyhow::Result;
 use mlua::prelude::*;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::approval::{ApprovalConfig, ApprovalMode};
+use super::config_schema::{validate_config, ConfigDiagnostic};
+use super::hooks::ToolHooks;
+use crate::kota_code::agent::Provider;
+use crate::kota_code::tools::patch::DEFAULT_FUZZ;
+use crate::kota_code::tools::read_file::DEFAULT_MAX_READ_BYTES;
+use crate::kota_code::tools::ExecOptions;
 
 /// Command definition that can be either a string or a Lua function
 #[derive(Debug, Clone)]
@@ -12,28 +22,117 @@ pub enum CommandDef {
     Function(Vec<u8>),
 }
 
+/// MCP server launch definition from the `mcp_servers` table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct McpServerConfig {
+    /// The command to execute (e.g., "uvx", "npx")
+    pub command: String,
+    /// Arguments for the command
+    pub args: Vec<String>,
+    /// Extra environment variables for the server process
+    pub env: HashMap<String, String>,
+    /// Working directory for the server process
+    pub cwd: Option<String>,
+}
+
+/// Configuration layer a value was set by
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Built-in default
+    #[default]
+    Default,
+    /// `$XDG_CONFIG_HOME/kota/config.lua`
+    Global(PathBuf),
+    /// Project `.kota/config.lua` (or the file given with `--config`)
+    Project(PathBuf),
+    /// Command-line flag
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Global(path) => write!(f, "global {}", path.display()),
+            ConfigSource::Project(path) => write!(f, "project {}", path.display()),
+            ConfigSource::Cli => write!(f, "command line"),
+        }
+    }
+}
+
 /// Configuration loaded from Lua config file
 #[derive(Debug, Clone)]
 pub struct KotaConfig {
+    /// LLM provider; `None` guesses it from the model name
+    pub provider: Option<Provider>,
     pub model: String,
     pub api_key: String,
-    pub api_base: String,
+    /// Custom API endpoint; `None` uses the provider's default
+    pub api_base: Option<String>,
     pub temperature: Option<f64>,
+    /// Maximum tokens per completion; `None` uses the agent default
+    pub max_tokens: Option<u64>,
+    /// Maximum number of messages kept in the conversation history
+    pub context_max_messages: usize,
+    /// Token budget for the conversation history; when set, old messages are
+    /// summarized instead of dropped by count
+    pub context_token_budget: Option<usize>,
     pub enabled_tools: Vec<String>,
     pub disabled_tools: Vec<String>,
+    /// Expose no tools at all (`tools = false`)
+    pub disable_all_tools: bool,
     pub commands: HashMap<String, CommandDef>,
+    pub hooks: ToolHooks,
+    pub mcp_servers: HashMap<String, McpServerConfig>,
+    /// Reload config, skills and Lua tools automatically when their files change
+    pub watch: bool,
+    /// When file edits, deletes and commands need the user's approval
+    pub approval: ApprovalConfig,
+    /// Directory the file tools are confined to; `None` uses the current directory
+    pub workspace_root: Option<String>,
+    /// Extra globs, relative to the workspace root, the file tools may not write to
+    pub protected_paths: Vec<String>,
+    /// Default timeout, output limit and environment policy of `exec_cmd` and `shell_session`
+    pub exec: ExecOptions,
+    /// Context lines an `edit_file` hunk may ignore at each end to find a match
+    pub edit_fuzz: usize,
+    /// Bytes of numbered content one `read_file` call returns before it is cut off
+    pub read_max_bytes: usize,
+    /// Layer each value came from, keyed by dotted path (see [`KotaConfig::source_of`])
+    pub sources: BTreeMap<String, ConfigSource>,
+    /// Config files applied, in order
+    pub layers: Vec<ConfigSource>,
+    /// Non-fatal problems found while validating the config files
+    pub warnings: Vec<ConfigDiagnostic>,
 }
 
 impl Default for KotaConfig {
     fn default() -> Self {
         Self {
+            provider: None,
             model: "gpt-4o".to_string(),
             api_key: String::new(),
-            api_base: "https://api.openai.com/v1".to_string(),
-            temperature: Some(0.7),
+            api_base: None,
+            temperature: None,
+            max_tokens: None,
+            context_max_messages: 100,
+            context_token_budget: None,
             enabled_tools: vec![],
             disabled_tools: vec![],
+            disable_all_tools: false,
             commands: HashMap::new(),
+            hooks: ToolHooks::default(),
+            mcp_servers: HashMap::new(),
+            watch: false,
+            approval: ApprovalConfig::default(),
+            workspace_root: None,
+            protected_paths: vec![],
+            exec: ExecOptions::default(),
+            edit_fuzz: DEFAULT_FUZZ,
+            read_max_bytes: DEFAULT_MAX_READ_BYTES,
+            sources: BTreeMap::new(),
+            layers: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 }
@@ -49,11 +148,72 @@ impl KotaConfig {
     ///
     /// Returns a KotaConfig with parsed values
     pub fn from_lua_file<P: AsRef<Path>>(config_path: P) -> Result<Self> {
-        let lua = Lua::new();
         let mut config = Self::default();
+        let source = ConfigSource::Project(config_path.as_ref().to_path_buf());
+        config.apply_lua_file(config_path, source)?;
+        Ok(config)
+    }
+
+    /// Apply a Lua config file on top of the current values
+    ///
+    /// Values set in the file replace the current ones, including the `tools`
+    /// lists; `commands`, `mcp_servers` and `hooks` are merged entry by entry, and
+    /// `workspace.protected` and the `exec.env` lists accumulate. The file is
+    /// validated first: any error rejects the whole file, warnings are kept in `warnings`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - Path to the Lua configuration file
+    /// * `source` - Layer the file belongs to, recorded for every value it sets
+    pub fn apply_lua_file<P: AsRef<Path>>(
+        &mut self,
+        config_path: P,
+        source: ConfigSource,
+    ) -> Result<()> {
+        let (lua, diagnostics) = Self::eval_lua_file(config_path.as_ref())?;
+
+        let (errors, warnings): (Vec<_>, Vec<_>) = diagnostics
+            .into_iter()
+            .partition(ConfigDiagnostic::is_error);
+        if !errors.is_empty() {
+            let report: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+            return Err(anyhow::anyhow!(
+                "Invalid configuration:\n{}",
+                report.join("\n")
+            ));
+        }
+        self.warnings.extend(warnings);
+
+        // Parse the configuration
+        Self::parse_from_lua(&lua, self, &source)?;
+        self.layers.push(source);
+
+        Ok(())
+    }
+
+    /// Validate a Lua config file without applying it
+    ///
+    /// Failures to read or run the file are reported as diagnostics too, so the
+    /// result is empty only for a valid file.
+    pub fn check_lua_file<P: AsRef<Path>>(config_path: P) -> Vec<ConfigDiagnostic> {
+        let config_path = config_path.as_ref();
+        match Self::eval_lua_file(config_path) {
+            Ok((_, diagnostics)) => diagnostics,
+            Err(e) => vec![ConfigDiagnostic {
+                file: Some(config_path.to_path_buf()),
+                ..ConfigDiagnostic::error("", e.to_string())
+            }],
+        }
+    }
+
+    /// Run a config file and validate the table it passed to `kota.setup`
+    ///
+    /// Returns the Lua state holding the captured table in `_kota_config`.
+    fn eval_lua_file(config_path: &Path) -> Result<(Lua, Vec<ConfigDiagnostic>)> {
+        let lua = Lua::new();
 
         // Load and execute the config file to capture the setup call
-        let config_content = std::fs::read_to_string(config_path.as_ref())
+        let config_content = std::fs::read_to_string(config_path)
             .map_err(|e| anyhow::anyhow!("Failed to read config file: {}", e))?;
 
         // Create a custom os.getenv function that reads from Rust environment
@@ -82,110 +242,374 @@ impl KotaConfig {
             lua.create_function(|_, name: String| Ok(std::env::var(&name).ok()))?,
         )?;
 
-        // Execute the config file
+        // Execute the config file, named after its path so errors carry `path:line`
         lua.load(&config_content)
+            .set_name(format!("@{}", config_path.display()))
             .exec()
             .map_err(|e| anyhow::anyhow!("Failed to execute Lua config: {}", e))?;
 
-        // Parse the configuration
-        Self::parse_from_lua(&lua, &mut config)?;
+        let mut diagnostics = match globals.get::<_, LuaValue>("_kota_config")? {
+            LuaValue::Nil => vec![ConfigDiagnostic::error(
+                "",
+                "kota.setup({...}) was not called",
+            )],
+            captured => validate_config(&captured, &config_content),
+        };
+        for diagnostic in &mut diagnostics {
+            diagnostic.file = Some(config_path.to_path_buf());
+        }
+        drop(globals);
 
-        Ok(config)
+        Ok((lua, diagnostics))
     }
 
-    fn parse_from_lua(lua: &Lua, config: &mut KotaConfig) -> Result<()> {
+    fn parse_from_lua(lua: &Lua, config: &mut KotaConfig, source: &ConfigSource) -> Result<()> {
         // Get the captured config
         let captured: LuaTable = lua
             .globals()
             .get("_kota_config")
             .map_err(|e| anyhow::anyhow!("Config not properly initialized: {}", e))?;
 
+        // Parse provider
+        if let Ok(provider) = captured.get::<_, String>("provider") {
+            config.provider = Some(provider.parse()?);
+            config.set_source("provider", source);
+        }
+
         // Parse model
         if let Ok(model) = captured.get::<_, String>("model") {
             config.model = model;
+            config.set_source("model", source);
         }
 
         // Parse api_key - Lua will have already evaluated os.getenv() expressions
         if let Ok(api_key) = captured.get::<_, String>("api_key") {
             config.api_key = api_key;
+            config.set_source("api_key", source);
         }
 
         // Parse api_base - Lua will have already evaluated os.getenv() expressions
         if let Ok(api_base) = captured.get::<_, String>("api_base") {
-            config.api_base = api_base;
+            config.api_base = Some(api_base);
+            config.set_source("api_base", source);
         }
 
         // Parse temperature
         if let Ok(temp) = captured.get::<_, f64>("temperature") {
             config.temperature = Some(temp);
+            config.set_source("temperature", source);
         }
 
-        // Parse tools configuration
-        if let Ok(tools) = captured.get::<_, LuaTable>("tools") {
-            if let Ok(enabled) = tools.get::<&str, LuaTable>("enabled") {
-                for pair in enabled.pairs::<LuaValue, String>() {
-                    if let Ok((_, tool)) = pair {
-                        config.enabled_tools.push(tool);
+        // Parse max_tokens
+        if let Ok(max_tokens) = captured.get::<_, u64>("max_tokens") {
+            config.max_tokens = Some(max_tokens);
+            config.set_source("max_tokens", source);
+        }
+
+        // Parse context configuration
+        if let Ok(context) = captured.get::<_, LuaTable>("context") {
+            if let Ok(max_messages) = context.get::<_, usize>("max_messages") {
+                config.context_max_messages = max_messages;
+                config.set_source("context.max_messages", source);
+            }
+
+            if let Ok(token_budget) = context.get::<_, usize>("token_budget") {
+                config.context_token_budget = Some(token_budget);
+                config.set_source("context.token_budget", source);
+            }
+        }
+
+        // Parse watch
+        if let Ok(watch) = captured.get::<_, bool>("watch") {
+            config.watch = watch;
+            config.set_source("watch", source);
+        }
+
+        // Parse approval configuration - pattern tables are merged like commands
+        if let Ok(approval) = captured.get::<_, LuaTable>("approval") {
+            if let Ok(mode) = approval.get::<_, String>("mode") {
+                config.approval.mode = mode.parse()?;
+                config.set_source("approval.mode", source);
+            }
+
+            if let Ok(tools) = approval.get::<_, LuaTable>("tools") {
+                for (tool, mode) in tools.pairs::<String, String>().flatten() {
+                    config.set_source(format!("approval.tools.{}", tool), source);
+                    config.approval.tools.insert(tool, mode.parse()?);
+                }
+            }
+
+            for (key, rules) in [
+                ("paths", &mut config.approval.paths),
+                ("commands", &mut config.approval.commands),
+            ] {
+                let Ok(table) = approval.get::<_, LuaTable>(key) else {
+                    continue;
+                };
+                for (pattern, mode) in table.pairs::<String, String>().flatten() {
+                    let mode: ApprovalMode = mode.parse()?;
+                    rules.retain(|(existing, _)| *existing != pattern);
+                    config
+                        .sources
+                        .insert(format!("approval.{}.{}", key, pattern), source.clone());
+                    rules.push((pattern, mode));
+                }
+            }
+        }
+
+        // Parse workspace configuration - protected paths accumulate across layers
+        if let Ok(workspace) = captured.get::<_, LuaTable>("workspace") {
+            if let Ok(root) = workspace.get::<_, String>("root") {
+                config.workspace_root = Some(root);
+                config.set_source("workspace.root", source);
+            }
+
+            if let Ok(protected) = workspace.get::<_, LuaTable>("protected") {
+                for (_, pattern) in protected.pairs::<LuaValue, String>().flatten() {
+                    if !config.protected_paths.contains(&pattern) {
+                        config.protected_paths.push(pattern);
+                    }
+                }
+                config.set_source("workspace.protected", source);
+            }
+        }
+
+        // Parse edit_file matching
+        if let Ok(edit) = captured.get::<_, LuaTable>("edit") {
+            if let Ok(fuzz) = edit.get::<_, usize>("fuzz") {
+                config.edit_fuzz = fuzz;
+                config.set_source("edit.fuzz", source);
+            }
+        }
+
+        // Parse read_file limits
+        if let Ok(read) = captured.get::<_, LuaTable>("read") {
+            if let Ok(max_bytes) = read.get::<_, usize>("max_bytes") {
+                config.read_max_bytes = max_bytes;
+                config.set_source("read.max_bytes", source);
+            }
+        }
+
+        // Parse exec_cmd and shell_session limits
+        if let Ok(exec) = captured.get::<_, LuaTable>("exec") {
+            if let Ok(timeout_secs) = exec.get::<_, u64>("timeout_secs") {
+                config.exec.timeout_secs = timeout_secs;
+                config.set_source("exec.timeout_secs", source);
+            }
+
+            if let Ok(max_output_bytes) = exec.get::<_, usize>("max_output_bytes") {
+                config.exec.max_output_bytes = max_output_bytes;
+                config.set_source("exec.max_output_bytes", source);
+            }
+
+            // Environment lists accumulate across layers like protected paths
+            if let Ok(env) = exec.get::<_, LuaTable>("env") {
+                for (key, patterns) in [
+                    ("allow", &mut config.exec.env_allow),
+                    ("deny", &mut config.exec.env_deny),
+                ] {
+                    let Ok(table) = env.get::<_, LuaTable>(key) else {
+                        continue;
+                    };
+                    for (_, pattern) in table.pairs::<LuaValue, String>().flatten() {
+                        if !patterns.contains(&pattern) {
+                            patterns.push(pattern);
+                        }
                     }
+                    config
+                        .sources
+                        .insert(format!("exec.env.{}", key), source.clone());
                 }
             }
+        }
 
-            if let Ok(disabled) = tools.get::<&str, LuaTable>("disabled") {
-                for pair in disabled.pairs::<LuaValue, String>() {
-                    if let Ok((_, tool)) = pair {
-                        config.disabled_tools.push(tool);
+        // Parse tools configuration - `tools = false` disables every tool
+        if let Ok(LuaValue::Boolean(false)) = captured.get::<_, LuaValue>("tools") {
+            config.disable_all_tools = true;
+            config.set_source("tools", source);
+        }
+        // Unlike protected paths and environment lists, tool lists are replaced
+        // by a later layer, so a project can re-enable a tool the global config disables
+        if let Ok(tools) = captured.get::<_, LuaTable>("tools") {
+            for (key, list) in [
+                ("enabled", &mut config.enabled_tools),
+                ("disabled", &mut config.disabled_tools),
+            ] {
+                let Ok(table) = tools.get::<&str, LuaTable>(key) else {
+                    continue;
+                };
+                list.clear();
+                for (_, tool) in table.pairs::<LuaValue, String>().flatten() {
+                    if !list.contains(&tool) {
+                        list.push(tool);
                     }
                 }
+                config
+                    .sources
+                    .insert(format!("tools.{}", key), source.clone());
             }
         }
 
         // Parse commands configuration
         if let Ok(commands) = captured.get::<_, LuaTable>("commands") {
-            for pair in commands.pairs::<String, LuaValue>() {
-                if let Ok((name, value)) = pair {
-                    match value {
-                        LuaValue::String(s) => {
-                            // Simple string command
-                            config
-                                .commands
-                                .insert(name, CommandDef::String(s.to_str()?.to_string()));
-                        }
-                        LuaValue::Function(func) => {
-                            // Function command - dump to bytecode
-                            let bytecode = func.dump(false);
-                            config.commands.insert(name, CommandDef::Function(bytecode));
-                        }
-                        _ => {
-                            // Ignore other types
-                        }
+            for (name, value) in commands.pairs::<String, LuaValue>().flatten() {
+                match value {
+                    LuaValue::String(s) => {
+                        // Simple string command
+                        config.set_source(format!("commands.{}", name), source);
+                        config
+                            .commands
+                            .insert(name, CommandDef::String(s.to_str()?.to_string()));
+                    }
+                    LuaValue::Function(func) => {
+                        // Function command - dump to bytecode
+                        let bytecode = func.dump(false);
+                        config.set_source(format!("commands.{}", name), source);
+                        config.commands.insert(name, CommandDef::Function(bytecode));
+                    }
+                    _ => {
+                        // Rejected by validation
                     }
                 }
             }
         }
 
+        // Parse hooks configuration - functions are dumped to bytecode like commands
+        if let Ok(hooks) = captured.get::<_, LuaTable>("hooks") {
+            if let Ok(func) = hooks.get::<_, LuaFunction>("before_execute") {
+                config.hooks.before_execute = Some(func.dump(false));
+                config.set_source("hooks.before_execute", source);
+            }
+
+            if let Ok(func) = hooks.get::<_, LuaFunction>("after_execute") {
+                config.hooks.after_execute = Some(func.dump(false));
+                config.set_source("hooks.after_execute", source);
+            }
+        }
+
+        // Parse MCP servers configuration
+        if let Ok(servers) = captured.get::<_, LuaTable>("mcp_servers") {
+            for (name, server) in servers.pairs::<String, LuaTable>().flatten() {
+                let Ok(command) = server.get::<_, String>("command") else {
+                    continue;
+                };
+
+                let mut server_config = McpServerConfig {
+                    command,
+                    ..Default::default()
+                };
+
+                if let Ok(args) = server.get::<_, LuaTable>("args") {
+                    for arg in args.sequence_values::<String>().flatten() {
+                        server_config.args.push(arg);
+                    }
+                }
+
+                if let Ok(env) = server.get::<_, LuaTable>("env") {
+                    for (key, value) in env.pairs::<String, String>().flatten() {
+                        server_config.env.insert(key, value);
+                    }
+                }
+
+                if let Ok(cwd) = server.get::<_, String>("cwd") {
+                    server_config.cwd = Some(cwd);
+                }
+
+                config.set_source(format!("mcp_servers.{}", name), source);
+                config.mcp_servers.insert(name, server_config);
+            }
+        }
+
         Ok(())
     }
 
-    /// Load configuration from .kota/config.lua
+    /// Record which layer set a value
+    pub fn set_source(&mut self, key: impl Into<String>, source: &ConfigSource) {
+        self.sources.insert(key.into(), source.clone());
+    }
+
+    /// Layer the effective value of `key` came from
     ///
-    /// # Returns
+    /// Keys are dotted paths such as `model`, `tools.enabled` or `commands.fix`.
+    pub fn source_of(&self, key: &str) -> &ConfigSource {
+        self.sources.get(key).unwrap_or(&ConfigSource::Default)
+    }
+
+    /// Path of the global config file: `$XDG_CONFIG_HOME/kota/config.lua`,
+    /// falling back to `~/.config/kota/config.lua`
+    pub fn global_config_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("kota").join("config.lua"))
+    }
+
+    /// Find the nearest `.kota/config.lua`, walking up from `start`
+    pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(".kota").join("config.lua"))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the layered configuration for the current directory
     ///
-    /// Returns a KotaConfig loaded from .kota/config.lua
+    /// Layers are applied in order: built-in defaults, the global config
+    /// ([`global_config_path`](Self::global_config_path)), then the nearest project
+    /// `.kota/config.lua` found walking up from the current directory. Missing
+    /// files are skipped.
     ///
     /// # Errors
     ///
-    /// Returns an error if the config file doesn't exist or has syntax errors
+    /// Returns an error if a config file has syntax errors
     pub fn load() -> Result<Self> {
-        let config_path = ".kota/config.lua";
+        Self::load_layered(None)
+    }
 
-        if !Path::new(config_path).exists() {
-            return Err(anyhow::anyhow!(
-                "Configuration file not found: {}\n\
-                Please create a .kota/config.lua file. ",
-                config_path
-            ));
+    /// Load the layered configuration, optionally replacing the project layer
+    ///
+    /// # Arguments
+    ///
+    /// * `project_config` - Config file to use instead of searching for
+    ///   `.kota/config.lua` (e.g. from `--config`)
+    pub fn load_layered(project_config: Option<&Path>) -> Result<Self> {
+        let mut config = Self::default();
+        for (path, source) in Self::config_files(project_config)? {
+            config.apply_lua_file(&path, source)?;
+        }
+        Ok(config)
+    }
+
+    /// Config files that exist for the current directory, in the order they apply
+    ///
+    /// # Arguments
+    ///
+    /// * `project_config` - Config file to use instead of searching for
+    ///   `.kota/config.lua`; it is an error if it does not exist
+    pub fn config_files(project_config: Option<&Path>) -> Result<Vec<(PathBuf, ConfigSource)>> {
+        let mut files = Vec::new();
+
+        if let Some(global) = Self::global_config_path().filter(|path| path.is_file()) {
+            files.push((global.clone(), ConfigSource::Global(global)));
+        }
+
+        let project = match project_config {
+            Some(path) => {
+                if !path.is_file() {
+                    return Err(anyhow::anyhow!(
+                        "Configuration file not found: {}",
+                        path.display()
+                    ));
+                }
+                Some(path.to_path_buf())
+            }
+            None => Self::find_project_config(&std::env::current_dir()?),
+        };
+        if let Some(project) = project {
+            files.push((project.clone(), ConfigSource::Project(project)));
         }
 
-        Self::from_lua_file(config_path)
+        Ok(files)
     }
 }
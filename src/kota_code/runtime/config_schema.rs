@@ -0,0 +1,575 @@
+//! Schema for `kota.setup({...})` and the validation pass run before parsing
+//!
+//! Every problem is reported as a [`ConfigDiagnostic`] carrying the dotted key path
+//! (e.g. `tools.enabled[2]`) and, when it can be found, the line in the Lua file.
+
+use mlua::prelude::*;
+use std::fmt;
+use std::path::PathBuf;
+
+use super::approval::{ApprovalConfig, ApprovalMode};
+use crate::kota_code::agent::Provider;
+
+/// How serious a config problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config cannot be loaded
+    Error,
+    /// The value is ignored (e.g. an unknown key)
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A single problem found in a config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiagnostic {
+    pub severity: Severity,
+    /// Config file the problem was found in
+    pub file: Option<PathBuf>,
+    /// Line in the config file (1-based), when it can be located
+    pub line: Option<usize>,
+    /// Dotted key path, empty for problems with the file as a whole
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigDiagnostic {
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            file: None,
+            line: None,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(path, message)
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+            if let Some(line) = self.line {
+                write!(f, "{}:", line)?;
+            }
+            write!(f, " ")?;
+        }
+        write!(f, "{}: ", self.severity)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Expected shape of a config value
+#[derive(Debug, Clone, Copy)]
+pub enum Schema {
+    String,
+    /// String accepted by a parser, which returns the error message otherwise
+    ParsedString(fn(&str) -> Result<(), String>),
+    /// Number within an inclusive range
+    Number {
+        min: f64,
+        max: f64,
+    },
+    /// Integer no smaller than `min`
+    Integer {
+        min: i64,
+    },
+    Boolean,
+    /// Only the value `false` (e.g. `tools = false`)
+    False,
+    Function,
+    /// Array of strings
+    StringList,
+    /// Table with a fixed set of keys
+    Table(&'static [Field]),
+    /// Table mapping arbitrary names to values of one shape
+    Map(&'static Schema),
+    /// Like [`Schema::Map`], keyed by glob patterns
+    GlobMap(&'static Schema),
+    /// Any one of the listed shapes
+    OneOf(&'static [Schema]),
+}
+
+/// A key of a [`Schema::Table`]
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub schema: Schema,
+    pub required: bool,
+}
+
+const fn optional(name: &'static str, schema: Schema) -> Field {
+    Field {
+        name,
+        schema,
+        required: false,
+    }
+}
+
+const fn required(name: &'static str, schema: Schema) -> Field {
+    Field {
+        name,
+        schema,
+        required: true,
+    }
+}
+
+fn parse_provider(value: &str) -> Result<(), String> {
+    value
+        .parse::<Provider>()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_approval_mode(value: &str) -> Result<(), String> {
+    value
+        .parse::<ApprovalMode>()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Approval mode per glob pattern
+const APPROVAL_PATTERNS: Schema = Schema::GlobMap(&Schema::ParsedString(parse_approval_mode));
+
+/// Schema of the table passed to `kota.setup`
+pub static CONFIG_SCHEMA: Schema = Schema::Table(&[
+    optional("provider", Schema::ParsedString(parse_provider)),
+    optional("model", Schema::String),
+    optional("api_key", Schema::String),
+    optional("api_base", Schema::String),
+    optional("temperature", Schema::Number { min: 0.0, max: 2.0 }),
+    optional("max_tokens", Schema::Integer { min: 1 }),
+    optional(
+        "context",
+        Schema::Table(&[
+            optional("max_messages", Schema::Integer { min: 1 }),
+            optional("token_budget", Schema::Integer { min: 1 }),
+        ]),
+    ),
+    optional("watch", Schema::Boolean),
+    optional(
+        "approval",
+        Schema::Table(&[
+            optional("mode", Schema::ParsedString(parse_approval_mode)),
+            optional(
+                "tools",
+                Schema::Map(&Schema::ParsedString(parse_approval_mode)),
+            ),
+            optional("paths", APPROVAL_PATTERNS),
+            optional("commands", APPROVAL_PATTERNS),
+        ]),
+    ),
+    optional(
+        "workspace",
+        Schema::Table(&[
+            optional("root", Schema::String),
+            optional("protected", Schema::StringList),
+        ]),
+    ),
+    optional(
+        "edit",
+        Schema::Table(&[optional("fuzz", Schema::Integer { min: 0 })]),
+    ),
+    optional(
+        "read",
+        Schema::Table(&[optional("max_bytes", Schema::Integer { min: 1 })]),
+    ),
+    optional(
+        "exec",
+        Schema::Table(&[
+            optional("timeout_secs", Schema::Integer { min: 1 }),
+            optional("max_output_bytes", Schema::Integer { min: 1 }),
+            optional(
+                "env",
+                Schema::Table(&[
+                    optional("allow", Schema::StringList),
+                    optional("deny", Schema::StringList),
+                ]),
+            ),
+        ]),
+    ),
+    optional(
+        "tools",
+        Schema::OneOf(&[
+            Schema::False,
+            Schema::Table(&[
+                optional("enabled", Schema::StringList),
+                optional("disabled", Schema::StringList),
+            ]),
+        ]),
+    ),
+    optional(
+        "commands",
+        Schema::Map(&Schema::OneOf(&[Schema::String, Schema::Function])),
+    ),
+    optional(
+        "hooks",
+        Schema::Table(&[
+            optional("before_execute", Schema::Function),
+            optional("after_execute", Schema::Function),
+        ]),
+    ),
+    optional(
+        "mcp_servers",
+        Schema::Map(&Schema::Table(&[
+            required("command", Schema::String),
+            optional("args", Schema::StringList),
+            optional("env", Schema::Map(&Schema::String)),
+            optional("cwd", Schema::String),
+        ])),
+    ),
+]);
+
+impl Schema {
+    /// Human readable name of the expected type
+    fn describe(&self) -> String {
+        match self {
+            Schema::String | Schema::ParsedString(_) => "string".to_string(),
+            Schema::Number { .. } => "number".to_string(),
+            Schema::Integer { .. } => "integer".to_string(),
+            Schema::Boolean => "boolean".to_string(),
+            Schema::False => "false".to_string(),
+            Schema::Function => "function".to_string(),
+            Schema::StringList => "list of strings".to_string(),
+            Schema::Table(_) | Schema::Map(_) | Schema::GlobMap(_) => "table".to_string(),
+            Schema::OneOf(options) => options
+                .iter()
+                .map(Schema::describe)
+                .collect::<Vec<_>>()
+                .join(" or "),
+        }
+    }
+
+    /// Check only the Lua type, used to pick an option of [`Schema::OneOf`]
+    fn accepts_type(&self, value: &LuaValue) -> bool {
+        match self {
+            Schema::String | Schema::ParsedString(_) => matches!(value, LuaValue::String(_)),
+            Schema::Number { .. } | Schema::Integer { .. } => {
+                matches!(value, LuaValue::Integer(_) | LuaValue::Number(_))
+            }
+            Schema::Boolean => matches!(value, LuaValue::Boolean(_)),
+            Schema::False => matches!(value, LuaValue::Boolean(false)),
+            Schema::Function => matches!(value, LuaValue::Function(_)),
+            Schema::StringList | Schema::Table(_) | Schema::Map(_) | Schema::GlobMap(_) => {
+                matches!(value, LuaValue::Table(_))
+            }
+            Schema::OneOf(options) => options.iter().any(|option| option.accepts_type(value)),
+        }
+    }
+}
+
+/// Name of a Lua value's type as shown in diagnostics
+fn type_name(value: &LuaValue) -> &'static str {
+    match value {
+        LuaValue::Integer(_) | LuaValue::Number(_) => "number",
+        other => other.type_name(),
+    }
+}
+
+/// Validate the table passed to `kota.setup` against [`CONFIG_SCHEMA`]
+///
+/// # Arguments
+///
+/// * `config` - The captured `kota.setup` argument
+/// * `source` - Lua source of the config file, used to locate key paths
+pub fn validate_config(config: &LuaValue, source: &str) -> Vec<ConfigDiagnostic> {
+    let mut validator = Validator {
+        lines: source.lines().collect(),
+        diagnostics: Vec::new(),
+    };
+    validator.check(config, &CONFIG_SCHEMA, "");
+
+    // Table iteration order is unspecified, report in source order
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by(|a, b| {
+        (a.line.unwrap_or(usize::MAX), &a.path).cmp(&(b.line.unwrap_or(usize::MAX), &b.path))
+    });
+    diagnostics
+}
+
+struct Validator<'s> {
+    lines: Vec<&'s str>,
+    diagnostics: Vec<ConfigDiagnostic>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, mut diagnostic: ConfigDiagnostic, value: Option<&LuaValue>) {
+        // Functions know where they were defined, other values are searched for by key
+        diagnostic.line = match value {
+            Some(LuaValue::Function(func)) => func.info().line_defined,
+            _ => None,
+        }
+        .or_else(|| self.line_of(&diagnostic.path));
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn mismatch(&mut self, path: &str, schema: &Schema, value: &LuaValue) {
+        self.report(
+            ConfigDiagnostic::error(
+                path,
+                format!("expected {}, got {}", schema.describe(), type_name(value)),
+            ),
+            Some(value),
+        );
+    }
+
+    fn check(&mut self, value: &LuaValue, schema: &Schema, path: &str) {
+        match schema {
+            Schema::String => {
+                if !matches!(value, LuaValue::String(_)) {
+                    self.mismatch(path, schema, value);
+                }
+            }
+            Schema::ParsedString(parse) => match value {
+                LuaValue::String(s) => {
+                    if let Err(message) = parse(&s.to_string_lossy()) {
+                        self.report(ConfigDiagnostic::error(path, message), Some(value));
+                    }
+                }
+                _ => self.mismatch(path, schema, value),
+            },
+            Schema::Number { min, max } => match value {
+                LuaValue::Integer(_) | LuaValue::Number(_) => {
+                    let number = match value {
+                        LuaValue::Integer(i) => *i as f64,
+                        LuaValue::Number(n) => *n,
+                        _ => unreachable!(),
+                    };
+                    if number < *min || number > *max {
+                        self.report(
+                            ConfigDiagnostic::error(
+                                path,
+                                format!("must be between {} and {}, got {}", min, max, number),
+                            ),
+                            Some(value),
+                        );
+                    }
+                }
+                _ => self.mismatch(path, schema, value),
+            },
+            Schema::Integer { min } => {
+                let integer = match value {
+                    LuaValue::Integer(i) => Some(*i),
+                    LuaValue::Number(n) if n.fract() == 0.0 => Some(*n as i64),
+                    LuaValue::Number(_) => None,
+                    _ => return self.mismatch(path, schema, value),
+                };
+                match integer {
+                    Some(i) if i < *min => self.report(
+                        ConfigDiagnostic::error(
+                            path,
+                            format!("must be at least {}, got {}", min, i),
+                        ),
+                        Some(value),
+                    ),
+                    Some(_) => {}
+                    None => self.mismatch(path, schema, value),
+                }
+            }
+            Schema::Boolean | Schema::False | Schema::Function => {
+                if !schema.accepts_type(value) {
+                    self.mismatch(path, schema, value);
+                }
+            }
+            Schema::StringList => {
+                let LuaValue::Table(table) = value else {
+                    return self.mismatch(path, schema, value);
+                };
+                for (key, item) in table.clone().pairs::<LuaValue, LuaValue>().flatten() {
+                    match key {
+                        LuaValue::Integer(index) => {
+                            let item_path = format!("{}[{}]", path, index);
+                            self.check(&item, &Schema::String, &item_path);
+                        }
+                        key => self.report(
+                            ConfigDiagnostic::error(
+                                path,
+                                format!(
+                                    "expected a list of strings, found key {}",
+                                    Self::key_name(&key)
+                                ),
+                            ),
+                            None,
+                        ),
+                    }
+                }
+            }
+            Schema::Table(fields) => {
+                let LuaValue::Table(table) = value else {
+                    return self.mismatch(path, schema, value);
+                };
+                for (key, item) in table.clone().pairs::<LuaValue, LuaValue>().flatten() {
+                    let name = Self::key_name(&key);
+                    let item_path = Self::join(path, &name);
+                    match fields.iter().find(|field| field.name == name) {
+                        Some(field) => self.check(&item, &field.schema, &item_path),
+                        None => {
+                            let mut message = "unknown key".to_string();
+                            if let Some(similar) = Self::suggest(&name, fields) {
+                                message.push_str(&format!(", did you mean `{}`?", similar));
+                            }
+                            self.report(ConfigDiagnostic::warning(item_path, message), None);
+                        }
+                    }
+                }
+                for field in fields.iter().filter(|field| field.required) {
+                    if matches!(table.get::<_, LuaValue>(field.name), Ok(LuaValue::Nil)) {
+                        // Entries missing a required key are skipped when parsing
+                        self.report(
+                            ConfigDiagnostic::warning(
+                                path,
+                                format!("missing required key `{}`, entry is ignored", field.name),
+                            ),
+                            None,
+                        );
+                    }
+                }
+            }
+            Schema::Map(inner) | Schema::GlobMap(inner) => {
+                let LuaValue::Table(table) = value else {
+                    return self.mismatch(path, schema, value);
+                };
+                for (key, item) in table.clone().pairs::<LuaValue, LuaValue>().flatten() {
+                    match key {
+                        LuaValue::String(_) => {
+                            let name = Self::key_name(&key);
+                            let item_path = Self::join(path, &name);
+                            if let Schema::GlobMap(_) = schema {
+                                if let Err(message) = ApprovalConfig::check_pattern(&name) {
+                                    self.report(
+                                        ConfigDiagnostic::error(
+                                            &item_path,
+                                            format!("invalid pattern: {}", message),
+                                        ),
+                                        None,
+                                    );
+                                    continue;
+                                }
+                            }
+                            self.check(&item, inner, &item_path);
+                        }
+                        key => self.report(
+                            ConfigDiagnostic::error(
+                                path,
+                                format!("keys must be names, found {}", Self::key_name(&key)),
+                            ),
+                            None,
+                        ),
+                    }
+                }
+            }
+            Schema::OneOf(options) => {
+                match options.iter().find(|option| option.accepts_type(value)) {
+                    Some(option) => self.check(value, option, path),
+                    None => self.mismatch(path, schema, value),
+                }
+            }
+        }
+    }
+
+    fn key_name(key: &LuaValue) -> String {
+        match key {
+            LuaValue::String(s) => s.to_string_lossy().to_string(),
+            LuaValue::Integer(i) => format!("[{}]", i),
+            other => format!("<{}>", type_name(other)),
+        }
+    }
+
+    fn join(path: &str, name: &str) -> String {
+        if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", path, name)
+        }
+    }
+
+    /// Closest known key for a misspelled one
+    fn suggest(name: &str, fields: &[Field]) -> Option<&'static str> {
+        fields
+            .iter()
+            .map(|field| (field.name, edit_distance(name, field.name)))
+            .filter(|(_, distance)| *distance <= 2)
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(name, _)| name)
+    }
+
+    /// Find the line of a key path by searching the source for each segment in turn
+    ///
+    /// mlua does not keep positions for table fields, so this looks for `name =` or
+    /// `["name"] =`, starting from the line where the parent key was found.
+    fn line_of(&self, path: &str) -> Option<usize> {
+        let mut line = 0;
+        let mut found = false;
+        for segment in path.split('.') {
+            let name = segment.split('[').next().unwrap_or_default();
+            if name.is_empty() {
+                continue;
+            }
+            let offset = self.lines[line..]
+                .iter()
+                .position(|text| Self::assigns(text, name))?;
+            line += offset;
+            found = true;
+        }
+        found.then_some(line + 1)
+    }
+
+    /// Check whether a source line assigns the key `name`
+    fn assigns(text: &str, name: &str) -> bool {
+        let text = text.split("--").next().unwrap_or_default();
+        for quoted in [format!("[\"{}\"]", name), format!("['{}']", name)] {
+            if let Some(index) = text.find(&quoted) {
+                if Self::followed_by_assign(&text[index + quoted.len()..]) {
+                    return true;
+                }
+            }
+        }
+        text.match_indices(name).any(|(index, _)| {
+            let before = text[..index].chars().next_back();
+            let is_word_start = !before.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            is_word_start && Self::followed_by_assign(&text[index + name.len()..])
+        })
+    }
+
+    fn followed_by_assign(rest: &str) -> bool {
+        let rest = rest.trim_start();
+        rest.starts_with('=') && !rest.starts_with("==")
+    }
+}
+
+/// Levenshtein distance, used for "did you mean" suggestions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -120,7 +120,7 @@ impl ToolDyn for LuaDynTool {
 }
 
 // Helper: Convert JSON to Lua value
-fn json_to_lua<'lua>(lua: &'lua Lua, json: &JsonValue) -> LuaResult<LuaValue<'lua>> {
+pub(crate) fn json_to_lua<'lua>(lua: &'lua Lua, json: &JsonValue) -> LuaResult<LuaValue<'lua>> {
     match json {
         JsonValue::Null => Ok(LuaValue::Nil),
         JsonValue::Bool(b) => Ok(LuaValue::Boolean(*b)),
@@ -152,7 +152,7 @@ fn json_to_lua<'lua>(lua: &'lua Lua, json: &JsonValue) -> LuaResult<LuaValue<'lu
 }
 
 // Helper: Convert Lua value to JSON
-fn lua_to_json(value: &LuaValue) -> LuaResult<JsonValue> {
+pub(crate) fn lua_to_json(value: &LuaValue) -> LuaResult<JsonValue> {
     match value {
         LuaValue::Nil => Ok(JsonValue::Null),
         LuaValue::Boolean(b) => Ok(JsonValue::Bool(*b)),
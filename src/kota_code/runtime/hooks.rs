@@ -1,5 +1,14 @@
+use anyhow::Result;
+use colored::*;
+use mlua::prelude::*;
 use rig::agent::StreamingPromptHook;
-use rig::completion::CompletionModel;
+use rig::completion::{CompletionModel, ToolDefinition};
+use rig::tool::{ToolDyn, ToolError};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use thiserror::Error;
+
+use super::dyn_tools_loader::dyn_tool::{json_to_lua, lua_to_json};
 
 /// Session-aware hook that logs tool calls and completions with session context
 #[derive(Clone)]
@@ -14,3 +23,239 @@ impl SessionIdHook {
 }
 
 impl<M: CompletionModel> StreamingPromptHook<M> for SessionIdHook {}
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Tool call '{tool}' was blocked by before_execute hook: {reason}")]
+    Vetoed { tool: String, reason: String },
+    /// A tool error as rewritten by the `after_execute` hook
+    #[error("{0}")]
+    ToolFailed(String),
+    #[error("{hook} hook failed for tool '{tool}': {message}")]
+    Failed {
+        hook: &'static str,
+        tool: String,
+        message: String,
+    },
+}
+
+/// Outcome of running the `before_execute` hook
+#[derive(Debug, Clone, PartialEq)]
+pub enum BeforeExecute {
+    /// Run the tool with these (possibly rewritten) JSON arguments
+    Continue(String),
+    /// Do not run the tool, report the reason to the model instead
+    Veto(String),
+}
+
+/// Tool lifecycle hooks defined in the `hooks` table of `.kota/config.lua`
+///
+/// Both hooks are stored as Lua bytecode and executed in a fresh Lua state on
+/// every tool call, so they cannot capture local variables from the config file.
+///
+/// - `before_execute(tool, args)` returns `nil`/`true` to continue, `false[, reason]`
+///   to veto the call, or a table to replace the arguments.
+/// - `after_execute(result, tool, args, err)` returns `nil` to keep the result, or a
+///   string or table to replace what the model sees. For a failed call `result` is
+///   `nil` and `err` is the error message, which the returned value replaces.
+#[derive(Debug, Clone, Default)]
+pub struct ToolHooks {
+    pub before_execute: Option<Vec<u8>>,
+    pub after_execute: Option<Vec<u8>>,
+}
+
+impl ToolHooks {
+    /// Check whether any hook is defined
+    pub fn is_empty(&self) -> bool {
+        self.before_execute.is_none() && self.after_execute.is_none()
+    }
+
+    /// Run the `before_execute` hook for a tool call
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Name of the tool about to be called
+    /// * `args` - JSON arguments produced by the model
+    pub fn before_execute(&self, tool: &str, args: &str) -> Result<BeforeExecute> {
+        let Some(bytecode) = &self.before_execute else {
+            return Ok(BeforeExecute::Continue(args.to_string()));
+        };
+
+        let lua = Lua::new();
+        let func: LuaFunction = lua.load(bytecode.as_slice()).into_function()?;
+        let lua_args = Self::json_str_to_lua(&lua, args)?;
+
+        let (decision, reason): (LuaValue, Option<String>) = func.call((tool, lua_args))?;
+
+        match decision {
+            LuaValue::Nil | LuaValue::Boolean(true) => {
+                Ok(BeforeExecute::Continue(args.to_string()))
+            }
+            LuaValue::Boolean(false) => Ok(BeforeExecute::Veto(
+                reason.unwrap_or_else(|| "denied by project policy".to_string()),
+            )),
+            LuaValue::Table(_) => {
+                let new_args = lua_to_json(&decision)?;
+                Ok(BeforeExecute::Continue(serde_json::to_string(&new_args)?))
+            }
+            other => Err(anyhow::anyhow!(
+                "before_execute must return nil, a boolean or a table, got {}",
+                other.type_name()
+            )),
+        }
+    }
+
+    /// Run the `after_execute` hook on a successful tool result
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Name of the tool that was called
+    /// * `args` - JSON arguments the tool was called with
+    /// * `result` - Tool output that will be sent to the model
+    pub fn after_execute(&self, tool: &str, args: &str, result: &str) -> Result<String> {
+        Ok(self
+            .run_after_execute(tool, args, Ok(result))?
+            .unwrap_or_else(|| result.to_string()))
+    }
+
+    /// Run the `after_execute` hook on a failed tool call
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Name of the tool that was called
+    /// * `args` - JSON arguments the tool was called with
+    /// * `error` - Error message that will be sent to the model
+    ///
+    /// # Returns
+    ///
+    /// The error message to report, rewritten by the hook or unchanged
+    pub fn after_execute_error(&self, tool: &str, args: &str, error: &str) -> Result<String> {
+        Ok(self
+            .run_after_execute(tool, args, Err(error))?
+            .unwrap_or_else(|| error.to_string()))
+    }
+
+    /// Call `after_execute`, returning its replacement or `None` to keep the original
+    fn run_after_execute(
+        &self,
+        tool: &str,
+        args: &str,
+        outcome: std::result::Result<&str, &str>,
+    ) -> Result<Option<String>> {
+        let Some(bytecode) = &self.after_execute else {
+            return Ok(None);
+        };
+
+        let lua = Lua::new();
+        let func: LuaFunction = lua.load(bytecode.as_slice()).into_function()?;
+        let lua_args = Self::json_str_to_lua(&lua, args)?;
+        let (lua_result, error) = match outcome {
+            Ok(result) => (Self::json_str_to_lua(&lua, result)?, None),
+            Err(error) => (LuaValue::Nil, Some(error)),
+        };
+
+        let replacement: LuaValue = func.call((lua_result, tool, lua_args, error))?;
+
+        match replacement {
+            LuaValue::Nil => Ok(None),
+            LuaValue::String(s) => Ok(Some(s.to_str()?.to_string())),
+            other => Ok(Some(serde_json::to_string(&lua_to_json(&other)?)?)),
+        }
+    }
+
+    /// Decode a JSON string into a Lua value, falling back to a plain Lua string
+    fn json_str_to_lua<'lua>(lua: &'lua Lua, value: &str) -> LuaResult<LuaValue<'lua>> {
+        match serde_json::from_str::<JsonValue>(value) {
+            Ok(json) => json_to_lua(lua, &json),
+            Err(_) => Ok(LuaValue::String(lua.create_string(value)?)),
+        }
+    }
+}
+
+/// Tool wrapper that runs the configured [`ToolHooks`] around every call
+///
+/// Works for any [`ToolDyn`], so built-in tools, Lua tools and MCP tools are all
+/// covered once they are in the [`ToolRegistry`](super::ToolRegistry).
+pub struct HookedTool {
+    inner: Box<dyn ToolDyn>,
+    hooks: Arc<ToolHooks>,
+}
+
+impl HookedTool {
+    pub fn new(inner: Box<dyn ToolDyn>, hooks: Arc<ToolHooks>) -> Self {
+        Self { inner, hooks }
+    }
+}
+
+impl ToolDyn for HookedTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(
+        &'a self,
+        prompt: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
+
+    fn call<'a>(
+        &'a self,
+        args: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let tool = self.inner.name();
+
+            let args = match self.hooks.before_execute(&tool, &args) {
+                Ok(BeforeExecute::Continue(args)) => args,
+                Ok(BeforeExecute::Veto(reason)) => {
+                    println!("\n{} {}({})", "●".yellow(), "Blocked".yellow(), tool);
+                    println!("  └─ {}", reason.dimmed());
+                    println!();
+                    return Err(ToolError::ToolCallError(Box::new(HookError::Vetoed {
+                        tool,
+                        reason,
+                    })));
+                }
+                Err(e) => {
+                    return Err(ToolError::ToolCallError(Box::new(HookError::Failed {
+                        hook: "before_execute",
+                        tool,
+                        message: e.to_string(),
+                    })));
+                }
+            };
+
+            let result = match self.inner.call(args.clone()).await {
+                Ok(result) => result,
+                Err(error) => {
+                    if self.hooks.after_execute.is_none() {
+                        return Err(error);
+                    }
+                    let error = error.to_string();
+                    return Err(ToolError::ToolCallError(Box::new(
+                        match self.hooks.after_execute_error(&tool, &args, &error) {
+                            Ok(message) => HookError::ToolFailed(message),
+                            // Keep the tool's own error visible when the hook breaks on it
+                            Err(e) => HookError::Failed {
+                                hook: "after_execute",
+                                tool,
+                                message: format!("{} (tool error: {})", e, error),
+                            },
+                        },
+                    )));
+                }
+            };
+
+            self.hooks
+                .after_execute(&tool, &args, &result)
+                .map_err(|e| {
+                    ToolError::ToolCallError(Box::new(HookError::Failed {
+                        hook: "after_execute",
+                        tool,
+                        message: e.to_string(),
+                    }))
+                })
+        })
+    }
+}
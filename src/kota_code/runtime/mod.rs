@@ -1,8 +1,17 @@
+pub mod approval;
 pub mod config;
+pub mod config_schema;
 pub mod dyn_tools_loader;
 pub mod hooks;
+pub mod tool_log;
 pub mod tool_registry;
 
-pub use config::{CommandDef, KotaConfig};
-pub use hooks::SessionIdHook;
-pub use tool_registry::ToolRegistry;
+pub use approval::{
+    ApprovalAnswer, ApprovalConfig, ApprovalError, ApprovalGate, ApprovalMode, ApprovalTool,
+    Approver, Decision, TerminalApprover, ToolKind,
+};
+pub use config::{CommandDef, ConfigSource, KotaConfig, McpServerConfig};
+pub use config_schema::{ConfigDiagnostic, Severity};
+pub use hooks::{BeforeExecute, HookError, HookedTool, SessionIdHook, ToolHooks};
+pub use tool_log::{LoggedTool, ToolCallLog, ToolCallRecord};
+pub use tool_registry::{ToolFilter, ToolRegistry};
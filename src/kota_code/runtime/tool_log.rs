@@ -0,0 +1,93 @@
+use rig::completion::ToolDefinition;
+use rig::tool::{ToolDyn, ToolError};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::sync::{Arc, Mutex};
+
+/// One tool call made by the agent
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolCallRecord {
+    pub name: String,
+    /// Arguments as produced by the model (before any `before_execute` rewrite)
+    pub arguments: JsonValue,
+    pub success: bool,
+    /// Error message returned to the model when the call failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Shared log of the tool calls made by an agent, in call order
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallLog {
+    records: Arc<Mutex<Vec<ToolCallRecord>>>,
+}
+
+impl ToolCallLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, record: ToolCallRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+
+    /// Get a copy of all records
+    pub fn records(&self) -> Vec<ToolCallRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Remove and return all records
+    pub fn take(&self) -> Vec<ToolCallRecord> {
+        std::mem::take(&mut *self.records.lock().unwrap())
+    }
+
+    /// Check whether any recorded call failed
+    pub fn has_failures(&self) -> bool {
+        self.records.lock().unwrap().iter().any(|r| !r.success)
+    }
+}
+
+/// Tool wrapper that records every call in a [`ToolCallLog`]
+pub struct LoggedTool {
+    inner: Box<dyn ToolDyn>,
+    log: ToolCallLog,
+}
+
+impl LoggedTool {
+    pub fn new(inner: Box<dyn ToolDyn>, log: ToolCallLog) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl ToolDyn for LoggedTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(
+        &'a self,
+        prompt: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
+
+    fn call<'a>(
+        &'a self,
+        args: String,
+    ) -> rig::wasm_compat::WasmBoxedFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let arguments =
+                serde_json::from_str(&args).unwrap_or_else(|_| JsonValue::String(args.clone()));
+            let result = self.inner.call(args).await;
+
+            self.log.record(ToolCallRecord {
+                name: self.inner.name(),
+                arguments,
+                success: result.is_ok(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+
+            result
+        })
+    }
+}
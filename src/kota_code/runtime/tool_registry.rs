@@ -1,5 +1,58 @@
 use rig::tool::ToolDyn;
 
+/// Allow-list and deny-list of tool names, from `tools.enabled` / `tools.disabled`
+///
+/// An empty allow-list allows every tool. The deny-list always wins.
+#[derive(Debug, Clone, Default)]
+pub struct ToolFilter {
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+    /// Reject every tool, regardless of the lists
+    pub disable_all: bool,
+}
+
+impl ToolFilter {
+    /// Create a new tool filter
+    pub fn new(enabled: Vec<String>, disabled: Vec<String>) -> Self {
+        Self {
+            enabled,
+            disabled,
+            disable_all: false,
+        }
+    }
+
+    /// Create a filter that rejects every tool
+    pub fn none() -> Self {
+        Self {
+            disable_all: true,
+            ..Self::default()
+        }
+    }
+
+    /// Check whether a tool with the given name may be registered
+    pub fn is_allowed(&self, name: &str) -> bool {
+        if self.disable_all {
+            return false;
+        }
+        let enabled = self.enabled.is_empty() || self.enabled.iter().any(|n| n == name);
+        enabled && !self.disabled.iter().any(|n| n == name)
+    }
+
+    /// Names in the filter that do not match any of the known tool names
+    pub fn unknown_names(&self, known: &[String]) -> Vec<&str> {
+        let mut unknown: Vec<&str> = self
+            .enabled
+            .iter()
+            .chain(self.disabled.iter())
+            .filter(|name| !known.contains(name))
+            .map(String::as_str)
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        unknown
+    }
+}
+
 pub struct ToolRegistry {
     tools: Vec<Box<dyn ToolDyn>>,
 }
@@ -37,6 +90,16 @@ impl ToolRegistry {
         &mut self.tools
     }
 
+    /// Get the names of all registered tools
+    pub fn names(&self) -> Vec<String> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    /// Keep only the tools allowed by the filter
+    pub fn apply_filter(&mut self, filter: &ToolFilter) {
+        self.tools.retain(|tool| filter.is_allowed(&tool.name()));
+    }
+
     /// Get the number of registered tools
     pub fn len(&self) -> usize {
         self.tools.len()
@@ -21,6 +21,12 @@ pub struct SkillManager {
     skills_dir: PathBuf,
 }
 
+impl Default for SkillManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SkillManager {
     pub fn new() -> Self {
         let skills_dir = PathBuf::from(".kota/skills");
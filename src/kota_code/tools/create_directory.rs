@@ -1,3 +1,4 @@
+use super::workspace::{confine, Access, Workspace};
 use super::FileToolError;
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
@@ -97,14 +98,23 @@ impl Tool for CreateDirectoryTool {
 #[derive(Deserialize, Serialize, Default)]
 pub struct WrappedCreateDirectoryTool {
     inner: CreateDirectoryTool,
+    #[serde(skip)]
+    workspace: Option<Workspace>,
 }
 
 impl WrappedCreateDirectoryTool {
     pub fn new() -> Self {
         Self {
             inner: CreateDirectoryTool,
+            workspace: None,
         }
     }
+
+    /// Confine `dir_path` to a workspace root
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
 }
 
 impl Tool for WrappedCreateDirectoryTool {
@@ -118,10 +128,13 @@ impl Tool for WrappedCreateDirectoryTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
         println!("\n{} CreateDir({})", "●".bright_green(), args.dir_path);
 
-        let result = self.inner.call(args).await;
+        let result = match confine(self.workspace.as_ref(), &mut args.dir_path, Access::Write) {
+            Ok(()) => self.inner.call(args).await,
+            Err(e) => Err(e),
+        };
 
         match &result {
             Ok(output) => {
@@ -1,4 +1,6 @@
+use super::workspace::{confine, Access, Workspace};
 use super::FileToolError;
+use crate::kota_code::checkpoint::{snapshot_before_write, CheckpointManager};
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
@@ -77,14 +79,32 @@ impl Tool for DeleteFileTool {
 #[derive(Deserialize, Serialize, Default)]
 pub struct WrappedDeleteFileTool {
     inner: DeleteFileTool,
+    #[serde(skip)]
+    workspace: Option<Workspace>,
+    #[serde(skip)]
+    checkpoints: Option<CheckpointManager>,
 }
 
 impl WrappedDeleteFileTool {
     pub fn new() -> Self {
         Self {
             inner: DeleteFileTool,
+            workspace: None,
+            checkpoints: None,
         }
     }
+
+    /// Confine `file_path` to a workspace root
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Snapshot the file before changing it, so the change can be undone
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointManager) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
 }
 
 impl Tool for WrappedDeleteFileTool {
@@ -98,10 +118,15 @@ impl Tool for WrappedDeleteFileTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
         println!("\n{} Delete({})", "●".bright_green(), args.file_path);
 
-        let result = self.inner.call(args).await;
+        let result = match confine(self.workspace.as_ref(), &mut args.file_path, Access::Write)
+            .and_then(|()| snapshot_before_write(self.checkpoints.as_ref(), &args.file_path))
+        {
+            Ok(()) => self.inner.call(args).await,
+            Err(e) => Err(e),
+        };
 
         match &result {
             Ok(_output) => {
@@ -0,0 +1,92 @@
+use colored::*;
+use similar::{Algorithm, ChangeTag, TextDiff};
+use std::time::Duration;
+
+/// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// Lines kept in a rendered diff; the rest are counted in a final line
+const MAX_DIFF_LINES: usize = 200;
+
+/// Lines of a diff printed to the console
+const MAX_CONSOLE_LINES: usize = 40;
+
+/// Time after which a large diff settles for a less minimal result
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How the content of one file changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// `@@` hunks of a unified diff without file headers, empty if nothing changed
+    pub rendered: String,
+}
+
+/// Diff two versions of a file line by line
+///
+/// CRLF line endings are shown as plain newlines in the rendered diff, which
+/// is cut after [`MAX_DIFF_LINES`] lines.
+pub fn diff(before: &str, after: &str) -> FileDiff {
+    let text_diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(before, after);
+
+    let mut lines_added = 0;
+    let mut lines_removed = 0;
+    for change in text_diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => lines_added += 1,
+            ChangeTag::Delete => lines_removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let unified = text_diff
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .to_string()
+        .replace("\r\n", "\n");
+    let lines: Vec<&str> = unified.lines().collect();
+    let mut rendered = lines
+        .iter()
+        .take(MAX_DIFF_LINES)
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    if lines.len() > MAX_DIFF_LINES {
+        rendered.push_str(&format!(
+            "... {} more diff lines\n",
+            lines.len() - MAX_DIFF_LINES
+        ));
+    }
+
+    FileDiff {
+        lines_added,
+        lines_removed,
+        rendered,
+    }
+}
+
+/// Print a rendered diff under a tool's status line, added lines in green and removed in red
+pub fn print_colored(rendered: &str) {
+    let lines: Vec<&str> = rendered.lines().collect();
+    for line in lines.iter().take(MAX_CONSOLE_LINES) {
+        let line = if line.starts_with("@@") {
+            line.cyan()
+        } else if line.starts_with('+') {
+            line.green()
+        } else if line.starts_with('-') {
+            line.red()
+        } else {
+            line.dimmed()
+        };
+        println!("     {}", line);
+    }
+    if lines.len() > MAX_CONSOLE_LINES {
+        println!(
+            "     {}",
+            format!("... {} more lines", lines.len() - MAX_CONSOLE_LINES).dimmed()
+        );
+    }
+}
@@ -1,29 +1,540 @@
+use super::diff::{self, print_colored};
+use super::patch::{self, FilePatch, HunkReport, DEFAULT_FUZZ};
+use super::workspace::{confine, Access, Workspace};
 use super::FileToolError;
+use crate::kota_code::checkpoint::{snapshot_before_write, CheckpointManager};
 use colored::*;
-use patch_apply::{apply, Patch};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct EditFileArgs {
+    /// File to patch; empty to take the files from the patch headers
+    #[serde(default)]
     pub file_path: String,
+    /// Unified diff; leave empty when using `old_string`/`new_string`
+    #[serde(default)]
     pub patch: String,
+    /// Exact text to replace (str_replace mode)
+    #[serde(default)]
+    pub old_string: Option<String>,
+    /// Text that replaces `old_string`
+    #[serde(default)]
+    pub new_string: Option<String>,
+    /// Replace every occurrence instead of requiring a unique match
+    #[serde(default)]
+    pub replace_all: bool,
+    /// Number of occurrences that must be found; all of them are replaced
+    #[serde(default)]
+    pub expected_occurrences: Option<usize>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct EditFileOutput {
+    /// The patched files, separated by `, `
     pub file_path: String,
     pub lines_added: usize,
     pub lines_removed: usize,
     pub success: bool,
     pub message: String,
+    /// Result per file, in patch order
+    pub files: Vec<PatchedFile>,
+}
+
+/// How the patch changed one file
+#[derive(Serialize, Debug)]
+pub struct PatchedFile {
+    pub file_path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub created: bool,
+    pub deleted: bool,
+    pub hunks: Vec<HunkReport>,
+    /// Occurrences replaced in str_replace mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacements: Option<usize>,
+    /// Unified diff of what changed in the file
+    pub diff: String,
+}
+
+/// New content of one file, checked but not yet written
+pub(crate) struct PendingEdit {
+    file_path: String,
+    /// Content before the edit, `None` if the file does not exist yet
+    before: Option<String>,
+    /// `None` deletes the file
+    content: Option<String>,
+    result: PatchedFile,
+}
+
+impl PendingEdit {
+    /// Diff `before` against the new content to report what actually changed
+    fn new(
+        file_path: String,
+        before: Option<String>,
+        content: Option<String>,
+        hunks: Vec<HunkReport>,
+        replacements: Option<usize>,
+    ) -> Self {
+        let changes = diff::diff(
+            before.as_deref().unwrap_or_default(),
+            content.as_deref().unwrap_or_default(),
+        );
+        Self {
+            result: PatchedFile {
+                file_path: file_path.clone(),
+                lines_added: changes.lines_added,
+                lines_removed: changes.lines_removed,
+                created: before.is_none() && content.is_some(),
+                deleted: content.is_none(),
+                hunks,
+                replacements,
+                diff: changes.rendered,
+            },
+            file_path,
+            before,
+            content,
+        }
+    }
+
+    pub(crate) fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    /// Write the new content to a temporary file next to the target
+    ///
+    /// # Returns
+    ///
+    /// The temporary file and the path to rename it to, or `None` for a deletion
+    fn stage(&self) -> std::io::Result<Option<(PathBuf, PathBuf)>> {
+        let Some(content) = &self.content else {
+            return Ok(None);
+        };
+        // Replace the file a symlink points to, as writing through it would
+        let path = Path::new(&self.file_path);
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(parent) = target.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let name = target
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temp = target.with_file_name(format!(".{}.kota-{}.tmp", name, std::process::id()));
+
+        let written = fs::write(&temp, content).and_then(|()| match fs::metadata(&target) {
+            // Keep the mode of the replaced file, e.g. of an executable script
+            Ok(metadata) => fs::set_permissions(&temp, metadata.permissions()),
+            Err(_) => Ok(()),
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        Ok(Some((temp, target)))
+    }
+
+    /// Put back the content the edit replaced, after a later edit failed
+    fn restore(&self) {
+        let _ = match &self.before {
+            Some(before) => fs::write(&self.file_path, before),
+            None => fs::remove_file(&self.file_path),
+        };
+    }
+}
+
+/// A file changed by one or more sections of a patch
+struct PatchedContent {
+    file_path: String,
+    before: Option<String>,
+    /// Content after the sections applied so far, `None` if it is deleted
+    content: Option<String>,
+    hunks: Vec<HunkReport>,
 }
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct EditFileTool;
 
+/// Candidate locations listed in a failed replacement
+const MAX_CANDIDATES: usize = 10;
+
+impl EditFileTool {
+    /// Check the arguments and compute the new content of every edited file
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either a `patch` or an `old_string`/`new_string` pair
+    /// * `fuzz` - Context lines a hunk may ignore at each end
+    /// * `resolve` - Checks, and may rewrite, each target path before it is read
+    pub(crate) fn plan(
+        args: EditFileArgs,
+        fuzz: usize,
+        resolve: impl Fn(&mut String) -> Result<(), FileToolError>,
+    ) -> Result<Vec<PendingEdit>, FileToolError> {
+        let Some(old_string) = args.old_string else {
+            if args.patch.trim().is_empty() {
+                return Err(FileToolError::InvalidInput(
+                    "Pass either patch or old_string/new_string".to_string(),
+                ));
+            }
+            let files = patch::parse(&args.patch)?;
+            let mut targets = Self::targets(&args.file_path, files)?;
+            for (file_path, _) in &mut targets {
+                resolve(file_path)?;
+            }
+            return Self::prepare(targets, fuzz);
+        };
+
+        if !args.patch.is_empty() {
+            return Err(FileToolError::InvalidInput(
+                "Pass either patch or old_string/new_string, not both".to_string(),
+            ));
+        }
+        let Some(new_string) = args.new_string else {
+            return Err(FileToolError::InvalidInput(
+                "new_string is required with old_string".to_string(),
+            ));
+        };
+        if args.file_path.is_empty() {
+            return Err(FileToolError::InvalidInput(
+                "file_path is required with old_string".to_string(),
+            ));
+        }
+        if old_string.is_empty() {
+            return Err(FileToolError::InvalidInput(
+                "old_string must not be empty; use write_file to create a file".to_string(),
+            ));
+        }
+        if old_string == new_string {
+            return Err(FileToolError::InvalidInput(
+                "old_string and new_string are identical".to_string(),
+            ));
+        }
+
+        let mut file_path = args.file_path;
+        resolve(&mut file_path)?;
+        let edit = Self::replace(
+            file_path,
+            &old_string,
+            &new_string,
+            args.replace_all,
+            args.expected_occurrences,
+        )?;
+        Ok(vec![edit])
+    }
+
+    /// Replace exact occurrences of `old_string` in memory
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FileToolError::ReplaceFailed`] listing the candidate locations when
+    /// the text is missing, or found a different number of times than required
+    fn replace(
+        file_path: String,
+        old_string: &str,
+        new_string: &str,
+        replace_all: bool,
+        expected_occurrences: Option<usize>,
+    ) -> Result<PendingEdit, FileToolError> {
+        let path = Path::new(&file_path);
+        if !path.exists() {
+            return Err(FileToolError::FileNotFound(file_path));
+        }
+        if !path.is_file() {
+            return Err(FileToolError::NotAFile(file_path));
+        }
+        let current_content = fs::read_to_string(path)?;
+
+        let positions: Vec<usize> = current_content
+            .match_indices(old_string)
+            .map(|(position, _)| position)
+            .collect();
+        let count = positions.len();
+
+        if count == 0 {
+            return Err(FileToolError::ReplaceFailed(format!(
+                "old_string was not found in '{}'{}",
+                file_path,
+                Self::missing_hint(&current_content, old_string)
+            )));
+        }
+        let required = match expected_occurrences {
+            Some(expected) if expected != count => Some(format!("{} expected", expected)),
+            Some(_) => None,
+            None if count > 1 && !replace_all => Some(
+                "add surrounding lines to old_string to make it unique, or set replace_all"
+                    .to_string(),
+            ),
+            None => None,
+        };
+        if let Some(required) = required {
+            let candidates: Vec<String> = positions
+                .iter()
+                .take(MAX_CANDIDATES)
+                .map(|&position| {
+                    let line = current_content[..position].matches('\n').count();
+                    let text = current_content.lines().nth(line).unwrap_or_default();
+                    format!("  line {}: {}", line + 1, text.trim_end_matches('\r'))
+                })
+                .collect();
+            let more = if count > MAX_CANDIDATES {
+                format!("\n  ... and {} more", count - MAX_CANDIDATES)
+            } else {
+                String::new()
+            };
+            return Err(FileToolError::ReplaceFailed(format!(
+                "old_string matches {} times in '{}' ({})\n{}{}",
+                count,
+                file_path,
+                required,
+                candidates.join("\n"),
+                more
+            )));
+        }
+
+        let content = current_content.replace(old_string, new_string);
+        Ok(PendingEdit::new(
+            file_path,
+            Some(current_content),
+            Some(content),
+            Vec::new(),
+            Some(count),
+        ))
+    }
+
+    /// Point the model at text that is close to a missing `old_string`
+    fn missing_hint(content: &str, old_string: &str) -> String {
+        let lines = patch::whitespace_insensitive_matches(content, old_string);
+        if !lines.is_empty() {
+            let lines: Vec<String> = lines
+                .iter()
+                .take(MAX_CANDIDATES)
+                .map(|line| line.to_string())
+                .collect();
+            return format!(
+                "\n  it matches at line {} when whitespace is ignored; copy the exact indentation from the file",
+                lines.join(", ")
+            );
+        }
+        match patch::closest_region(content, old_string) {
+            Some(closest) => format!(
+                "\n  closest match at line {} ({} of {} lines match):\n{}",
+                closest.line,
+                closest.matching_lines,
+                closest.total_lines,
+                closest
+                    .text
+                    .lines()
+                    .map(|line| format!("    {}", line))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Pair every file of a parsed patch with the path it is applied to
+    ///
+    /// A single-file patch goes to `file_path` when it is set, whatever its
+    /// headers say. Otherwise each file needs a `---`/`+++` header.
+    pub fn targets(
+        file_path: &str,
+        files: Vec<FilePatch>,
+    ) -> Result<Vec<(String, FilePatch)>, FileToolError> {
+        if files.len() == 1 && !file_path.is_empty() {
+            return Ok(files
+                .into_iter()
+                .map(|file| (file_path.to_string(), file))
+                .collect());
+        }
+        files
+            .into_iter()
+            .map(|file| match file.path() {
+                Some(path) => Ok((path.to_string(), file)),
+                None => Err(FileToolError::InvalidInput(
+                    "Hunks without a '--- a/path' / '+++ b/path' header; set file_path or add the header"
+                        .to_string(),
+                )),
+            })
+            .collect()
+    }
+
+    /// Apply every file of the patch in memory
+    ///
+    /// Sections for the same file apply one after the other, each to the
+    /// content left by the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FileToolError::PatchFailed`] with a report per hunk if any hunk
+    /// of any file does not apply
+    pub(crate) fn prepare(
+        targets: Vec<(String, FilePatch)>,
+        fuzz: usize,
+    ) -> Result<Vec<PendingEdit>, FileToolError> {
+        let mut patched: Vec<PatchedContent> = Vec::new();
+        let mut failures = Vec::new();
+
+        for (file_path, file) in targets {
+            let index = match patched.iter().position(|p| p.file_path == file_path) {
+                Some(index) => index,
+                None => {
+                    let path = Path::new(&file_path);
+                    let before = if !path.exists() {
+                        None
+                    } else if !path.is_file() {
+                        return Err(FileToolError::NotAFile(file_path));
+                    } else {
+                        Some(fs::read_to_string(path)?)
+                    };
+                    patched.push(PatchedContent {
+                        file_path,
+                        content: before.clone(),
+                        before,
+                        hunks: Vec::new(),
+                    });
+                    patched.len() - 1
+                }
+            };
+            let target = &mut patched[index];
+            let current_content = match &target.content {
+                Some(content) => content.clone(),
+                None if file.is_creation() => String::new(),
+                None => return Err(FileToolError::FileNotFound(target.file_path.clone())),
+            };
+
+            // Number hunks across all sections of the file
+            let renumber = |mut reports: Vec<HunkReport>| {
+                for report in &mut reports {
+                    report.hunk += target.hunks.len();
+                }
+                reports
+            };
+            match patch::apply(&current_content, &file.hunks, fuzz) {
+                Ok((content, hunks)) => {
+                    let hunks = renumber(hunks);
+                    target.hunks.extend(hunks);
+                    target.content = (!file.is_deletion()).then_some(content);
+                }
+                Err(reports) => {
+                    let reports = renumber(reports);
+                    let file_path = &target.file_path;
+                    let failed = reports.iter().filter(|report| !report.applied).count();
+                    let details: Vec<String> = reports
+                        .iter()
+                        .map(|report| format!("  {}", report.describe()))
+                        .collect();
+                    failures.push(format!(
+                        "'{}': {} of {} hunks failed\n{}",
+                        file_path,
+                        failed,
+                        reports.len(),
+                        details.join("\n")
+                    ));
+                }
+            }
+        }
+
+        if !failures.is_empty() {
+            return Err(FileToolError::PatchFailed(format!(
+                "no files were changed\n{}",
+                failures.join("\n")
+            )));
+        }
+        Ok(patched
+            .into_iter()
+            .map(|file| {
+                PendingEdit::new(file.file_path, file.before, file.content, file.hunks, None)
+            })
+            .collect())
+    }
+
+    /// Write checked edits to disk, all of them or none
+    ///
+    /// The new content of every file is written to a temporary file first, and
+    /// the temporary files replace the targets only once all are written. If a
+    /// rename or delete still fails, the files already changed are put back.
+    pub(crate) fn write(edits: Vec<PendingEdit>) -> Result<EditFileOutput, FileToolError> {
+        let failed = |edit: &PendingEdit, e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                FileToolError::PermissionDenied(edit.file_path.clone())
+            }
+            _ => FileToolError::Io(e),
+        };
+
+        let mut staged = Vec::new();
+        for edit in &edits {
+            match edit.stage() {
+                Ok(temp) => staged.push(temp),
+                Err(e) => {
+                    for (temp, _) in staged.iter().flatten() {
+                        let _ = fs::remove_file(temp);
+                    }
+                    return Err(failed(edit, e));
+                }
+            }
+        }
+
+        for (i, (edit, temp)) in edits.iter().zip(&staged).enumerate() {
+            let moved = match temp {
+                Some((temp, target)) => fs::rename(temp, target),
+                None => fs::remove_file(&edit.file_path),
+            };
+            if let Err(e) = moved {
+                for edit in &edits[..i] {
+                    edit.restore();
+                }
+                for (temp, _) in staged[i..].iter().flatten() {
+                    let _ = fs::remove_file(temp);
+                }
+                return Err(failed(edit, e));
+            }
+        }
+        let files: Vec<PatchedFile> = edits.into_iter().map(|edit| edit.result).collect();
+
+        let file_path = files
+            .iter()
+            .map(|file| file.file_path.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let lines_added = files.iter().map(|file| file.lines_added).sum();
+        let lines_removed = files.iter().map(|file| file.lines_removed).sum();
+        let mut message = match files.first().and_then(|file| file.replacements) {
+            Some(count) => format!(
+                "Successfully replaced {} occurrence{} in '{}': +{} lines, -{} lines",
+                count,
+                if count == 1 { "" } else { "s" },
+                file_path,
+                lines_added,
+                lines_removed
+            ),
+            None => format!(
+                "Successfully applied patch to '{}': +{} lines, -{} lines",
+                file_path, lines_added, lines_removed
+            ),
+        };
+        // Tell the model where hunks landed when it was not where it said
+        for hunk in files.iter().flat_map(|file| &file.hunks) {
+            if hunk.offset != 0 || hunk.fuzz > 0 || hunk.ignored_whitespace {
+                message.push_str(&format!("\n  {}", hunk.describe()));
+            }
+        }
+
+        Ok(EditFileOutput {
+            file_path,
+            lines_added,
+            lines_removed,
+            success: true,
+            message,
+            files,
+        })
+    }
+}
+
 impl Tool for EditFileTool {
     const NAME: &'static str = "edit_file";
 
@@ -34,105 +545,104 @@ impl Tool for EditFileTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "edit_file".to_string(),
-            description: "Apply a unified diff patch to a file. This is efficient for making small, targeted changes to existing files without rewriting the entire content.".to_string(),
+            description: "Edit existing files without rewriting their entire content, in one of two modes. \
+                Patch mode: apply a unified diff patch to one or more files. Hunks are located by their content, so line numbers may be approximate and whitespace differences are tolerated; a hunk without line numbers must match in only one place. \
+                If any hunk does not apply, no file is changed and the error shows the closest matching lines. \
+                Replace mode: set file_path, old_string and new_string instead of patch. old_string must match the file exactly, including indentation, and must be unique unless replace_all or expected_occurrences is set. \
+                If it is missing or ambiguous, the file is not changed and the error lists the candidate locations.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "file_path": {
                         "type": "string",
-                        "description": "The path to the file to edit (relative or absolute). The file must exist. Examples: 'src/main.rs', 'README.md'"
+                        "description": "The path to the file to edit (relative or absolute). Examples: 'src/main.rs', 'README.md'. Omit it for a patch that changes several files; each file is then taken from its '+++ b/path' header."
                     },
                     "patch": {
                         "type": "string",
-                        "description": "A unified diff patch string in standard format. CRITICAL REQUIREMENTS:\n1. MUST start with '--- a/filename' header line\n2. MUST have '+++ b/filename' as second line\n3. MUST have hunk header: '@@ -old_start,old_count +new_start,new_count @@'\n4. Context lines MUST start with ' ' (space)\n5. Removed lines MUST start with '-'\n6. Added lines MUST start with '+'\n7. EVERY line (including the last) MUST end with '\\n'\n\nExample:\n--- a/main.rs\n+++ b/main.rs\n@@ -1,3 +1,4 @@\n fn main() {\n+    println!(\"Hello, world!\");\n     // existing code\n }\n"
+                        "description": "Patch mode. A unified diff patch string in standard format:\n1. Start each file with '--- a/filename' and '+++ b/filename' header lines\n2. Start each hunk with '@@ -old_start,old_count +new_start,new_count @@'\n3. Context lines start with ' ' (space)\n4. Removed lines start with '-'\n5. Added lines start with '+'\n6. Include a few unchanged context lines around each change so the hunk can be located\n7. Use '--- /dev/null' to create a file and '+++ /dev/null' to delete one\n\nExample:\n--- a/main.rs\n+++ b/main.rs\n@@ -1,3 +1,4 @@\n fn main() {\n+    println!(\"Hello, world!\");\n     // existing code\n }\n"
+                    },
+                    "old_string": {
+                        "type": "string",
+                        "description": "Replace mode. The exact text to replace, including whitespace and indentation. Include enough surrounding lines to make it unique in the file."
+                    },
+                    "new_string": {
+                        "type": "string",
+                        "description": "Replace mode. The text to replace old_string with."
+                    },
+                    "replace_all": {
+                        "type": "boolean",
+                        "description": "Replace mode. Replace every occurrence of old_string instead of requiring exactly one (default: false)."
+                    },
+                    "expected_occurrences": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Replace mode. How many times old_string must occur; all occurrences are replaced. The edit fails if the count differs."
                     }
                 },
-                "required": ["file_path", "patch"]
+                "required": []
             })
         }
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let file_path = &args.file_path;
-        let patch_str = &args.patch;
-        let path = Path::new(file_path);
-
-        // Check if file exists
-        if !path.exists() {
-            return Err(FileToolError::FileNotFound(file_path.clone()));
-        }
-
-        // Check if it's actually a file (not a directory)
-        if !path.is_file() {
-            return Err(FileToolError::NotAFile(file_path.clone()));
-        }
-
-        // Read the current file content
-        let current_content = fs::read_to_string(file_path)?;
-
-        // Ensure patch_str ends with a newline
-        let patch_str_normalized = if !patch_str.ends_with('\n') {
-            format!("{}\n", patch_str)
-        } else {
-            patch_str.to_string()
-        };
-
-        // Parse the patch
-        let patch = Patch::from_single(&patch_str_normalized).map_err(|e| {
-            FileToolError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Failed to parse patch: {}", e),
-            ))
-        })?;
-
-        // Apply the patch using patch_apply::apply
-        let patched_content = apply(current_content, patch);
-
-        // Calculate statistics
-        let original_lines: Vec<&str> = args.patch.lines().collect();
-        let mut lines_added = 0usize;
-        let mut lines_removed = 0usize;
-
-        for line in original_lines {
-            if line.starts_with('+') && !line.starts_with("+++") {
-                lines_added += 1;
-            } else if line.starts_with('-') && !line.starts_with("---") {
-                lines_removed += 1;
-            }
-        }
-
-        // Write the modified content back to the file
-        match fs::write(file_path, &patched_content) {
-            Ok(()) => Ok(EditFileOutput {
-                file_path: file_path.clone(),
-                lines_added,
-                lines_removed,
-                success: true,
-                message: format!(
-                    "Successfully applied patch to '{}': +{} lines, -{} lines",
-                    file_path, lines_added, lines_removed
-                ),
-            }),
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::PermissionDenied => {
-                    Err(FileToolError::PermissionDenied(file_path.clone()))
-                }
-                _ => Err(FileToolError::Io(e)),
-            },
-        }
+        let edits = Self::plan(args, DEFAULT_FUZZ, |_| Ok(()))?;
+        Self::write(edits)
     }
 }
 
-#[derive(Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize)]
 pub struct WrappedEditFileTool {
     inner: EditFileTool,
+    #[serde(skip)]
+    workspace: Option<Workspace>,
+    #[serde(skip)]
+    checkpoints: Option<CheckpointManager>,
+    #[serde(skip)]
+    fuzz: usize,
+}
+
+impl Default for WrappedEditFileTool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WrappedEditFileTool {
     pub fn new() -> Self {
         Self {
             inner: EditFileTool,
+            workspace: None,
+            checkpoints: None,
+            fuzz: DEFAULT_FUZZ,
+        }
+    }
+
+    /// Confine the patched files to a workspace root
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Snapshot the files before changing them, so the change can be undone
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointManager) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Set how many context lines a hunk may ignore at each end (default: 2)
+    pub fn with_fuzz(mut self, fuzz: usize) -> Self {
+        self.fuzz = fuzz;
+        self
+    }
+
+    fn edit(&self, args: EditFileArgs) -> Result<EditFileOutput, FileToolError> {
+        let edits = EditFileTool::plan(args, self.fuzz, |file_path| {
+            confine(self.workspace.as_ref(), file_path, Access::Write)
+        })?;
+        for edit in &edits {
+            snapshot_before_write(self.checkpoints.as_ref(), edit.file_path())?;
         }
+        EditFileTool::write(edits)
     }
 }
 
@@ -148,21 +658,48 @@ impl Tool for WrappedEditFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!("\n{} Edit({})", "●".bright_green(), args.file_path);
+        let label = if args.file_path.is_empty() {
+            "patch"
+        } else {
+            args.file_path.as_str()
+        };
+        println!("\n{} Edit({})", "●".bright_green(), label);
 
-        let result = self.inner.call(args).await;
+        let result = self.edit(args);
 
         match &result {
             Ok(output) => {
-                println!(
-                    "  └─ {} (+{} lines, -{} lines)",
-                    format!("Patched '{}'", output.file_path).dimmed(),
-                    output.lines_added.to_string().green(),
-                    output.lines_removed.to_string().red()
-                );
+                for file in &output.files {
+                    let action = match file.replacements {
+                        Some(1) => "Replaced 1 occurrence in".to_string(),
+                        Some(count) => format!("Replaced {} occurrences in", count),
+                        None if file.created => "Created".to_string(),
+                        None if file.deleted => "Deleted".to_string(),
+                        None => "Patched".to_string(),
+                    };
+                    println!(
+                        "  └─ {} (+{} lines, -{} lines)",
+                        format!("{} '{}'", action, file.file_path).dimmed(),
+                        file.lines_added.to_string().green(),
+                        file.lines_removed.to_string().red()
+                    );
+                    for hunk in &file.hunks {
+                        if hunk.offset != 0 || hunk.fuzz > 0 || hunk.ignored_whitespace {
+                            println!("     {}", hunk.describe().dimmed());
+                        }
+                    }
+                    print_colored(&file.diff);
+                }
             }
             Err(e) => {
-                println!("  └─ {}", format!("Error: {}", e).red());
+                let message = format!("Error: {}", e);
+                let mut lines = message.lines();
+                if let Some(first) = lines.next() {
+                    println!("  └─ {}", first.red());
+                }
+                for line in lines {
+                    println!("     {}", line.dimmed());
+                }
             }
         }
         println!();
@@ -1,12 +1,285 @@
+use super::workspace::{confine, Access, Workspace};
 use super::FileToolError;
 use colored::*;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::futures::Notified;
+use tokio::sync::Notify;
+
+/// Default for `timeout_secs` when the model does not pass one
+pub const DEFAULT_TIMEOUT_SECS: u64 = 120;
+/// Default number of bytes kept from stdout and from stderr
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 30_000;
+/// Variables commands never inherit, so API keys and tokens stay with kota
+pub const DEFAULT_ENV_DENY: &[&str] = &[
+    "API_KEY",
+    "*_API_KEY",
+    "*_TOKEN",
+    "*_SECRET",
+    "*_SECRET_KEY",
+    "*_PASSWORD",
+];
+
+/// Limits applied to every `exec_cmd` call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecOptions {
+    /// Seconds a command may run when the call sets no `timeout_secs`
+    pub timeout_secs: u64,
+    /// Bytes kept from each of stdout and stderr; the middle is dropped beyond this
+    pub max_output_bytes: usize,
+    /// Globs of environment variables commands inherit; empty inherits all
+    pub env_allow: Vec<String>,
+    /// Globs of environment variables never inherited; starts with [`DEFAULT_ENV_DENY`]
+    pub env_deny: Vec<String>,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            env_allow: vec![],
+            env_deny: DEFAULT_ENV_DENY.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+impl ExecOptions {
+    /// Check whether a command inherits the variable `name` from kota
+    fn inherits(allow: &Option<GlobSet>, deny: &Option<GlobSet>, name: &str) -> bool {
+        allow.as_ref().is_none_or(|allow| allow.is_match(name))
+            && !deny.as_ref().is_some_and(|deny| deny.is_match(name))
+    }
+
+    /// Compile a list of variable name globs; `None` if the list is empty
+    fn env_globs(patterns: &[String]) -> Result<Option<GlobSet>, FileToolError> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(pattern).map_err(|e| {
+                FileToolError::InvalidInput(format!("Invalid env pattern '{}': {}", pattern, e))
+            })?;
+            builder.add(glob);
+        }
+        builder
+            .build()
+            .map(Some)
+            .map_err(|e| FileToolError::InvalidInput(e.to_string()))
+    }
+
+    /// Restrict the environment `cmd` inherits to the allow and deny lists
+    pub(crate) fn apply_env_policy(&self, cmd: &mut Command) -> Result<(), FileToolError> {
+        let allow = Self::env_globs(&self.env_allow)?;
+        let deny = Self::env_globs(&self.env_deny)?;
+        if allow.is_none() && deny.is_none() {
+            return Ok(());
+        }
+
+        cmd.env_clear();
+        for (name, value) in std::env::vars_os() {
+            if Self::inherits(&allow, &deny, &name.to_string_lossy()) {
+                cmd.env(name, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Handle to cancel the commands `exec_cmd` is running, e.g. on Ctrl-C
+///
+/// Clones share the same state, so the REPL keeps one and hands clones to the agent.
+#[derive(Debug, Clone, Default)]
+pub struct CommandInterrupt {
+    state: Arc<InterruptState>,
+}
+
+#[derive(Debug, Default)]
+struct InterruptState {
+    running: AtomicUsize,
+    notify: Notify,
+}
+
+impl CommandInterrupt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every running command
+    ///
+    /// # Returns
+    ///
+    /// `false` if no command was running
+    pub fn interrupt(&self) -> bool {
+        if self.state.running.load(Ordering::SeqCst) == 0 {
+            return false;
+        }
+        self.state.notify.notify_waiters();
+        true
+    }
+
+    /// Check whether a command is running
+    pub fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::SeqCst) > 0
+    }
+
+    /// Future completing on the next [`interrupt`](Self::interrupt)
+    ///
+    /// Create it before [`running`](Self::running) so no interrupt is missed.
+    pub(crate) fn notified(&self) -> Notified<'_> {
+        self.state.notify.notified()
+    }
+
+    /// Mark a command as running until the guard is dropped
+    pub(crate) fn running(&self) -> RunningGuard<'_> {
+        RunningGuard::new(&self.state)
+    }
+}
+
+/// Marks a command as running until dropped
+pub(crate) struct RunningGuard<'a>(&'a InterruptState);
+
+impl<'a> RunningGuard<'a> {
+    fn new(state: &'a InterruptState) -> Self {
+        state.running.fetch_add(1, Ordering::SeqCst);
+        Self(state)
+    }
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Output of one stream, keeping the first and last `limit / 2` bytes
+pub(crate) struct OutputBuffer {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    head_limit: usize,
+    tail_limit: usize,
+    total: usize,
+}
+
+impl OutputBuffer {
+    pub(crate) fn new(limit: usize) -> Self {
+        let head_limit = limit / 2;
+        Self {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            head_limit,
+            tail_limit: limit - head_limit,
+            total: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len();
+
+        let to_head = bytes.len().min(self.head_limit - self.head.len());
+        self.head.extend_from_slice(&bytes[..to_head]);
+        bytes = &bytes[to_head..];
+
+        if bytes.len() >= self.tail_limit {
+            self.tail.clear();
+            bytes = &bytes[bytes.len() - self.tail_limit..];
+        } else {
+            let overflow = (self.tail.len() + bytes.len()).saturating_sub(self.tail_limit);
+            self.tail.drain(..overflow);
+        }
+        self.tail.extend(bytes);
+    }
+
+    /// Read a stream to the end, ignoring read errors
+    pub(crate) async fn read_from<R: AsyncRead + Unpin>(&mut self, reader: Option<R>) {
+        let Some(mut reader) = reader else {
+            return;
+        };
+        let mut chunk = [0u8; 8192];
+        while let Ok(n) = reader.read(&mut chunk).await {
+            if n == 0 {
+                break;
+            }
+            self.push(&chunk[..n]);
+        }
+    }
+
+    /// Drop a trailing newline, e.g. one the caller wrote after the real output
+    pub(crate) fn pop_newline(&mut self) {
+        let popped = if self.tail.back() == Some(&b'\n') {
+            self.tail.pop_back().is_some()
+        } else if self.tail.is_empty() && self.head.last() == Some(&b'\n') {
+            self.head.pop().is_some()
+        } else {
+            false
+        };
+        if popped {
+            self.total -= 1;
+        }
+    }
+
+    pub(crate) fn truncated(&self) -> bool {
+        self.total > self.head.len() + self.tail.len()
+    }
+
+    pub(crate) fn into_string(mut self) -> String {
+        let dropped = self.total - self.head.len() - self.tail.len();
+        let head = String::from_utf8_lossy(&self.head);
+        let tail = String::from_utf8_lossy(self.tail.make_contiguous()).to_string();
+        if dropped == 0 {
+            format!("{}{}", head, tail)
+        } else {
+            format!(
+                "{}\n\n... [{} bytes truncated] ...\n\n{}",
+                head, dropped, tail
+            )
+        }
+    }
+}
+
+/// Kill a command started in its own process group, and everything it started
+pub(crate) async fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: killpg only sends a signal. `child.id()` is `None` once the child
+        // has been reaped, so the group id still belongs to our child and was not reused.
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+/// How a command ended
+enum Outcome {
+    Exited(std::process::ExitStatus),
+    TimedOut,
+    Cancelled,
+}
 
 #[derive(Deserialize)]
 pub struct ExecuteBashCommandArgs {
     pub command: String,
+    /// Seconds before the command is killed; `None` uses [`ExecOptions::timeout_secs`]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Directory to run in; `None` runs in the workspace root, or kota's
+    /// working directory without a workspace
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Variables to set for this command, on top of the inherited environment
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -16,10 +289,57 @@ pub struct ExecuteBashCommandOutput {
     pub stdout: String,
     pub stderr: String,
     pub exit_code: Option<i32>,
+    /// Absolute directory the command ran in
+    pub cwd: String,
+    /// The command was killed after running for `timeout_secs`
+    pub timed_out: bool,
+    /// The command was cancelled by the user
+    pub cancelled: bool,
+    /// Part of stdout or stderr was dropped
+    pub truncated: bool,
 }
 
 #[derive(Deserialize, Serialize, Default)]
-pub struct ExecuteBashCommandTool;
+pub struct ExecuteBashCommandTool {
+    #[serde(skip)]
+    options: ExecOptions,
+    #[serde(skip)]
+    interrupt: CommandInterrupt,
+}
+
+impl ExecuteBashCommandTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the default timeout, output limit and environment policy
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Let a shared [`CommandInterrupt`] cancel the commands of this tool
+    pub fn with_interrupt(mut self, interrupt: CommandInterrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Resolve the `cwd` argument to an existing absolute directory
+    fn resolve_cwd(cwd: Option<&str>) -> Result<std::path::PathBuf, FileToolError> {
+        let dir = Path::new(cwd.unwrap_or("."));
+        match dir.canonicalize() {
+            Ok(dir) if dir.is_dir() => Ok(dir),
+            Ok(_) => Err(FileToolError::InvalidInput(format!(
+                "Working directory is not a directory: {}",
+                dir.display()
+            ))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(FileToolError::FileNotFound(
+                format!("working directory {}", dir.display()),
+            )),
+            Err(e) => Err(FileToolError::Io(e)),
+        }
+    }
+}
 
 impl Tool for ExecuteBashCommandTool {
     const NAME: &'static str = "exec_cmd";
@@ -31,61 +351,171 @@ impl Tool for ExecuteBashCommandTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "exec_cmd".to_string(),
-            description: "Execute a bash command and return the output. Use with caution as this can modify the system.".to_string(),
+            description: format!(
+                "Execute a bash command and return the output. Use with caution as this can modify the system. \
+                 The command gets no stdin and is killed after timeout_secs (default {}s), so do not start servers \
+                 or interactive programs. Long output is truncated in the middle.",
+                self.options.timeout_secs
+            ),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "command": {
                         "type": "string",
                         "description": "The bash command to execute. Examples: 'ls -la', 'git status', 'cargo build'"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": format!("Seconds before the command is killed (default: {})", self.options.timeout_secs)
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Directory to run the command in, instead of 'cd dir && ...' (default: the workspace root)"
+                    },
+                    "env": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Environment variables to set for this command, e.g. {\"RUST_LOG\": \"debug\"}"
                     }
                 },
                 "required": ["command"]
-            })
+            }),
         }
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let command = &args.command;
+        let timeout_secs = args.timeout_secs.unwrap_or(self.options.timeout_secs);
+        if timeout_secs == 0 {
+            return Err(FileToolError::InvalidInput(
+                "timeout_secs must be at least 1".to_string(),
+            ));
+        }
+        if let Some(name) = args
+            .env
+            .keys()
+            .find(|name| name.is_empty() || name.contains('=') || name.contains('\0'))
+        {
+            return Err(FileToolError::InvalidInput(format!(
+                "Invalid environment variable name: '{}'",
+                name
+            )));
+        }
+        let cwd = Self::resolve_cwd(args.cwd.as_deref())?;
 
         // Execute the command using cmd on Windows or bash on Unix
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd").args(["/C", command]).output()
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
         } else {
-            Command::new("bash").args(["-c", command]).output()
+            let mut cmd = Command::new("bash");
+            cmd.args(["-c", command]);
+            cmd
         };
+        self.options.apply_env_policy(&mut cmd)?;
+        cmd.envs(&args.env)
+            .current_dir(&cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // A process group of its own lets a timeout kill the command's children too
+        #[cfg(unix)]
+        cmd.process_group(0);
 
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                let success = output.status.success();
-                let exit_code = output.status.code();
-
-                Ok(ExecuteBashCommandOutput {
-                    command: command.clone(),
-                    success,
-                    stdout,
-                    stderr,
-                    exit_code,
-                })
+        let cancelled = self.interrupt.notified();
+        let _running = self.interrupt.running();
+        let mut child = cmd.spawn()?;
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let mut stdout_buf = OutputBuffer::new(self.options.max_output_bytes);
+        let mut stderr_buf = OutputBuffer::new(self.options.max_output_bytes);
+
+        let outcome = {
+            // Wait for the pipes to close too, so background jobs holding them count as running
+            let run = async {
+                let (status, _, _) = tokio::join!(
+                    child.wait(),
+                    stdout_buf.read_from(stdout),
+                    stderr_buf.read_from(stderr)
+                );
+                status
+            };
+            tokio::select! {
+                status = run => Outcome::Exited(status?),
+                _ = tokio::time::sleep(Duration::from_secs(timeout_secs)) => Outcome::TimedOut,
+                _ = cancelled => Outcome::Cancelled,
             }
-            Err(e) => Err(FileToolError::Io(e)),
+        };
+
+        let (exit_code, success) = match &outcome {
+            Outcome::Exited(status) => (status.code(), status.success()),
+            Outcome::TimedOut | Outcome::Cancelled => {
+                kill_process_group(&mut child).await;
+                (None, false)
+            }
+        };
+
+        let truncated = stdout_buf.truncated() || stderr_buf.truncated();
+        let mut stderr = stderr_buf.into_string();
+        match outcome {
+            Outcome::TimedOut => stderr.push_str(&format!(
+                "\n[command timed out after {}s and was killed]",
+                timeout_secs
+            )),
+            Outcome::Cancelled => stderr.push_str("\n[command cancelled by the user]"),
+            Outcome::Exited(_) => {}
         }
+
+        Ok(ExecuteBashCommandOutput {
+            command: command.clone(),
+            success,
+            stdout: stdout_buf.into_string(),
+            stderr,
+            exit_code,
+            cwd: cwd.to_string_lossy().to_string(),
+            timed_out: matches!(outcome, Outcome::TimedOut),
+            cancelled: matches!(outcome, Outcome::Cancelled),
+            truncated,
+        })
     }
 }
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct WrappedExecuteBashCommandTool {
     inner: ExecuteBashCommandTool,
+    #[serde(skip)]
+    workspace: Option<Workspace>,
 }
 
 impl WrappedExecuteBashCommandTool {
     pub fn new() -> Self {
         Self {
-            inner: ExecuteBashCommandTool,
+            inner: ExecuteBashCommandTool::new(),
+            workspace: None,
         }
     }
+
+    /// Confine the `cwd` argument to a workspace root
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Set the default timeout, output limit and environment policy
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.inner = self.inner.with_options(options);
+        self
+    }
+
+    /// Let a shared [`CommandInterrupt`] cancel the commands of this tool
+    pub fn with_interrupt(mut self, interrupt: CommandInterrupt) -> Self {
+        self.inner = self.inner.with_interrupt(interrupt);
+        self
+    }
 }
 
 impl Tool for WrappedExecuteBashCommandTool {
@@ -99,14 +529,38 @@ impl Tool for WrappedExecuteBashCommandTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!("\n{} Exec({})", "●".bright_green(), args.command);
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match &args.cwd {
+            Some(cwd) => println!(
+                "\n{} Exec({}) {}",
+                "●".bright_green(),
+                args.command,
+                format!("in {}", cwd).dimmed()
+            ),
+            None => println!("\n{} Exec({})", "●".bright_green(), args.command),
+        }
 
-        let result = self.inner.call(args).await;
+        // Without a cwd the command runs in the workspace root, like the file tools
+        let confined = match (args.cwd.as_mut(), &self.workspace) {
+            (Some(cwd), workspace) => confine(workspace.as_ref(), cwd, Access::Read),
+            (None, Some(workspace)) => {
+                args.cwd = Some(workspace.root().to_string_lossy().to_string());
+                Ok(())
+            }
+            (None, None) => Ok(()),
+        };
+        let result = match confined {
+            Ok(()) => self.inner.call(args).await,
+            Err(e) => Err(e),
+        };
 
         match &result {
             Ok(output) => {
-                if output.success {
+                if output.timed_out {
+                    println!("  └─ {}", "Command timed out and was killed".red());
+                } else if output.cancelled {
+                    println!("  └─ {}", "Command cancelled".yellow());
+                } else if output.success {
                     let stdout_lines = output.stdout.lines().count();
                     if stdout_lines > 0 {
                         println!(
@@ -125,6 +579,9 @@ impl Tool for WrappedExecuteBashCommandTool {
                         output.exit_code.unwrap_or(-1)
                     );
                 }
+                if output.truncated {
+                    println!("  └─ {}", "Output truncated".dimmed());
+                }
             }
             Err(e) => {
                 println!("  └─ {}", format!("Error: {}", e).red());
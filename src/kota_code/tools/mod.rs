@@ -12,24 +12,38 @@ pub enum FileToolError {
     NotAFile(String),
     #[error("Input is invalid: {0}")]
     InvalidInput(String),
+    #[error("Path '{path}' is outside the workspace: {reason}")]
+    OutsideWorkspace { path: String, reason: String },
+    #[error("Patch did not apply, {0}")]
+    PatchFailed(String),
+    #[error("Replacement did not apply, {0}")]
+    ReplaceFailed(String),
 }
 
 pub mod create_directory;
 pub mod delete_file;
+pub mod diff;
 pub mod edit_file;
 pub mod execute_bash_command;
 pub mod grep_search;
+pub mod patch;
+pub mod process;
 pub mod read_file;
 pub mod scan_codebase;
+pub mod shell_session;
 pub mod update_plan;
+pub mod workspace;
 pub mod write_file;
 
 pub use create_directory::WrappedCreateDirectoryTool;
 pub use delete_file::WrappedDeleteFileTool;
 pub use edit_file::WrappedEditFileTool;
-pub use execute_bash_command::WrappedExecuteBashCommandTool;
+pub use execute_bash_command::{CommandInterrupt, ExecOptions, WrappedExecuteBashCommandTool};
 pub use grep_search::WrappedGrepSearchTool;
+pub use process::{ProcessManager, WrappedProcessTool};
 pub use read_file::WrappedReadFileTool;
 pub use scan_codebase::WrappedScanCodebaseTool;
+pub use shell_session::WrappedShellSessionTool;
 pub use update_plan::WrappedUpdatePlanTool;
+pub use workspace::{Access, Workspace};
 pub use write_file::WrappedWriteFileTool;
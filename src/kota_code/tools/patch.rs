@@ -0,0 +1,601 @@
+use super::FileToolError;
+use serde::Serialize;
+
+/// Default number of context lines a hunk may ignore at each end when matching
+pub const DEFAULT_FUZZ: usize = 2;
+
+/// Lines of the closest region shown when a hunk does not apply
+const CLOSEST_MATCH_MAX_LINES: usize = 12;
+
+/// One line of a hunk body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// A `@@ ... @@` section of a unified diff
+#[derive(Debug, Clone, Default)]
+pub struct Hunk {
+    /// The `@@` line as written in the patch
+    pub header: String,
+    /// 0-based line the hunk expects to start at; `None` if the header has no line numbers
+    pub old_start: Option<usize>,
+    pub lines: Vec<HunkLine>,
+    /// The original side ends without a newline (`\ No newline at end of file`)
+    pub old_missing_newline: bool,
+    /// The new side ends without a newline
+    pub new_missing_newline: bool,
+}
+
+impl Hunk {
+    /// Lines the hunk expects in the file: context and removed lines
+    pub fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn lines_added(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| matches!(line, HunkLine::Add(_)))
+            .count()
+    }
+
+    pub fn lines_removed(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| matches!(line, HunkLine::Remove(_)))
+            .count()
+    }
+
+    fn leading_context(&self) -> usize {
+        self.lines
+            .iter()
+            .take_while(|line| matches!(line, HunkLine::Context(_)))
+            .count()
+    }
+
+    fn trailing_context(&self) -> usize {
+        self.lines
+            .iter()
+            .rev()
+            .take_while(|line| matches!(line, HunkLine::Context(_)))
+            .count()
+    }
+}
+
+/// The hunks of one file in a patch
+#[derive(Debug, Clone, Default)]
+pub struct FilePatch {
+    /// Path from the `---` header, without the `a/` prefix
+    pub old_path: Option<String>,
+    /// Path from the `+++` header, without the `b/` prefix
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The file the patch changes, taken from its headers
+    pub fn path(&self) -> Option<&str> {
+        [&self.new_path, &self.old_path]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .find(|path| *path != "/dev/null")
+    }
+
+    /// The patch creates the file (`--- /dev/null`)
+    pub fn is_creation(&self) -> bool {
+        self.old_path.as_deref() == Some("/dev/null")
+    }
+
+    /// The patch deletes the file (`+++ /dev/null`)
+    pub fn is_deletion(&self) -> bool {
+        self.new_path.as_deref() == Some("/dev/null")
+    }
+}
+
+/// Parse a unified diff with one or more files
+///
+/// Parsing is lenient: text outside hunks (`diff --git`, `index`, commentary) is
+/// skipped, hunk line counts are ignored, and a blank line inside a hunk is read
+/// as a blank context line. Without `---`/`+++` headers all hunks belong to one
+/// file with no path.
+pub fn parse(patch: &str) -> Result<Vec<FilePatch>, FileToolError> {
+    // Blank lines after the last hunk are not part of it
+    let lines: Vec<&str> = patch.trim_end_matches(['\n', '\r']).lines().collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut current: Option<FilePatch> = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        if is_file_header(&lines, i) {
+            files.extend(current.take());
+            current = Some(FilePatch {
+                old_path: header_path(&lines[i][4..]),
+                new_path: header_path(&lines[i + 1][4..]),
+                hunks: Vec::new(),
+            });
+            i += 2;
+        } else if lines[i].starts_with("@@") {
+            let (hunk, next) = parse_hunk(&lines, i);
+            current
+                .get_or_insert_with(FilePatch::default)
+                .hunks
+                .push(hunk);
+            i = next;
+        } else {
+            i += 1;
+        }
+    }
+    files.extend(current);
+
+    if files.iter().all(|file| file.hunks.is_empty()) {
+        return Err(FileToolError::InvalidInput(
+            "No hunks found in the patch; each change needs a '@@ -start,count +start,count @@' line"
+                .to_string(),
+        ));
+    }
+    if let Some(file) = files.iter().find(|file| file.hunks.is_empty()) {
+        return Err(FileToolError::InvalidInput(format!(
+            "No hunks for '{}' in the patch",
+            file.path().unwrap_or("(unnamed file)")
+        )));
+    }
+    Ok(files)
+}
+
+/// A `---` line directly followed by a `+++` line starts a file
+fn is_file_header(lines: &[&str], i: usize) -> bool {
+    lines[i].starts_with("--- ")
+        && lines
+            .get(i + 1)
+            .is_some_and(|next| next.starts_with("+++ "))
+}
+
+/// Path of a `---`/`+++` header, without a timestamp or `a/`/`b/` prefix
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or("").trim();
+    if path.is_empty() {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Parse the hunk starting at `lines[start]`, returning it and the next line index
+fn parse_hunk(lines: &[&str], start: usize) -> (Hunk, usize) {
+    let header = lines[start];
+    let mut hunk = Hunk {
+        header: header.to_string(),
+        old_start: parse_old_start(header),
+        ..Default::default()
+    };
+
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("@@") || is_file_header(lines, i) {
+            break;
+        }
+        match line.chars().next() {
+            Some(' ') => hunk.lines.push(HunkLine::Context(line[1..].to_string())),
+            Some('+') => hunk.lines.push(HunkLine::Add(line[1..].to_string())),
+            Some('-') => hunk.lines.push(HunkLine::Remove(line[1..].to_string())),
+            Some('\\') => match hunk.lines.last() {
+                Some(HunkLine::Remove(_)) => hunk.old_missing_newline = true,
+                Some(HunkLine::Add(_)) => hunk.new_missing_newline = true,
+                Some(HunkLine::Context(_)) => {
+                    hunk.old_missing_newline = true;
+                    hunk.new_missing_newline = true;
+                }
+                None => {}
+            },
+            // Models often drop the space of blank context lines
+            None => hunk.lines.push(HunkLine::Context(String::new())),
+            Some(_) => break,
+        }
+        i += 1;
+    }
+    (hunk, i)
+}
+
+/// 0-based start of the original side of `@@ -start,count +start,count @@`
+fn parse_old_start(header: &str) -> Option<usize> {
+    let range = header
+        .trim_start_matches('@')
+        .split_whitespace()
+        .next()?
+        .strip_prefix('-')?;
+    let mut parts = range.splitn(2, ',');
+    let start: usize = parts.next()?.parse().ok()?;
+    let count: usize = match parts.next() {
+        Some(count) => count.parse().ok()?,
+        None => 1,
+    };
+    // An empty range names the line after which the hunk inserts
+    Some(if count == 0 {
+        start
+    } else {
+        start.saturating_sub(1)
+    })
+}
+
+/// Region of the file most similar to a hunk that did not apply
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosestMatch {
+    /// First line of the region, 1-based
+    pub line: usize,
+    /// Lines of the region equal to the hunk, ignoring whitespace
+    pub matching_lines: usize,
+    /// Lines the hunk expects
+    pub total_lines: usize,
+    pub text: String,
+}
+
+/// How one hunk was applied, or why it was not
+#[derive(Debug, Clone, Serialize)]
+pub struct HunkReport {
+    /// 1-based position of the hunk in its file
+    pub hunk: usize,
+    pub header: String,
+    pub applied: bool,
+    /// Line of the original file the hunk matched at, 1-based
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Lines between the position in the header and the match
+    pub offset: isize,
+    /// Context lines ignored at the ends of the hunk to find a match
+    pub fuzz: usize,
+    /// The hunk only matched with whitespace differences ignored
+    pub ignored_whitespace: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closest: Option<ClosestMatch>,
+    /// Lines a hunk without line numbers matched at, when it matched more than once
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ambiguous: Vec<usize>,
+}
+
+impl HunkReport {
+    /// One line summary, followed by the closest region for a failed hunk
+    pub fn describe(&self) -> String {
+        let mut text = format!("hunk {} {}: ", self.hunk, self.header);
+        if !self.applied {
+            text.push_str("FAILED");
+            if !self.ambiguous.is_empty() {
+                let lines: Vec<String> = self.ambiguous.iter().map(|l| l.to_string()).collect();
+                text.push_str(&format!(
+                    ", matches at lines {}; add line numbers to the @@ header or more context",
+                    lines.join(", ")
+                ));
+                return text;
+            }
+            match &self.closest {
+                Some(closest) => text.push_str(&format!(
+                    ", closest match at line {} ({} of {} lines match):\n{}",
+                    closest.line,
+                    closest.matching_lines,
+                    closest.total_lines,
+                    closest
+                        .text
+                        .lines()
+                        .map(|line| format!("    | {}", line))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )),
+                None => text.push_str(", no similar lines in the file"),
+            }
+            return text;
+        }
+
+        text.push_str(&format!("applied at line {}", self.line.unwrap_or(1)));
+        let mut notes = Vec::new();
+        if self.offset != 0 {
+            notes.push(format!("offset {:+}", self.offset));
+        }
+        if self.fuzz > 0 {
+            notes.push(format!("fuzz {}", self.fuzz));
+        }
+        if self.ignored_whitespace {
+            notes.push("ignoring whitespace".to_string());
+        }
+        if !notes.is_empty() {
+            text.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        text
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LineMatch {
+    Exact,
+    IgnoreWhitespace,
+}
+
+impl LineMatch {
+    fn same(self, file_line: &str, hunk_line: &str) -> bool {
+        match self {
+            LineMatch::Exact => {
+                file_line.trim_end_matches('\r') == hunk_line.trim_end_matches('\r')
+            }
+            LineMatch::IgnoreWhitespace => file_line
+                .split_whitespace()
+                .eq(hunk_line.split_whitespace()),
+        }
+    }
+}
+
+/// Where a hunk matched in the file being patched
+struct Found {
+    /// Index of the first matched line
+    position: usize,
+    /// Context lines ignored at the start and at the end
+    skip_start: usize,
+    skip_end: usize,
+    mode: LineMatch,
+}
+
+/// Apply hunks to the content of one file
+///
+/// Each hunk is located by its content, nearest to the line in its header:
+/// first exactly, then ignoring whitespace, then ignoring up to `fuzz` context
+/// lines at each end. A hunk without line numbers in its header fails if it
+/// matches in more than one place. Hunks apply in order and never overlap. Context lines keep
+/// the file's version, and CRLF line endings and the final newline are preserved.
+///
+/// # Returns
+///
+/// The new content and a report per hunk, or every report if any hunk failed
+pub fn apply(
+    content: &str,
+    hunks: &[Hunk],
+    fuzz: usize,
+) -> Result<(String, Vec<HunkReport>), Vec<HunkReport>> {
+    let crlf = content.contains("\r\n");
+    let mut final_newline = content.is_empty() || content.ends_with('\n');
+    let mut lines: Vec<String> = if content.is_empty() {
+        Vec::new()
+    } else {
+        content
+            .strip_suffix('\n')
+            .unwrap_or(content)
+            .split('\n')
+            .map(String::from)
+            .collect()
+    };
+
+    let mut reports = Vec::new();
+    // Lines before `min` were written by an earlier hunk
+    let mut min = 0usize;
+    // Lines added minus lines removed by the hunks applied so far
+    let mut delta = 0isize;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let expected = hunk
+            .old_start
+            .map(|start| (start as isize + delta).max(0) as usize);
+        let mut report = HunkReport {
+            hunk: index + 1,
+            header: hunk.header.clone(),
+            applied: false,
+            line: None,
+            offset: 0,
+            fuzz: 0,
+            ignored_whitespace: false,
+            closest: None,
+            ambiguous: Vec::new(),
+        };
+
+        let found = match find_hunk(&lines, hunk, min, expected, fuzz) {
+            Ok(Some(found)) => found,
+            Ok(None) => {
+                report.closest = closest_match(&lines, &hunk.old_lines(), expected, delta);
+                reports.push(report);
+                continue;
+            }
+            Err(positions) => {
+                report.ambiguous = positions
+                    .into_iter()
+                    .map(|position| ((position as isize - delta) + 1).max(1) as usize)
+                    .collect();
+                reports.push(report);
+                continue;
+            }
+        };
+
+        let body = &hunk.lines[found.skip_start..hunk.lines.len() - found.skip_end];
+        let mut cursor = found.position;
+        let mut replacement = Vec::new();
+        for line in body {
+            match line {
+                HunkLine::Context(_) => {
+                    replacement.push(lines[cursor].clone());
+                    cursor += 1;
+                }
+                HunkLine::Remove(_) => cursor += 1,
+                HunkLine::Add(text) if crlf && !text.ends_with('\r') => {
+                    replacement.push(format!("{}\r", text))
+                }
+                HunkLine::Add(text) => replacement.push(text.clone()),
+            }
+        }
+
+        if cursor == lines.len() && found.skip_end == 0 {
+            if hunk.new_missing_newline {
+                final_newline = false;
+            } else if hunk.old_missing_newline {
+                final_newline = true;
+            }
+        }
+
+        let start = found.position as isize - delta;
+        report.applied = true;
+        report.line = Some((start + 1).max(1) as usize);
+        report.offset = expected.map_or(0, |expected| {
+            found.position as isize - (expected + found.skip_start) as isize
+        });
+        report.fuzz = found.skip_start.max(found.skip_end);
+        report.ignored_whitespace = found.mode == LineMatch::IgnoreWhitespace;
+        reports.push(report);
+
+        let removed = cursor - found.position;
+        delta += replacement.len() as isize - removed as isize;
+        min = found.position + replacement.len();
+        lines.splice(found.position..cursor, replacement);
+    }
+
+    if reports.iter().any(|report| !report.applied) {
+        return Err(reports);
+    }
+
+    let mut patched = lines.join("\n");
+    if final_newline && !lines.is_empty() {
+        patched.push('\n');
+    }
+    Ok((patched, reports))
+}
+
+/// Locate a hunk at or after `min`
+///
+/// # Returns
+///
+/// `Ok(None)` if it matches nowhere, or `Err` with every position it matches at
+/// when there is no `expected` line to pick the nearest one
+fn find_hunk(
+    lines: &[String],
+    hunk: &Hunk,
+    min: usize,
+    expected: Option<usize>,
+    fuzz: usize,
+) -> Result<Option<Found>, Vec<usize>> {
+    let old = hunk.old_lines();
+    let leading = hunk.leading_context();
+    let trailing = hunk.trailing_context();
+
+    for level in 0..=fuzz.min(leading.max(trailing)) {
+        let skip_start = level.min(leading);
+        let skip_end = level.min(trailing);
+        // Keep at least one line to match unless the hunk only adds lines
+        if !old.is_empty() && skip_start + skip_end >= old.len() {
+            break;
+        }
+        let window = &old[skip_start..old.len() - skip_end];
+        let expected = expected.map(|expected| expected + skip_start);
+
+        for mode in [LineMatch::Exact, LineMatch::IgnoreWhitespace] {
+            let positions = match_positions(lines, window, min, expected, mode);
+            if positions.len() > 1 && expected.is_none() {
+                return Err(positions);
+            }
+            // Positions are sorted nearest to `expected` first
+            if let Some(&position) = positions.first() {
+                return Ok(Some(Found {
+                    position,
+                    skip_start,
+                    skip_end,
+                    mode,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Positions at or after `min` where `window` matches, nearest to `expected` first
+fn match_positions(
+    lines: &[String],
+    window: &[&str],
+    min: usize,
+    expected: Option<usize>,
+    mode: LineMatch,
+) -> Vec<usize> {
+    if window.is_empty() {
+        // A hunk that only adds lines goes where its header says
+        let position = expected.unwrap_or(lines.len());
+        return vec![position.clamp(min, lines.len())];
+    }
+    if lines.len() < window.len() {
+        return Vec::new();
+    }
+
+    let mut positions: Vec<usize> = (min..=lines.len() - window.len())
+        .filter(|&position| matches_at(lines, window, position, mode))
+        .collect();
+    positions.sort_by_key(|&position| expected.map_or(position, |e| position.abs_diff(e)));
+    positions
+}
+
+fn matches_at(lines: &[String], window: &[&str], position: usize, mode: LineMatch) -> bool {
+    window
+        .iter()
+        .enumerate()
+        .all(|(i, line)| mode.same(&lines[position + i], line))
+}
+
+/// Lines where `text` matches whole lines of `content` when whitespace is ignored, 1-based
+pub(crate) fn whitespace_insensitive_matches(content: &str, text: &str) -> Vec<usize> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let window: Vec<&str> = text.lines().collect();
+    if window.is_empty() || lines.len() < window.len() {
+        return Vec::new();
+    }
+    (0..=lines.len() - window.len())
+        .filter(|&position| matches_at(&lines, &window, position, LineMatch::IgnoreWhitespace))
+        .map(|position| position + 1)
+        .collect()
+}
+
+/// Region of `content` with the most lines equal to the lines of `text`, ignoring whitespace
+pub(crate) fn closest_region(content: &str, text: &str) -> Option<ClosestMatch> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let window: Vec<&str> = text.lines().collect();
+    closest_match(&lines, &window, None, 0)
+}
+
+/// Region with the most lines equal to the hunk, ignoring whitespace
+fn closest_match(
+    lines: &[String],
+    old: &[&str],
+    expected: Option<usize>,
+    delta: isize,
+) -> Option<ClosestMatch> {
+    if old.is_empty() || lines.is_empty() {
+        return None;
+    }
+    let size = old.len().min(lines.len());
+
+    let (position, matching_lines) = (0..=lines.len() - size)
+        .map(|position| {
+            let score = (0..size)
+                .filter(|&i| LineMatch::IgnoreWhitespace.same(&lines[position + i], old[i]))
+                .count();
+            (position, score)
+        })
+        .filter(|&(_, score)| score > 0)
+        .max_by_key(|&(position, score)| {
+            let distance = expected.map_or(position, |e| position.abs_diff(e));
+            (score, std::cmp::Reverse(distance))
+        })?;
+
+    let text = lines[position..position + size.min(CLOSEST_MATCH_MAX_LINES)]
+        .iter()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(ClosestMatch {
+        line: ((position as isize - delta) + 1).max(1) as usize,
+        matching_lines,
+        total_lines: old.len(),
+        text,
+    })
+}
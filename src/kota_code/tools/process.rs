@@ -0,0 +1,606 @@
+use super::execute_bash_command::ExecOptions;
+use super::workspace::{confine, Access, Workspace};
+use super::FileToolError;
+use colored::*;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{Mutex, Notify};
+
+/// Longest `read_output` may wait for new output
+pub const MAX_WAIT_SECS: u64 = 60;
+
+/// Output a process wrote since the last `read_output`
+///
+/// When the model does not read for a while, the oldest unread bytes are
+/// dropped so a chatty server cannot use up memory.
+#[derive(Debug)]
+struct PendingOutput {
+    unread: VecDeque<u8>,
+    dropped: usize,
+    limit: usize,
+    open_streams: usize,
+}
+
+impl PendingOutput {
+    fn push(&mut self, bytes: &[u8]) {
+        self.unread.extend(bytes);
+        let overflow = self.unread.len().saturating_sub(self.limit);
+        if overflow > 0 {
+            self.unread.drain(..overflow);
+            self.dropped += overflow;
+        }
+    }
+
+    fn take(&mut self) -> String {
+        let text = String::from_utf8_lossy(self.unread.make_contiguous()).to_string();
+        let text = match self.dropped {
+            0 => text,
+            dropped => format!("[{} earlier bytes dropped]\n{}", dropped, text),
+        };
+        self.unread.clear();
+        self.dropped = 0;
+        text
+    }
+}
+
+/// A command started by the `process` tool
+struct BackgroundProcess {
+    command: String,
+    cwd: String,
+    started: Instant,
+    /// Process group id, cleared once the child is reaped and its id may be reused
+    pgid: Option<u32>,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    output: Arc<StdMutex<PendingOutput>>,
+    new_output: Arc<Notify>,
+    exit_code: Option<Option<i32>>,
+}
+
+impl BackgroundProcess {
+    /// Check whether the process exited, remembering its exit code
+    fn poll_exit(&mut self) {
+        if self.exit_code.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.reaped(status);
+            }
+        }
+    }
+
+    /// Remember the exit status of the reaped child
+    ///
+    /// Its process group id is forgotten, since another process may get it now.
+    fn reaped(&mut self, status: std::process::ExitStatus) {
+        self.exit_code = Some(status.code());
+        self.pgid = None;
+    }
+
+    /// Kill the process group, including the children the command started
+    ///
+    /// Once the command has been reaped its group id may be reused, so children
+    /// it left running are no longer signalled.
+    async fn kill(&mut self) {
+        #[cfg(unix)]
+        if let Some(pgid) = self.pgid.take() {
+            // SAFETY: killpg only sends a signal. `pgid` is cleared when the child is
+            // reaped, so the group id still belongs to our child and was not reused.
+            unsafe {
+                libc::killpg(pgid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+        if self.exit_code.is_none() {
+            let _ = self.child.kill().await;
+            self.poll_exit();
+        }
+        self.stdin = None;
+    }
+
+    fn info(&mut self, id: &str) -> ProcessInfo {
+        self.poll_exit();
+        ProcessInfo {
+            id: id.to_string(),
+            command: self.command.clone(),
+            cwd: self.cwd.clone(),
+            pid: self.child.id(),
+            running: self.exit_code.is_none(),
+            exit_code: self.exit_code.flatten(),
+            uptime_secs: self.started.elapsed().as_secs(),
+        }
+    }
+}
+
+impl Drop for BackgroundProcess {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pgid) = self.pgid {
+            // SAFETY: as in `kill`, `pgid` is only set while the child is not reaped.
+            unsafe {
+                libc::killpg(pgid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Copy a stream into the pending output until it closes
+async fn pump<R: AsyncRead + Unpin>(
+    mut reader: R,
+    output: Arc<StdMutex<PendingOutput>>,
+    new_output: Arc<Notify>,
+) {
+    let mut chunk = [0u8; 8192];
+    while let Ok(n) = reader.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        if let Ok(mut output) = output.lock() {
+            output.push(&chunk[..n]);
+        }
+        new_output.notify_waiters();
+    }
+    if let Ok(mut output) = output.lock() {
+        output.open_streams -= 1;
+    }
+    new_output.notify_waiters();
+}
+
+/// Background processes started by the agent
+///
+/// Clones share the same processes. Every process is killed by
+/// [`kill_all`](Self::kill_all), or at the latest when the last clone is dropped.
+#[derive(Clone, Default)]
+pub struct ProcessManager {
+    processes: Arc<Mutex<BTreeMap<String, BackgroundProcess>>>,
+    next_id: Arc<AtomicUsize>,
+}
+
+impl ProcessManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start `command` with `bash -c` in its own process group
+    ///
+    /// # Returns
+    ///
+    /// The new process, whose `id` is the handle for the other actions
+    pub async fn start(
+        &self,
+        command: &str,
+        cwd: Option<&str>,
+        options: &ExecOptions,
+    ) -> Result<ProcessInfo, FileToolError> {
+        let cwd = Path::new(cwd.unwrap_or(".")).canonicalize().map_err(|e| {
+            FileToolError::InvalidInput(format!("Invalid working directory: {}", e))
+        })?;
+
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
+        } else {
+            let mut cmd = Command::new("bash");
+            cmd.args(["-c", command]);
+            cmd
+        };
+        options.apply_env_policy(&mut cmd)?;
+        cmd.current_dir(&cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let output = Arc::new(StdMutex::new(PendingOutput {
+            unread: VecDeque::new(),
+            dropped: 0,
+            limit: options.max_output_bytes,
+            open_streams: 2,
+        }));
+        let new_output = Arc::new(Notify::new());
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(pump(stdout, output.clone(), new_output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(pump(stderr, output.clone(), new_output.clone()));
+        }
+
+        let id = format!("p{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let mut process = BackgroundProcess {
+            command: command.to_string(),
+            cwd: cwd.to_string_lossy().to_string(),
+            started: Instant::now(),
+            pgid: child.id(),
+            stdin: child.stdin.take(),
+            child,
+            output,
+            new_output,
+            exit_code: None,
+        };
+        let info = process.info(&id);
+        self.processes.lock().await.insert(id, process);
+        Ok(info)
+    }
+
+    /// Take the output written since the last read
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Process handle from [`start`](Self::start)
+    /// * `wait` - When nothing is unread, how long to wait for output or exit
+    pub async fn read_output(
+        &self,
+        id: &str,
+        wait: Duration,
+    ) -> Result<(ProcessInfo, String), FileToolError> {
+        let (output, new_output) = {
+            let mut processes = self.processes.lock().await;
+            let process = Self::get(&mut processes, id)?;
+            (process.output.clone(), process.new_output.clone())
+        };
+
+        // Wait without holding the table, so other calls are not blocked
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let notified = new_output.notified();
+            let idle = output
+                .lock()
+                .map(|output| output.unread.is_empty() && output.open_streams > 0)
+                .unwrap_or(false);
+            if !idle || tokio::time::timeout_at(deadline, notified).await.is_err() {
+                break;
+            }
+        }
+
+        let mut processes = self.processes.lock().await;
+        let process = Self::get(&mut processes, id)?;
+        let (text, closed) = output
+            .lock()
+            .map(|mut output| (output.take(), output.open_streams == 0))
+            .unwrap_or_default();
+
+        // Closed pipes usually mean the process is exiting, give it a moment
+        if closed && process.exit_code.is_none() {
+            if let Ok(Ok(status)) =
+                tokio::time::timeout(Duration::from_millis(200), process.child.wait()).await
+            {
+                process.reaped(status);
+            }
+        }
+        Ok((process.info(id), text))
+    }
+
+    /// Write to the process's stdin
+    pub async fn send_input(&self, id: &str, input: &str) -> Result<ProcessInfo, FileToolError> {
+        let mut processes = self.processes.lock().await;
+        let process = Self::get(&mut processes, id)?;
+        process.poll_exit();
+        if process.exit_code.is_some() {
+            return Err(FileToolError::InvalidInput(format!(
+                "Process {} has exited",
+                id
+            )));
+        }
+        let stdin = process.stdin.as_mut().ok_or_else(|| {
+            FileToolError::InvalidInput(format!("Stdin of process {} is closed", id))
+        })?;
+        stdin.write_all(input.as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(process.info(id))
+    }
+
+    /// Describe one process, or every process when `id` is `None`
+    pub async fn status(&self, id: Option<&str>) -> Result<Vec<ProcessInfo>, FileToolError> {
+        let mut processes = self.processes.lock().await;
+        match id {
+            Some(id) => Ok(vec![Self::get(&mut processes, id)?.info(id)]),
+            None => Ok(processes
+                .iter_mut()
+                .map(|(id, process)| process.info(id))
+                .collect()),
+        }
+    }
+
+    /// Kill a process and everything it started
+    pub async fn kill(&self, id: &str) -> Result<ProcessInfo, FileToolError> {
+        let mut processes = self.processes.lock().await;
+        let process = Self::get(&mut processes, id)?;
+        process.kill().await;
+        Ok(process.info(id))
+    }
+
+    /// Kill every process, e.g. when kota exits
+    pub async fn kill_all(&self) {
+        let mut processes = self.processes.lock().await;
+        for process in processes.values_mut() {
+            process.kill().await;
+        }
+        processes.clear();
+    }
+
+    /// Number of processes still running
+    pub async fn running_count(&self) -> usize {
+        let mut processes = self.processes.lock().await;
+        processes
+            .values_mut()
+            .map(|process| {
+                process.poll_exit();
+                process.exit_code.is_none()
+            })
+            .filter(|running| *running)
+            .count()
+    }
+
+    fn get<'a>(
+        processes: &'a mut BTreeMap<String, BackgroundProcess>,
+        id: &str,
+    ) -> Result<&'a mut BackgroundProcess, FileToolError> {
+        processes
+            .get_mut(id)
+            .ok_or_else(|| FileToolError::InvalidInput(format!("No process with id '{}'", id)))
+    }
+}
+
+/// State of a background process
+#[derive(Serialize, Debug, Clone)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub command: String,
+    pub cwd: String,
+    pub pid: Option<u32>,
+    pub running: bool,
+    pub exit_code: Option<i32>,
+    pub uptime_secs: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ProcessArgs {
+    Start {
+        command: String,
+        #[serde(default)]
+        cwd: Option<String>,
+    },
+    ReadOutput {
+        id: String,
+        #[serde(default)]
+        wait_secs: Option<u64>,
+    },
+    SendInput {
+        id: String,
+        input: String,
+    },
+    Status {
+        #[serde(default)]
+        id: Option<String>,
+    },
+    Kill {
+        id: String,
+    },
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProcessOutput {
+    pub success: bool,
+    pub message: String,
+    /// The process the action concerns, or every process for `status` without an id
+    pub processes: Vec<ProcessInfo>,
+    /// Output since the previous `read_output`, stdout and stderr interleaved
+    pub output: Option<String>,
+}
+
+pub struct ProcessTool {
+    manager: ProcessManager,
+    options: ExecOptions,
+}
+
+impl ProcessTool {
+    pub fn new(manager: ProcessManager) -> Self {
+        Self {
+            manager,
+            options: ExecOptions::default(),
+        }
+    }
+
+    /// Set the output limit and environment policy for started processes
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Tool for ProcessTool {
+    const NAME: &'static str = "process";
+    type Error = FileToolError;
+    type Args = ProcessArgs;
+    type Output = ProcessOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "process".to_string(),
+            description: "Run long-lived commands in the background, such as dev servers or watchers, and keep working while they run. \
+                          Start returns an id; use it to read new output, send input, check status or kill the process. \
+                          Background processes are killed when kota exits.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["start", "read_output", "send_input", "status", "kill"],
+                        "description": "Action: start (run command in background), read_output (output since the last read), send_input (write to stdin), status (one process, or all without id), kill (stop the process)"
+                    },
+                    "command": {
+                        "type": "string",
+                        "description": "Command to run with bash (for start action). Example: 'cargo run --bin server'"
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Directory to start the command in (for start action, default: the workspace root)"
+                    },
+                    "id": {
+                        "type": "string",
+                        "description": "Process id returned by start, e.g. 'p1' (for read_output, send_input, status and kill actions)"
+                    },
+                    "input": {
+                        "type": "string",
+                        "description": "Text to write to stdin, include '\\n' to end a line (for send_input action)"
+                    },
+                    "wait_secs": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": format!("If there is no new output yet, wait up to this many seconds for some (for read_output action, default 0, max {})", MAX_WAIT_SECS)
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args {
+            ProcessArgs::Start { command, cwd } => {
+                let info = self
+                    .manager
+                    .start(&command, cwd.as_deref(), &self.options)
+                    .await?;
+                Ok(ProcessOutput {
+                    success: true,
+                    message: format!("Started process {}", info.id),
+                    processes: vec![info],
+                    output: None,
+                })
+            }
+            ProcessArgs::ReadOutput { id, wait_secs } => {
+                let wait = Duration::from_secs(wait_secs.unwrap_or(0).min(MAX_WAIT_SECS));
+                let (info, output) = self.manager.read_output(&id, wait).await?;
+                let message = match (info.running, info.exit_code) {
+                    (true, _) => format!("Process {} is running", id),
+                    (false, Some(code)) => format!("Process {} exited with code {}", id, code),
+                    (false, None) => format!("Process {} was killed", id),
+                };
+                Ok(ProcessOutput {
+                    success: true,
+                    message,
+                    processes: vec![info],
+                    output: Some(output),
+                })
+            }
+            ProcessArgs::SendInput { id, input } => {
+                let info = self.manager.send_input(&id, &input).await?;
+                Ok(ProcessOutput {
+                    success: true,
+                    message: format!("Sent {} bytes to process {}", input.len(), id),
+                    processes: vec![info],
+                    output: None,
+                })
+            }
+            ProcessArgs::Status { id } => {
+                let processes = self.manager.status(id.as_deref()).await?;
+                Ok(ProcessOutput {
+                    success: true,
+                    message: format!("{} process(es)", processes.len()),
+                    processes,
+                    output: None,
+                })
+            }
+            ProcessArgs::Kill { id } => {
+                let info = self.manager.kill(&id).await?;
+                Ok(ProcessOutput {
+                    success: true,
+                    message: format!("Killed process {}", id),
+                    processes: vec![info],
+                    output: None,
+                })
+            }
+        }
+    }
+}
+
+pub struct WrappedProcessTool {
+    inner: ProcessTool,
+    workspace: Option<Workspace>,
+}
+
+impl WrappedProcessTool {
+    pub fn new(manager: ProcessManager) -> Self {
+        Self {
+            inner: ProcessTool::new(manager),
+            workspace: None,
+        }
+    }
+
+    /// Confine the `cwd` of started processes to a workspace root, which is
+    /// also where they start without one
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Set the output limit and environment policy for started processes
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.inner = self.inner.with_options(options);
+        self
+    }
+}
+
+impl Tool for WrappedProcessTool {
+    const NAME: &'static str = "process";
+    type Error = FileToolError;
+    type Args = <ProcessTool as Tool>::Args;
+    type Output = <ProcessTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let label = match &args {
+            ProcessArgs::Start { command, .. } => format!("start {}", command),
+            ProcessArgs::ReadOutput { id, .. } => format!("read_output {}", id),
+            ProcessArgs::SendInput { id, .. } => format!("send_input {}", id),
+            ProcessArgs::Status { id } => format!("status {}", id.as_deref().unwrap_or("all")),
+            ProcessArgs::Kill { id } => format!("kill {}", id),
+        };
+        println!("\n{} Process({})", "●".bright_green(), label);
+
+        let confined = match (&mut args, &self.workspace) {
+            (ProcessArgs::Start { cwd: Some(cwd), .. }, workspace) => {
+                confine(workspace.as_ref(), cwd, Access::Read)
+            }
+            (ProcessArgs::Start { cwd, .. }, Some(workspace)) => {
+                *cwd = Some(workspace.root().to_string_lossy().to_string());
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        let result = match confined {
+            Ok(()) => self.inner.call(args).await,
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(output) => match &output.output {
+                Some(text) => println!(
+                    "  └─ {} ... +{} lines output",
+                    output.message.dimmed(),
+                    text.lines().count()
+                ),
+                None => println!("  └─ {}", output.message.dimmed()),
+            },
+            Err(e) => {
+                println!("  └─ {}", format!("Error: {}", e).red());
+            }
+        }
+        println!();
+        result
+    }
+}
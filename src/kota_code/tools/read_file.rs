@@ -1,20 +1,48 @@
+use super::workspace::{confine, Access, Workspace};
 use super::FileToolError;
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 
-#[derive(Deserialize)]
+/// Default number of bytes of numbered content returned by one read
+pub const DEFAULT_MAX_READ_BYTES: usize = 50_000;
+
+/// Bytes checked for a NUL byte to tell binary files from text
+const BINARY_CHECK_BYTES: usize = 8000;
+
+#[derive(Deserialize, Default)]
 pub struct ReadFileArgs {
     pub file_path: String,
+    /// 1-based line to start reading at (default: 1)
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// Maximum number of lines to return (default: until the byte limit)
+    #[serde(default)]
+    pub limit: Option<usize>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct ReadFileOutput {
+    /// The lines read, each prefixed with its line number and a tab
     pub content: String,
     pub file_path: String,
     pub size_bytes: u64,
+    /// Lines in the whole file; `None` when the read stopped before the end of the file
+    pub total_lines: Option<usize>,
+    /// First line returned, 1-based
+    pub start_line: usize,
+    /// Last line returned, 1-based; `start_line - 1` when no line was returned
+    pub end_line: usize,
+    /// The byte limit cut the read short before `limit` or the end of the file
+    pub truncated: bool,
+    /// The file looks binary, so only its metadata is returned
+    pub binary: bool,
+    /// The file is not valid UTF-8; invalid bytes were replaced with U+FFFD
+    pub lossy: bool,
     pub success: bool,
     pub message: String,
 }
@@ -22,6 +50,189 @@ pub struct ReadFileOutput {
 #[derive(Deserialize, Serialize, Default)]
 pub struct ReadFileTool;
 
+impl ReadFileTool {
+    /// Read a range of lines from a file
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The file and the optional `offset`/`limit` line range
+    /// * `max_bytes` - Bytes of numbered content after which the read stops
+    pub(crate) fn read(
+        args: ReadFileArgs,
+        max_bytes: usize,
+    ) -> Result<ReadFileOutput, FileToolError> {
+        let file_path = args.file_path;
+        let path = Path::new(&file_path);
+
+        // Check if file exists
+        if !path.exists() {
+            return Err(FileToolError::FileNotFound(file_path));
+        }
+
+        // Check if it's actually a file (not a directory)
+        if !path.is_file() {
+            return Err(FileToolError::NotAFile(file_path));
+        }
+
+        let start_line = args.offset.unwrap_or(1);
+        if start_line == 0 {
+            return Err(FileToolError::InvalidInput(
+                "offset is 1-based and must be at least 1".to_string(),
+            ));
+        }
+        if args.limit == Some(0) {
+            return Err(FileToolError::InvalidInput(
+                "limit must be at least 1".to_string(),
+            ));
+        }
+
+        let file = File::open(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                FileToolError::PermissionDenied(file_path.clone())
+            }
+            _ => FileToolError::Io(e),
+        })?;
+        let size_bytes = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut head = Vec::new();
+        (&mut reader)
+            .take(BINARY_CHECK_BYTES as u64)
+            .read_to_end(&mut head)?;
+        if head.contains(&0) {
+            return Ok(ReadFileOutput {
+                content: String::new(),
+                message: format!(
+                    "'{}' is a binary file of {} bytes; its content is not shown",
+                    file_path, size_bytes
+                ),
+                file_path,
+                size_bytes,
+                total_lines: None,
+                start_line: 0,
+                end_line: 0,
+                truncated: false,
+                binary: true,
+                lossy: false,
+                success: true,
+            });
+        }
+        let mut reader = head.as_slice().chain(reader);
+
+        // Lines before the range are skipped without being decoded
+        let mut skipped = 0;
+        while skipped + 1 < start_line && reader.skip_until(b'\n')? > 0 {
+            skipped += 1;
+        }
+        if start_line > 1 && reader.fill_buf()?.is_empty() {
+            return Err(FileToolError::InvalidInput(format!(
+                "offset {} is past the end of '{}', which has {} lines",
+                start_line, file_path, skipped
+            )));
+        }
+
+        let limit = args.limit.unwrap_or(usize::MAX);
+        let mut content = String::new();
+        let mut end_line = start_line - 1;
+        let mut truncated = false;
+        let mut lossy = false;
+        let mut eof = false;
+        let mut line = Vec::new();
+        while end_line + 1 - start_line < limit {
+            line.clear();
+            // A line longer than the limit is only read up to it, since it gets cut anyway
+            if (&mut reader)
+                .take(max_bytes as u64 + 1)
+                .read_until(b'\n', &mut line)?
+                == 0
+            {
+                eof = true;
+                break;
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+            } else if line.len() > max_bytes {
+                // Drop a character split by the cut so it is not taken for invalid UTF-8
+                if let Err(e) = std::str::from_utf8(&line) {
+                    if e.error_len().is_none() {
+                        line.truncate(e.valid_up_to());
+                    }
+                }
+            }
+            let text = String::from_utf8_lossy(&line);
+            lossy |= matches!(text, Cow::Owned(_));
+
+            let numbered = format!("{:>6}\t{}\n", end_line + 1, text);
+            if content.len() + numbered.len() > max_bytes {
+                truncated = true;
+                if content.is_empty() {
+                    // A single line longer than the limit is cut rather than skipped
+                    let mut cut = max_bytes;
+                    while !numbered.is_char_boundary(cut) {
+                        cut -= 1;
+                    }
+                    content.push_str(&numbered[..cut]);
+                    content.push('\n');
+                    end_line += 1;
+                }
+                break;
+            }
+            content.push_str(&numbered);
+            end_line += 1;
+        }
+
+        // The total is only known when the read reached the end of the file
+        let total_lines = if !truncated && (eof || reader.fill_buf()?.is_empty()) {
+            Some(end_line)
+        } else {
+            None
+        };
+
+        let mut message = match total_lines {
+            Some(0) => format!("'{}' is empty", file_path),
+            Some(total) => format!(
+                "Read lines {}-{} of {} from '{}' ({} bytes)",
+                start_line, end_line, total, file_path, size_bytes
+            ),
+            None => format!(
+                "Read lines {}-{} from '{}' ({} bytes)",
+                start_line, end_line, file_path, size_bytes
+            ),
+        };
+        if truncated {
+            let notice = format!(
+                "Output truncated at {} bytes; pass offset={} to read on",
+                max_bytes,
+                end_line + 1
+            );
+            content.push_str(&format!("... {}\n", notice));
+            message.push_str(&format!(". {}", notice));
+        } else if total_lines.is_none() {
+            message.push_str(&format!("; pass offset={} to read on", end_line + 1));
+        }
+        if lossy {
+            message.push_str(". The file is not valid UTF-8; invalid bytes were replaced");
+        }
+
+        Ok(ReadFileOutput {
+            content,
+            file_path,
+            size_bytes,
+            total_lines,
+            start_line,
+            end_line,
+            truncated,
+            binary: false,
+            lossy,
+            success: true,
+            message,
+        })
+    }
+}
+
 impl Tool for ReadFileTool {
     const NAME: &'static str = "read_file";
 
@@ -32,13 +243,25 @@ impl Tool for ReadFileTool {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: "read_file".to_string(),
-            description: "Read the contents of a file from the filesystem. Supports text files and returns the content as a string.".to_string(),
+            description: "Read lines of a file from the filesystem. Each line is prefixed with its line number and a tab. \
+                Long files are cut off after a byte limit; use offset and limit to read a specific range, for example around a line reported by grep. \
+                Binary files return only their size, and invalid UTF-8 bytes are replaced.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "file_path": {
                         "type": "string",
                         "description": "The path to the file to read (relative or absolute). Examples: 'README.md', 'src/main.rs', '/path/to/file.txt'"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "The 1-based line number to start reading from (default: 1)."
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "The maximum number of lines to read (default: as many as fit in the byte limit)."
                     }
                 },
                 "required": ["file_path"]
@@ -47,58 +270,39 @@ impl Tool for ReadFileTool {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let file_path = &args.file_path;
-        let path = Path::new(file_path);
-
-        // Check if file exists
-        if !path.exists() {
-            return Err(FileToolError::FileNotFound(file_path.clone()));
-        }
-
-        // Check if it's actually a file (not a directory)
-        if !path.is_file() {
-            return Err(FileToolError::NotAFile(file_path.clone()));
-        }
-
-        // Try to read the file
-        match fs::read_to_string(file_path) {
-            Ok(content) => {
-                // Get file metadata for size
-                let metadata = fs::metadata(file_path)?;
-                let size_bytes = metadata.len();
-
-                Ok(ReadFileOutput {
-                    content,
-                    file_path: file_path.clone(),
-                    size_bytes,
-                    success: true,
-                    message: format!(
-                        "Successfully read {} bytes from '{}'",
-                        size_bytes, file_path
-                    ),
-                })
-            }
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::PermissionDenied => {
-                    Err(FileToolError::PermissionDenied(file_path.clone()))
-                }
-                _ => Err(FileToolError::Io(e)),
-            },
-        }
+        Self::read(args, DEFAULT_MAX_READ_BYTES)
     }
 }
 // 在工具调用前后显示信息
 #[derive(Deserialize, Serialize, Default)]
 pub struct WrappedReadFileTool {
     inner: ReadFileTool,
+    #[serde(skip)]
+    workspace: Option<Workspace>,
+    #[serde(skip)]
+    max_bytes: usize,
 }
 
 impl WrappedReadFileTool {
     pub fn new() -> Self {
         Self {
             inner: ReadFileTool,
+            workspace: None,
+            max_bytes: DEFAULT_MAX_READ_BYTES,
         }
     }
+
+    /// Confine `file_path` to a workspace root
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Set how many bytes of numbered content one read returns (default: 50000)
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
 }
 
 impl Tool for WrappedReadFileTool {
@@ -112,26 +316,55 @@ impl Tool for WrappedReadFileTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
         println!("\n{} Read({})", "●".bright_green(), args.file_path);
 
-        let result = self.inner.call(args).await;
+        let result = match confine(self.workspace.as_ref(), &mut args.file_path, Access::Read) {
+            Ok(()) => ReadFileTool::read(args, self.max_bytes),
+            Err(e) => Err(e),
+        };
 
         match &result {
+            Ok(output) if output.binary => {
+                println!(
+                    "  └─ {}",
+                    format!("binary file, {} bytes", output.size_bytes).dimmed()
+                );
+            }
             Ok(output) => {
                 // 对于读取文件，显示行数和预览
-                let line_count = output.content.lines().count();
-                let first_line = output.content.lines().next().unwrap_or("");
-                let preview = if first_line.len() > 50 {
-                    format!("{}...", &first_line[..50])
+                let line_count = output.end_line + 1 - output.start_line;
+                let first_line = output
+                    .content
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_once('\t'))
+                    .map_or("", |(_, text)| text);
+                let preview: String = if first_line.chars().count() > 50 {
+                    format!("{}...", first_line.chars().take(50).collect::<String>())
                 } else {
                     first_line.to_string()
                 };
+                let mut notes = Vec::new();
+                if output.truncated {
+                    notes.push("truncated");
+                }
+                if output.lossy {
+                    notes.push("lossy UTF-8");
+                }
                 println!(
-                    "  └─ {}| {} ... +{} lines",
-                    "1".dimmed(),
+                    "  └─ {}| {} ... +{} lines{}{}",
+                    output.start_line.to_string().dimmed(),
                     preview.dimmed(),
-                    line_count
+                    line_count,
+                    output
+                        .total_lines
+                        .map_or(String::new(), |total| format!(" of {}", total)),
+                    if notes.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", notes.join(", ")).yellow().to_string()
+                    }
                 );
             }
             Err(e) => {
@@ -0,0 +1,412 @@
+use super::execute_bash_command::{
+    kill_process_group, CommandInterrupt, ExecOptions, OutputBuffer,
+};
+use super::FileToolError;
+use colored::*;
+use rig::{completion::ToolDefinition, tool::Tool};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+/// Start of the line the shell prints after each command, followed by a nonce
+const SENTINEL_PREFIX: &str = "__KOTA_SHELL_DONE_";
+
+/// How a command in the session ended
+enum Outcome {
+    /// Exit code and working directory after the command
+    Finished(i32, String),
+    /// The shell itself exited, e.g. after `exit`
+    ShellExited(Option<i32>),
+    TimedOut,
+    Cancelled,
+}
+
+/// A long-lived `bash` process commands are written to
+struct ShellProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    sentinel: String,
+    cwd: String,
+}
+
+impl ShellProcess {
+    fn spawn(options: &ExecOptions) -> Result<Self, FileToolError> {
+        let mut cmd = Command::new("bash");
+        cmd.args(["--noprofile", "--norc"]);
+        options.apply_env_policy(&mut cmd)?;
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        // A process group of its own lets a timeout kill the shell and its jobs together
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| {
+            FileToolError::InvalidInput("Failed to open the shell's stdin".to_string())
+        })?;
+        let stdout = child.stdout.take().ok_or_else(|| {
+            FileToolError::InvalidInput("Failed to open the shell's stdout".to_string())
+        })?;
+
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            sentinel: format!("{}{:x}_{:x}__", SENTINEL_PREFIX, std::process::id(), nonce),
+            cwd: std::env::current_dir()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Shell input that runs `command` in the current shell and prints the sentinel
+    ///
+    /// The command goes through `eval`, so a syntax error fails the command
+    /// instead of ending the shell. Its stdin is `/dev/null` so it cannot read
+    /// the lines meant for the shell.
+    fn script(&self, command: &str) -> String {
+        format!(
+            "__kota_cmd={}\n{{ eval \"$__kota_cmd\"\n}} < /dev/null 2>&1\nprintf '\\n%s %d %s\\n' '{}' \"$?\" \"$PWD\"\n",
+            ansi_c_quote(command),
+            self.sentinel
+        )
+    }
+
+    /// Read output until the sentinel line, keeping memory bounded by `output`
+    ///
+    /// # Returns
+    ///
+    /// The exit code and working directory, or `None` if the shell exited
+    async fn read_until_sentinel(
+        &mut self,
+        output: &mut OutputBuffer,
+    ) -> std::io::Result<Option<(i32, String)>> {
+        let sentinel = self.sentinel.as_bytes();
+        let mut line = Vec::new();
+        loop {
+            let available = self.stdout.fill_buf().await?;
+            if available.is_empty() {
+                output.push(&line);
+                return Ok(None);
+            }
+            let (len, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            line.extend_from_slice(&available[..len]);
+            self.stdout.consume(len);
+
+            if complete {
+                if let Some(rest) = line.strip_prefix(sentinel) {
+                    let rest = String::from_utf8_lossy(rest);
+                    let rest = rest.trim_start().trim_end_matches('\n');
+                    let (code, cwd) = rest.split_once(' ').unwrap_or((rest, ""));
+                    // Drop the newline printed before the sentinel
+                    output.pop_newline();
+                    return Ok(Some((code.parse().unwrap_or(-1), cwd.to_string())));
+                }
+                output.push(&line);
+                line.clear();
+            } else if !sentinel.starts_with(&line[..line.len().min(sentinel.len())]) {
+                // Not the sentinel line, so it need not be held back whole
+                output.push(&line);
+                line.clear();
+            }
+        }
+    }
+}
+
+impl Drop for ShellProcess {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            // SAFETY: killpg only sends a signal. `child.id()` is `None` once the shell
+            // has been reaped, so the group id still belongs to it and was not reused.
+            unsafe {
+                libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Quote a string as a bash `$'...'` literal
+fn ansi_c_quote(value: &str) -> String {
+    let mut quoted = String::from("$'");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[derive(Deserialize)]
+pub struct ShellSessionArgs {
+    pub command: String,
+    /// Seconds before the session is killed; `None` uses [`ExecOptions::timeout_secs`]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Start a fresh shell before running the command
+    #[serde(default)]
+    pub restart: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ShellSessionOutput {
+    pub command: String,
+    pub success: bool,
+    /// stdout and stderr, interleaved as the command wrote them
+    pub output: String,
+    pub exit_code: Option<i32>,
+    /// Working directory of the session after the command
+    pub cwd: String,
+    /// A new shell was started for this call, so earlier state is gone
+    pub new_session: bool,
+    /// The command was killed after running for `timeout_secs`
+    pub timed_out: bool,
+    /// The command was cancelled by the user
+    pub cancelled: bool,
+    /// Part of the output was dropped
+    pub truncated: bool,
+}
+
+/// Runs commands in one `bash` process that lives as long as the tool
+///
+/// `cd`, exported variables, activated virtualenvs and shell functions carry
+/// over between calls. A timeout or cancellation kills the shell, and the next
+/// call starts a new one.
+#[derive(Default)]
+pub struct ShellSessionTool {
+    options: ExecOptions,
+    interrupt: CommandInterrupt,
+    shell: Arc<Mutex<Option<ShellProcess>>>,
+}
+
+impl ShellSessionTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the default timeout, output limit and environment policy
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Let a shared [`CommandInterrupt`] cancel the commands of this tool
+    pub fn with_interrupt(mut self, interrupt: CommandInterrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+}
+
+impl Tool for ShellSessionTool {
+    const NAME: &'static str = "shell_session";
+
+    type Error = FileToolError;
+    type Args = ShellSessionArgs;
+    type Output = ShellSessionOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "shell_session".to_string(),
+            description: format!(
+                "Run a command in a persistent bash session. Unlike exec_cmd, the working directory (cd), \
+                 exported variables, activated virtualenvs and shell functions carry over between calls. \
+                 stderr is merged into the output and the command gets no stdin. A command is killed after \
+                 timeout_secs (default {}s); a timeout or cancellation restarts the session and its state is lost.",
+                self.options.timeout_secs
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "The command to run in the session. Examples: 'cd crates/core', 'source .venv/bin/activate', 'cargo test'"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": format!("Seconds before the command is killed (default: {})", self.options.timeout_secs)
+                    },
+                    "restart": {
+                        "type": "boolean",
+                        "description": "Start a fresh session before running the command (default: false)"
+                    }
+                },
+                "required": ["command"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let timeout_secs = args.timeout_secs.unwrap_or(self.options.timeout_secs);
+        if timeout_secs == 0 {
+            return Err(FileToolError::InvalidInput(
+                "timeout_secs must be at least 1".to_string(),
+            ));
+        }
+
+        // One command at a time: the session has a single stdin and stdout
+        let mut shell = self.shell.lock().await;
+        if args.restart {
+            *shell = None;
+        }
+        let new_session = shell.is_none();
+        if new_session {
+            *shell = Some(ShellProcess::spawn(&self.options)?);
+        }
+        let Some(process) = shell.as_mut() else {
+            unreachable!("the shell was just started");
+        };
+
+        let cancelled = self.interrupt.notified();
+        let _running = self.interrupt.running();
+        let script = process.script(&args.command);
+        process.stdin.write_all(script.as_bytes()).await?;
+        process.stdin.flush().await?;
+
+        let mut output = OutputBuffer::new(self.options.max_output_bytes);
+        let outcome = tokio::select! {
+            done = process.read_until_sentinel(&mut output) => match done? {
+                Some((code, cwd)) => Outcome::Finished(code, cwd),
+                None => Outcome::ShellExited(process.child.wait().await?.code()),
+            },
+            _ = tokio::time::sleep(Duration::from_secs(timeout_secs)) => Outcome::TimedOut,
+            _ = cancelled => Outcome::Cancelled,
+        };
+
+        let truncated = output.truncated();
+        let mut text = output.into_string();
+        let cwd = match &outcome {
+            Outcome::Finished(_, cwd) => cwd.clone(),
+            _ => process.cwd.clone(),
+        };
+        let exit_code = match outcome {
+            Outcome::Finished(code, _) => {
+                process.cwd = cwd.clone();
+                Some(code)
+            }
+            Outcome::ShellExited(code) => {
+                text.push_str("\n[the shell exited; the next call starts a new session]");
+                *shell = None;
+                code
+            }
+            Outcome::TimedOut | Outcome::Cancelled => {
+                kill_process_group(&mut process.child).await;
+                text.push_str(&if matches!(outcome, Outcome::TimedOut) {
+                    format!(
+                        "\n[command timed out after {}s; the session was killed and its state is lost]",
+                        timeout_secs
+                    )
+                } else {
+                    "\n[command cancelled by the user; the session was killed and its state is lost]"
+                        .to_string()
+                });
+                *shell = None;
+                None
+            }
+        };
+
+        Ok(ShellSessionOutput {
+            command: args.command,
+            success: exit_code == Some(0),
+            output: text,
+            exit_code,
+            cwd,
+            new_session,
+            timed_out: matches!(outcome, Outcome::TimedOut),
+            cancelled: matches!(outcome, Outcome::Cancelled),
+            truncated,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct WrappedShellSessionTool {
+    inner: ShellSessionTool,
+}
+
+impl WrappedShellSessionTool {
+    pub fn new() -> Self {
+        Self {
+            inner: ShellSessionTool::new(),
+        }
+    }
+
+    /// Set the default timeout, output limit and environment policy
+    pub fn with_options(mut self, options: ExecOptions) -> Self {
+        self.inner = self.inner.with_options(options);
+        self
+    }
+
+    /// Let a shared [`CommandInterrupt`] cancel the commands of this tool
+    pub fn with_interrupt(mut self, interrupt: CommandInterrupt) -> Self {
+        self.inner = self.inner.with_interrupt(interrupt);
+        self
+    }
+}
+
+impl Tool for WrappedShellSessionTool {
+    const NAME: &'static str = "shell_session";
+
+    type Error = FileToolError;
+    type Args = <ShellSessionTool as Tool>::Args;
+    type Output = <ShellSessionTool as Tool>::Output;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        println!("\n{} Shell({})", "●".bright_green(), args.command);
+
+        let result = self.inner.call(args).await;
+
+        match &result {
+            Ok(output) => {
+                if output.timed_out {
+                    println!("  └─ {}", "Command timed out, session restarted".red());
+                } else if output.cancelled {
+                    println!("  └─ {}", "Command cancelled, session restarted".yellow());
+                } else if output.success {
+                    let lines = output.output.lines().count();
+                    println!(
+                        "  └─ {} in {} ... +{} lines output",
+                        "Command succeeded".dimmed(),
+                        output.cwd,
+                        lines
+                    );
+                } else {
+                    println!(
+                        "  └─ {} (exit: {})",
+                        "Command failed".red(),
+                        output.exit_code.unwrap_or(-1)
+                    );
+                }
+            }
+            Err(e) => {
+                println!("  └─ {}", format!("Error: {}", e).red());
+            }
+        }
+        println!();
+        result
+    }
+}
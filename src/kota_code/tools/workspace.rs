@@ -0,0 +1,193 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::{Component, Path, PathBuf};
+
+use super::FileToolError;
+
+/// Paths file tools may never write to, relative to the workspace root
+///
+/// Nested `.git` directories (submodules) and `.env` files of subprojects are
+/// protected like the ones at the root.
+pub const DEFAULT_PROTECTED_PATHS: &[&str] = &[
+    ".git",
+    ".git/**",
+    ".env",
+    "**/.git",
+    "**/.git/**",
+    "**/.env",
+];
+
+/// Kind of access a file tool needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    /// Create, modify or delete; protected paths are refused
+    Write,
+}
+
+/// Root directory the file tools are confined to
+///
+/// Relative paths are resolved against the root. Existing parts of a path are
+/// canonicalized, so `..` components and symlinks pointing outside the root are
+/// caught before the tool touches the file system.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: PathBuf,
+    protected_patterns: Vec<String>,
+    protected: GlobSet,
+}
+
+impl Workspace {
+    /// Create a workspace protecting [`DEFAULT_PROTECTED_PATHS`]
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Workspace directory; it must exist
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self, FileToolError> {
+        let root = root.as_ref().canonicalize()?;
+        let mut workspace = Self {
+            root,
+            protected_patterns: Vec::new(),
+            protected: GlobSet::empty(),
+        };
+        workspace.add_protected(DEFAULT_PROTECTED_PATHS.iter().map(|p| p.to_string()))?;
+        Ok(workspace)
+    }
+
+    /// Protect additional glob patterns, relative to the root (e.g. `secrets/**`)
+    pub fn with_protected<I>(mut self, patterns: I) -> Result<Self, FileToolError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.add_protected(patterns)?;
+        Ok(self)
+    }
+
+    fn add_protected<I>(&mut self, patterns: I) -> Result<(), FileToolError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        for pattern in patterns {
+            if !self.protected_patterns.contains(&pattern) {
+                self.protected_patterns.push(pattern);
+            }
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.protected_patterns {
+            let glob = Glob::new(pattern).map_err(|e| {
+                FileToolError::InvalidInput(format!("Invalid protected path '{}': {}", pattern, e))
+            })?;
+            builder.add(glob);
+        }
+        self.protected = builder
+            .build()
+            .map_err(|e| FileToolError::InvalidInput(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn protected_patterns(&self) -> &[String] {
+        &self.protected_patterns
+    }
+
+    /// Resolve a path given to a file tool
+    ///
+    /// # Returns
+    ///
+    /// The absolute, canonical path inside the workspace
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FileToolError::OutsideWorkspace`] if the path leaves the root, or
+    /// is protected and `access` is [`Access::Write`]
+    pub fn resolve(&self, path: &str, access: Access) -> Result<PathBuf, FileToolError> {
+        let outside = |reason: &str| FileToolError::OutsideWorkspace {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let joined = self.root.join(path);
+        let normalized = Self::normalize(&joined).ok_or_else(|| outside("escapes the root"))?;
+        let resolved = Self::canonicalize_existing(&normalized).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => outside("contains a dangling symlink"),
+            _ => FileToolError::Io(e),
+        })?;
+
+        let Ok(relative) = resolved.strip_prefix(&self.root) else {
+            let reason = if normalized.starts_with(&self.root) {
+                "a symlink points outside the workspace"
+            } else {
+                "not under the workspace root"
+            };
+            return Err(outside(&format!("{} ({})", reason, self.root.display())));
+        };
+
+        if access == Access::Write && self.protected.is_match(relative) {
+            return Err(outside("the path is protected"));
+        }
+
+        Ok(resolved)
+    }
+
+    /// Remove `.` and `..` components without touching the file system
+    fn normalize(path: &Path) -> Option<PathBuf> {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return None;
+                    }
+                }
+                Component::CurDir => {}
+                other => normalized.push(other),
+            }
+        }
+        Some(normalized)
+    }
+
+    /// Canonicalize the longest existing ancestor and append the rest
+    ///
+    /// The missing tail cannot contain symlinks, so the result is where a new
+    /// file or directory would actually be created.
+    fn canonicalize_existing(path: &Path) -> std::io::Result<PathBuf> {
+        let mut existing = path;
+        let mut tail = Vec::new();
+        // `symlink_metadata` also sees dangling symlinks, which must not be skipped
+        while existing.symlink_metadata().is_err() {
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    tail.push(name.to_os_string());
+                    existing = parent;
+                }
+                _ => break,
+            }
+        }
+
+        let mut resolved = existing.canonicalize()?;
+        for name in tail.iter().rev() {
+            resolved.push(name);
+        }
+        Ok(resolved)
+    }
+}
+
+/// Resolve a tool argument in place when the tool has a workspace
+///
+/// Without a workspace the path is left unchanged.
+pub fn confine(
+    workspace: Option<&Workspace>,
+    path: &mut String,
+    access: Access,
+) -> Result<(), FileToolError> {
+    if let Some(workspace) = workspace {
+        *path = workspace
+            .resolve(path, access)?
+            .to_string_lossy()
+            .to_string();
+    }
+    Ok(())
+}
@@ -1,4 +1,7 @@
+use super::diff::{self, print_colored};
+use super::workspace::{confine, Access, Workspace};
 use super::FileToolError;
+use crate::kota_code::checkpoint::{snapshot_before_write, CheckpointManager};
 use colored::*;
 use rig::{completion::ToolDefinition, tool::Tool};
 use serde::{Deserialize, Serialize};
@@ -15,8 +18,16 @@ pub struct WriteFileArgs {
 pub struct WriteFileOutput {
     pub file_path: String,
     pub bytes_written: u64,
+    /// The file did not exist before
+    pub created: bool,
+    /// Lines changed, `None` when the previous content could not be compared
+    pub lines_added: Option<usize>,
+    pub lines_removed: Option<usize>,
     pub success: bool,
     pub message: String,
+    /// Unified diff against the previous content, empty for a new file
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub diff: String,
 }
 
 #[derive(Deserialize, Serialize, Default)]
@@ -55,6 +66,18 @@ impl Tool for WriteFileTool {
         let content = &args.content;
         let path = Path::new(file_path);
 
+        // Keep the previous content to report what changed; an unreadable or
+        // binary file is overwritten without a diff
+        let existed = path.is_file();
+        let before = if existed {
+            fs::read(path)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .filter(|text| !text.contains('\0'))
+        } else {
+            Some(String::new())
+        };
+
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
             if !parent.exists() {
@@ -66,14 +89,29 @@ impl Tool for WriteFileTool {
         match fs::write(file_path, content) {
             Ok(()) => {
                 let bytes_written = content.len() as u64;
+                let changes = before.as_deref().map(|before| diff::diff(before, content));
+                let mut message = format!(
+                    "Successfully wrote {} bytes to '{}'",
+                    bytes_written, file_path
+                );
+                if let Some(changes) = &changes {
+                    message.push_str(&format!(
+                        ": +{} lines, -{} lines",
+                        changes.lines_added, changes.lines_removed
+                    ));
+                }
                 Ok(WriteFileOutput {
                     file_path: file_path.clone(),
                     bytes_written,
+                    created: !existed,
+                    lines_added: changes.as_ref().map(|changes| changes.lines_added),
+                    lines_removed: changes.as_ref().map(|changes| changes.lines_removed),
                     success: true,
-                    message: format!(
-                        "Successfully wrote {} bytes to '{}'",
-                        bytes_written, file_path
-                    ),
+                    message,
+                    diff: match changes {
+                        Some(changes) if existed => changes.rendered,
+                        _ => String::new(),
+                    },
                 })
             }
             Err(e) => match e.kind() {
@@ -88,14 +126,32 @@ impl Tool for WriteFileTool {
 #[derive(Deserialize, Serialize, Default)]
 pub struct WrappedWriteFileTool {
     inner: WriteFileTool,
+    #[serde(skip)]
+    workspace: Option<Workspace>,
+    #[serde(skip)]
+    checkpoints: Option<CheckpointManager>,
 }
 
 impl WrappedWriteFileTool {
     pub fn new() -> Self {
         Self {
             inner: WriteFileTool,
+            workspace: None,
+            checkpoints: None,
         }
     }
+
+    /// Confine `file_path` to a workspace root
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Snapshot the file before changing it, so the change can be undone
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointManager) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
 }
 
 impl Tool for WrappedWriteFileTool {
@@ -109,21 +165,38 @@ impl Tool for WrappedWriteFileTool {
         self.inner.definition(prompt).await
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
         println!("\n{} Write({})", "●".bright_green(), args.file_path);
 
         // Store line count before moving args
         let line_count = args.content.lines().count();
 
-        let result = self.inner.call(args).await;
+        let result = match confine(self.workspace.as_ref(), &mut args.file_path, Access::Write)
+            .and_then(|()| snapshot_before_write(self.checkpoints.as_ref(), &args.file_path))
+        {
+            Ok(()) => self.inner.call(args).await,
+            Err(e) => Err(e),
+        };
 
         match &result {
             Ok(output) => {
-                println!(
-                    "  └─ {} bytes written, {} lines",
-                    output.bytes_written.to_string().dimmed(),
-                    line_count.to_string().dimmed()
-                );
+                if let (false, Some(added), Some(removed)) =
+                    (output.created, output.lines_added, output.lines_removed)
+                {
+                    println!(
+                        "  └─ {} bytes written (+{} lines, -{} lines)",
+                        output.bytes_written.to_string().dimmed(),
+                        added.to_string().green(),
+                        removed.to_string().red()
+                    );
+                    print_colored(&output.diff);
+                } else {
+                    println!(
+                        "  └─ {} bytes written, {} lines",
+                        output.bytes_written.to_string().dimmed(),
+                        line_count.to_string().dimmed()
+                    );
+                }
             }
             Err(e) => {
                 println!("  └─ {}", format!("Error: {}", e).red());
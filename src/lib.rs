@@ -12,9 +12,10 @@ pub mod kota_code;
 
 // Re-export commonly used types for convenience
 pub use kota_code::{
-    create_agent, AgentBuilder, AgentInstance, AgentType, CommandDef, ContextManager, KotaConfig,
-    McpClient, McpManager, Plan, PlanManager, Provider, SerializableMessage, SessionIdHook,
-    SessionMetadata, Skill, SkillManager, Task, TaskStatus, ToolRegistry,
+    create_agent, AgentBuilder, AgentInstance, AgentType, CheckpointManager, CommandDef,
+    ConfigSource, ContextManager, KotaConfig, McpClient, McpManager, McpTool, Plan, PlanManager,
+    Provider, SerializableMessage, SessionIdHook, SessionMetadata, Skill, SkillManager, Task,
+    TaskStatus, ToolFilter, ToolHooks, ToolRegistry,
 };
 
 // Re-export CLI components for testing
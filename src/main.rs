@@ -1,9 +1,16 @@
 use anyhow::{Ok, Result};
 use colored::Colorize;
-use kota::{CommandRegistry, ContextManager, KotaConfig, SkillManager};
+use kota::{CommandRegistry, ContextManager, KotaConfig, McpManager, SkillManager};
 use names::Generator;
 
-use kota::kota_cli::KotaCli;
+use kota::kota_cli::{
+    config_check::check_config, oneshot::redirect_stdout_to_stderr, CliArgs, CliCommand, KotaCli,
+};
+use std::io::{IsTerminal, Read, Write};
+use std::time::Duration;
+
+/// How long an MCP server may take to start before it is skipped
+const MCP_STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
 
 fn load_session_id() -> String {
     let mut generator = Generator::default();
@@ -14,7 +21,7 @@ fn load_session_id() -> String {
 
 fn load_command_registry(config: &KotaConfig) -> Option<CommandRegistry> {
     if !config.commands.is_empty() {
-        match CommandRegistry::new(&config) {
+        match CommandRegistry::new(config) {
             std::result::Result::Ok(registry) => {
                 println!(
                     "{} {} custom commands loaded",
@@ -37,39 +44,208 @@ fn load_command_registry(config: &KotaConfig) -> Option<CommandRegistry> {
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Load configuration from .kota/config.lua
-    let config = KotaConfig::load()?;
+async fn load_mcp_manager(config: &KotaConfig) -> McpManager {
+    let manager = McpManager::new();
+
+    let mut server_names: Vec<&String> = config.mcp_servers.keys().collect();
+    server_names.sort();
+
+    for name in server_names {
+        let server = &config.mcp_servers[name];
+        let started = tokio::time::timeout(
+            MCP_STARTUP_TIMEOUT,
+            manager.add_server_from_config(name, server),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(anyhow::anyhow!(
+                "no response within {}s",
+                MCP_STARTUP_TIMEOUT.as_secs()
+            ))
+        });
+        match started {
+            std::result::Result::Ok(()) => {
+                let tool_count = manager.list_tools(name).await.map(|t| t.len()).unwrap_or(0);
+                println!(
+                    "{} MCP server {} connected ({} tools)",
+                    "🔌".bright_cyan(),
+                    name.bright_yellow(),
+                    tool_count.to_string().bright_yellow()
+                );
+            }
+            Err(e) => {
+                println!(
+                    "{} Failed to start MCP server '{}': {:#}",
+                    "⚠️".yellow(),
+                    name,
+                    e
+                );
+            }
+        }
+    }
+
+    manager
+}
+
+/// Switch to the session requested with --session or --resume and load it
+fn load_session(context: &mut ContextManager, args: &CliArgs) -> Result<()> {
+    if args.resume {
+        match context.list_sessions()?.first() {
+            Some(latest) => context.switch_session(latest.session_id.clone()),
+            None => println!(
+                "{} No previous session found, starting a new one",
+                "💡".bright_blue()
+            ),
+        }
+    } else if let Some(session_id) = &args.session {
+        ContextManager::validate_session_id(session_id)?;
+        context.switch_session(session_id.clone());
+    }
 
     println!(
         "{} {}",
-        "📝 Model:".bright_cyan(),
-        config.model.bright_yellow()
+        "🎯 Session ID:".bright_cyan(),
+        context.session_id().bright_yellow()
     );
 
-    let session_id = load_session_id();
+    if (args.resume || args.session.is_some()) && context.load()? {
+        println!(
+            "{} Resumed {} messages",
+            "📂".bright_cyan(),
+            context.get_messages().len().to_string().bright_yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Resolve the one-shot prompt from `-p` or a piped stdin
+fn load_prompt(args: &CliArgs) -> Result<Option<String>> {
+    let read_stdin = match args.prompt.as_deref() {
+        Some("-") => true,
+        Some(prompt) => return Ok(Some(prompt.to_string())),
+        None => !std::io::stdin().is_terminal(),
+    };
+    if !read_stdin {
+        return Ok(None);
+    }
+
+    let mut prompt = String::new();
+    std::io::stdin().read_to_string(&mut prompt)?;
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Err(anyhow::anyhow!("No prompt provided on stdin"));
+    }
+    Ok(Some(prompt.to_string()))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = match CliArgs::parse() {
+        std::result::Result::Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, kota::kota_cli::args::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", kota::kota_cli::args::USAGE);
+        return Ok(());
+    }
+    if args.version {
+        println!("kota {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    // Resolve paths against the directory kota was started from, then switch
+    let config_path = args
+        .config
+        .as_deref()
+        .map(std::path::absolute)
+        .transpose()?;
+    let sessions_dir = std::path::absolute(
+        args.sessions_dir
+            .as_deref()
+            .unwrap_or(std::path::Path::new(".chat_sessions")),
+    )?;
+    if let Some(cwd) = &args.cwd {
+        std::env::set_current_dir(cwd).map_err(|e| {
+            anyhow::anyhow!("Failed to change directory to {}: {}", cwd.display(), e)
+        })?;
+    }
+
+    if args.command == Some(CliCommand::ConfigCheck) {
+        if !check_config(config_path.as_deref())? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // In one-shot mode only the final result goes to stdout
+    let prompt = load_prompt(&args)?;
+    let mut result_output: Box<dyn Write> = match prompt {
+        Some(_) => redirect_stdout_to_stderr()?,
+        None => Box::new(std::io::stdout()),
+    };
+
+    // Load defaults, global and project config (or --config), then apply CLI overrides
+    let mut config = KotaConfig::load_layered(config_path.as_deref())?;
+    args.apply_to(&mut config);
+    for warning in &config.warnings {
+        println!("{} {}", "⚠️ ".yellow(), warning);
+    }
+    if config.layers.is_empty() {
+        println!(
+            "{} No config file found, using built-in defaults. Create .kota/config.lua or ~/.config/kota/config.lua",
+            "⚠️ ".yellow()
+        );
+    }
 
     println!(
         "{} {}",
-        "🎯 Session ID:".bright_cyan(),
-        session_id.bright_yellow()
+        "📝 Model:".bright_cyan(),
+        config.model.bright_yellow()
     );
 
+    let mut context = ContextManager::new(&sessions_dir, load_session_id())?
+        .with_max_messages(config.context_max_messages);
+    if let Some(token_budget) = config.context_token_budget {
+        context = context.with_token_budget(token_budget);
+    }
+    load_session(&mut context, &args)?;
+
     // Initialize command registry if commands are defined
     let command_registry = load_command_registry(&config);
-
-    let context = ContextManager::new("./.chat_sessions", session_id)?.with_max_messages(100);
     let skill_manager = SkillManager::new();
+
+    // Start MCP servers declared in config
+    let mcp_manager = load_mcp_manager(&config).await;
+
+    // `/reload` re-applies the command line on top of the reloaded config
+    let mut cli_args = args.clone();
+    cli_args.config = config_path;
+
     let mut cli = KotaCli::new(
-        config.api_key,
-        config.api_base,
-        config.model,
+        &config,
         context,
         skill_manager,
         command_registry,
-    )?;
-    cli.run().await?;
+        mcp_manager,
+    )
+    .await?
+    .with_cli_args(cli_args);
+
+    match prompt {
+        Some(prompt) => {
+            let success = cli
+                .run_once(&prompt, args.output, result_output.as_mut())
+                .await?;
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        None => cli.run().await?,
+    }
 
     Ok(())
 }
@@ -1,4 +1,6 @@
-use kota::KotaConfig;
+use kota::kota_code::runtime::Severity;
+use kota::kota_code::tools::execute_bash_command::DEFAULT_ENV_DENY;
+use kota::{ConfigSource, KotaConfig, Provider};
 use std::fs;
 use tempfile::TempDir;
 
@@ -71,14 +73,459 @@ fn test_missing_config_file() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(temp_dir.path()).unwrap();
 
+    // Without a project config the built-in defaults (and any global config) apply
     let result = KotaConfig::load();
 
-    assert!(result.is_err());
-    assert!(result
-        .unwrap_err()
-        .to_string()
-        .contains("Configuration file not found"));
+    assert!(result.is_ok());
+    assert!(!result
+        .unwrap()
+        .layers
+        .iter()
+        .any(|layer| matches!(layer, ConfigSource::Project(_))));
 
     // Restore original directory
     std::env::set_current_dir(original_dir).unwrap();
 }
+
+#[test]
+fn test_lua_config_hooks() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    let config_content = r#"
+kota.setup({
+  model = "gpt-4o",
+  hooks = {
+    before_execute = function(tool, args)
+      return tool ~= "delete_file"
+    end,
+  },
+})
+"#;
+
+    fs::write(&config_path, config_content).unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+
+    assert!(config.hooks.before_execute.is_some());
+    assert!(config.hooks.after_execute.is_none());
+}
+
+#[test]
+fn test_lua_config_mcp_servers() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    let config_content = r#"
+kota.setup({
+  model = "gpt-4o",
+  mcp_servers = {
+    git = {
+      command = "uvx",
+      args = { "mcp-server-git", "--repository", "." },
+      env = { GIT_PAGER = "cat" },
+      cwd = "/tmp",
+    },
+    broken = { args = { "missing-command" } },
+  },
+})
+"#;
+
+    fs::write(&config_path, config_content).unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+
+    assert_eq!(config.mcp_servers.len(), 1);
+    assert_eq!(config.warnings.len(), 1);
+    assert_eq!(config.warnings[0].path, "mcp_servers.broken");
+    let git = &config.mcp_servers["git"];
+    assert_eq!(git.command, "uvx");
+    assert_eq!(git.args, vec!["mcp-server-git", "--repository", "."]);
+    assert_eq!(git.env.get("GIT_PAGER"), Some(&"cat".to_string()));
+    assert_eq!(git.cwd.as_deref(), Some("/tmp"));
+}
+
+#[test]
+fn test_lua_config_api_base_and_max_tokens() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"
+kota.setup({
+  model = "gpt-4o",
+  api_base = "http://localhost:8080/v1",
+  max_tokens = 8192,
+})
+"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.api_base.as_deref(), Some("http://localhost:8080/v1"));
+    assert_eq!(config.max_tokens, Some(8192));
+
+    // Temperature is only sent when configured; reasoning models reject it
+    assert_eq!(config.temperature, None);
+
+    let defaults = KotaConfig::default();
+    assert_eq!(defaults.api_base, None);
+    assert_eq!(defaults.temperature, None);
+    assert_eq!(defaults.max_tokens, None);
+}
+
+#[test]
+fn test_lua_config_provider() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"
+kota.setup({
+  provider = "openai_compatible",
+  model = "qwen2.5-coder-32b-instruct",
+})
+"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.provider, Some(Provider::OpenAICompatible));
+    assert_eq!(config.model, "qwen2.5-coder-32b-instruct");
+    assert_eq!(KotaConfig::default().provider, None);
+
+    fs::write(
+        &config_path,
+        r#"kota.setup({ provider = "not-a-provider" })"#,
+    )
+    .unwrap();
+    let err = KotaConfig::from_lua_file(&config_path).unwrap_err();
+    assert!(err.to_string().contains("Unknown provider"));
+}
+
+#[test]
+fn test_lua_config_context() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"
+kota.setup({
+  context = {
+    max_messages = 40,
+    token_budget = 64000,
+  },
+})
+"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.context_max_messages, 40);
+    assert_eq!(config.context_token_budget, Some(64000));
+
+    let defaults = KotaConfig::default();
+    assert_eq!(defaults.context_max_messages, 100);
+    assert_eq!(defaults.context_token_budget, None);
+}
+
+#[test]
+fn test_lua_config_tools_false() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(&config_path, r#"kota.setup({ tools = false })"#).unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert!(config.disable_all_tools);
+    assert!(!KotaConfig::default().disable_all_tools);
+}
+
+#[test]
+fn test_lua_config_workspace() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"
+kota.setup({
+  workspace = {
+    root = "/srv/project",
+    protected = { "secrets/**", "*.pem" },
+  },
+})
+"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.workspace_root.as_deref(), Some("/srv/project"));
+    assert_eq!(config.protected_paths, vec!["secrets/**", "*.pem"]);
+
+    let defaults = KotaConfig::default();
+    assert_eq!(defaults.workspace_root, None);
+    assert!(defaults.protected_paths.is_empty());
+}
+
+#[test]
+fn test_lua_config_exec() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"
+kota.setup({
+  exec = {
+    timeout_secs = 600,
+    max_output_bytes = 5000,
+    env = { allow = { "PATH", "CARGO_*" }, deny = { "*_API_KEY", "SSH_AUTH_SOCK" } },
+  },
+  edit = { fuzz = 0 },
+  read = { max_bytes = 1000 },
+})
+"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.exec.timeout_secs, 600);
+    assert_eq!(config.edit_fuzz, 0);
+    assert_eq!(config.read_max_bytes, 1000);
+    assert_eq!(config.exec.max_output_bytes, 5000);
+    assert_eq!(config.exec.env_allow, vec!["PATH", "CARGO_*"]);
+    // Configured deny patterns add to the defaults
+    let mut env_deny: Vec<&str> = DEFAULT_ENV_DENY.to_vec();
+    env_deny.push("SSH_AUTH_SOCK");
+    assert_eq!(config.exec.env_deny, env_deny);
+
+    let defaults = KotaConfig::default();
+    assert_eq!(defaults.exec.timeout_secs, 120);
+    assert_eq!(defaults.exec.max_output_bytes, 30_000);
+    assert_eq!(defaults.edit_fuzz, 2);
+    assert_eq!(defaults.read_max_bytes, 50_000);
+    assert!(defaults.exec.env_allow.is_empty());
+    assert_eq!(defaults.exec.env_deny, DEFAULT_ENV_DENY);
+}
+
+#[test]
+fn test_lua_config_layers_merge() {
+    let temp_dir = TempDir::new().unwrap();
+    let global_path = temp_dir.path().join("global.lua");
+    let project_path = temp_dir.path().join("project.lua");
+
+    fs::write(
+        &global_path,
+        r#"
+kota.setup({
+  model = "gpt-4o",
+  api_key = "global-key",
+  temperature = 0.2,
+  tools = { disabled = { "delete_file" } },
+  commands = { hello = "Say hello" },
+})
+"#,
+    )
+    .unwrap();
+    fs::write(
+        &project_path,
+        r#"
+kota.setup({
+  model = "deepseek-chat",
+  tools = { disabled = { "execute_bash", "delete_file" } },
+  commands = { review = "Review the code" },
+})
+"#,
+    )
+    .unwrap();
+
+    let mut config = KotaConfig::default();
+    config
+        .apply_lua_file(&global_path, ConfigSource::Global(global_path.clone()))
+        .unwrap();
+    config
+        .apply_lua_file(&project_path, ConfigSource::Project(project_path.clone()))
+        .unwrap();
+
+    assert_eq!(config.model, "deepseek-chat");
+    assert_eq!(config.api_key, "global-key");
+    assert_eq!(config.temperature, Some(0.2));
+    assert_eq!(config.disabled_tools, vec!["execute_bash", "delete_file"]);
+    assert!(config.commands.contains_key("hello"));
+    assert!(config.commands.contains_key("review"));
+
+    assert_eq!(
+        config.source_of("model"),
+        &ConfigSource::Project(project_path.clone())
+    );
+    assert_eq!(
+        config.source_of("api_key"),
+        &ConfigSource::Global(global_path.clone())
+    );
+    assert_eq!(
+        config.source_of("commands.hello"),
+        &ConfigSource::Global(global_path.clone())
+    );
+    assert_eq!(config.source_of("max_tokens"), &ConfigSource::Default);
+    assert_eq!(
+        config.layers,
+        vec![
+            ConfigSource::Global(global_path),
+            ConfigSource::Project(project_path)
+        ]
+    );
+}
+
+#[test]
+fn test_lua_config_layers_replace_tool_lists() {
+    let temp_dir = TempDir::new().unwrap();
+    let global_path = temp_dir.path().join("global.lua");
+    let project_path = temp_dir.path().join("project.lua");
+
+    fs::write(
+        &global_path,
+        r#"
+kota.setup({
+  tools = { enabled = { "read_file" }, disabled = { "exec_cmd" } },
+  workspace = { protected = { "secrets/**" } },
+  exec = { env = { deny = { "AWS_*" } } },
+})
+"#,
+    )
+    .unwrap();
+    fs::write(
+        &project_path,
+        r#"
+kota.setup({
+  tools = { disabled = {} },
+  workspace = { protected = {} },
+  exec = { env = { deny = {} } },
+})
+"#,
+    )
+    .unwrap();
+
+    let mut config = KotaConfig::default();
+    config
+        .apply_lua_file(&global_path, ConfigSource::Global(global_path.clone()))
+        .unwrap();
+    config
+        .apply_lua_file(&project_path, ConfigSource::Project(project_path.clone()))
+        .unwrap();
+
+    // The project re-enables exec_cmd and keeps the global enabled list
+    assert!(config.disabled_tools.is_empty());
+    assert_eq!(config.enabled_tools, vec!["read_file"]);
+    assert_eq!(
+        config.source_of("tools.disabled"),
+        &ConfigSource::Project(project_path)
+    );
+    assert_eq!(
+        config.source_of("tools.enabled"),
+        &ConfigSource::Global(global_path)
+    );
+
+    // Restrictions still accumulate
+    assert_eq!(config.protected_paths, vec!["secrets/**"]);
+    assert!(config.exec.env_deny.contains(&"AWS_*".to_string()));
+}
+
+#[test]
+fn test_find_project_config_walks_up() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = temp_dir.path().join(".kota");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("config.lua"), "kota.setup({})").unwrap();
+
+    let nested = temp_dir.path().join("src").join("deep");
+    fs::create_dir_all(&nested).unwrap();
+
+    assert_eq!(
+        KotaConfig::find_project_config(&nested),
+        Some(config_dir.join("config.lua"))
+    );
+}
+
+#[test]
+fn test_load_layered_missing_explicit_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let result = KotaConfig::load_layered(Some(&temp_dir.path().join("missing.lua")));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_check_lua_file_reports_paths_and_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"kota.setup({
+  model = "gpt-4o",
+  temperature = "high",
+  tools = {
+    enabled = "read_file",
+  },
+  commands = {
+    bad = 42,
+  },
+  mcp_servers = {
+    git = { args = { "mcp-server-git" } },
+  },
+  tempurature = 0.5,
+})
+"#,
+    )
+    .unwrap();
+
+    let diagnostics = KotaConfig::check_lua_file(&config_path);
+    let summary: Vec<(Severity, &str, Option<usize>)> = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.path.as_str(), d.line))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Severity::Error, "temperature", Some(3)),
+            (Severity::Error, "tools.enabled", Some(5)),
+            (Severity::Error, "commands.bad", Some(8)),
+            (Severity::Warning, "mcp_servers.git", Some(11)),
+            (Severity::Warning, "tempurature", Some(13)),
+        ]
+    );
+    assert!(diagnostics[4]
+        .message
+        .contains("did you mean `temperature`"));
+    assert!(diagnostics[0]
+        .to_string()
+        .starts_with(&format!("{}:3: error: temperature:", config_path.display())));
+
+    // Errors reject the file when loading
+    let err = KotaConfig::from_lua_file(&config_path).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("tools.enabled: expected list of strings"));
+}
+
+#[test]
+fn test_check_lua_file_valid_with_warning() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(
+        &config_path,
+        r#"kota.setup({ model = "gpt-4o", colour = "blue" })"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.warnings.len(), 1);
+    assert_eq!(config.warnings[0].path, "colour");
+    assert_eq!(config.warnings[0].severity, Severity::Warning);
+}
+
+#[test]
+fn test_check_lua_file_syntax_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(&config_path, "kota.setup({ model = ").unwrap();
+
+    let diagnostics = KotaConfig::check_lua_file(&config_path);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].is_error());
+    assert!(diagnostics[0].message.contains(":1:"));
+}
@@ -0,0 +1,50 @@
+use kota::{AgentBuilder, Provider};
+
+#[test]
+fn test_provider_from_str() {
+    assert_eq!("openai".parse::<Provider>().unwrap(), Provider::OpenAI);
+    assert_eq!(
+        "Anthropic".parse::<Provider>().unwrap(),
+        Provider::Anthropic
+    );
+    assert_eq!(
+        "openai-compatible".parse::<Provider>().unwrap(),
+        Provider::OpenAICompatible
+    );
+    assert_eq!(Provider::OpenAICompatible.to_string(), "openai_compatible");
+    assert!("bedrock".parse::<Provider>().is_err());
+}
+
+#[tokio::test]
+async fn test_explicit_provider_overrides_model_sniffing() {
+    // A model name without a known prefix needs an explicit provider
+    let result = AgentBuilder::new("key".to_string(), "qwen2.5-coder:14b".to_string())
+        .unwrap()
+        .build();
+    assert!(result.is_err());
+
+    let instance = AgentBuilder::new("key".to_string(), "qwen2.5-coder:14b".to_string())
+        .unwrap()
+        .with_provider(Provider::Ollama)
+        .build()
+        .unwrap();
+    assert_eq!(instance.agent.provider(), Provider::Ollama);
+
+    // Explicit provider wins even when the model name looks like another provider's
+    let instance = AgentBuilder::new("key".to_string(), "gpt-4o".to_string())
+        .unwrap()
+        .with_provider(Provider::OpenAICompatible)
+        .with_api_base("http://localhost:8000/v1")
+        .build()
+        .unwrap();
+    assert_eq!(instance.agent.provider(), Provider::OpenAICompatible);
+}
+
+#[tokio::test]
+async fn test_provider_sniffed_from_model_name() {
+    let instance = AgentBuilder::new("key".to_string(), "deepseek-reasoner".to_string())
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(instance.agent.provider(), Provider::DeepSeek);
+}
@@ -0,0 +1,422 @@
+use kota::kota_code::runtime::dyn_tools_loader::dyn_tool::LuaDynTool;
+use kota::kota_code::runtime::{
+    ApprovalAnswer, ApprovalConfig, ApprovalError, ApprovalGate, ApprovalMode, ApprovalTool,
+    Approver, Decision, ToolKind,
+};
+use kota::kota_code::tools::Workspace;
+use kota::KotaConfig;
+use rig::tool::ToolDyn;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+/// Approver that replays scripted answers and records what it was asked
+struct ScriptedApprover {
+    answers: Mutex<Vec<Option<ApprovalAnswer>>>,
+    asked: Mutex<Vec<(String, String)>>,
+}
+
+impl ScriptedApprover {
+    fn new(answers: Vec<Option<ApprovalAnswer>>) -> Arc<Self> {
+        Arc::new(Self {
+            answers: Mutex::new(answers),
+            asked: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl Approver for ScriptedApprover {
+    fn ask(&self, tool: &str, target: &str) -> Option<ApprovalAnswer> {
+        self.asked
+            .lock()
+            .unwrap()
+            .push((tool.to_string(), target.to_string()));
+        self.answers.lock().unwrap().remove(0)
+    }
+}
+
+fn write_tool() -> Box<dyn ToolDyn> {
+    let lua = mlua::Lua::new();
+    let func: mlua::Function = lua
+        .load("function(args) return 'wrote ' .. args.file_path end")
+        .eval()
+        .unwrap();
+    Box::new(LuaDynTool::new(
+        "write_file".to_string(),
+        "Write a file".to_string(),
+        json!({ "type": "object" }),
+        func.dump(false),
+    ))
+}
+
+#[test]
+fn test_approval_modes() {
+    let mut config = ApprovalConfig::default();
+    assert_eq!(config.decide("write_file", "a.rs"), Decision::Ask);
+
+    config.mode = ApprovalMode::AutoEdit;
+    assert_eq!(config.decide("write_file", "a.rs"), Decision::Allow);
+    assert_eq!(config.decide("edit_file", "a.rs"), Decision::Allow);
+    assert_eq!(config.decide("delete_file", "a.rs"), Decision::Ask);
+    assert_eq!(config.decide("exec_cmd", "ls"), Decision::Ask);
+
+    config.mode = ApprovalMode::Auto;
+    assert_eq!(config.decide("exec_cmd", "ls"), Decision::Allow);
+
+    config.mode = ApprovalMode::ReadOnly;
+    assert_eq!(config.decide("write_file", "a.rs"), Decision::Deny);
+
+    assert_eq!(
+        "auto_edit".parse::<ApprovalMode>().unwrap(),
+        ApprovalMode::AutoEdit
+    );
+    assert!("never".parse::<ApprovalMode>().is_err());
+}
+
+#[test]
+fn test_approval_tool_and_pattern_rules() {
+    let config = ApprovalConfig {
+        mode: ApprovalMode::Ask,
+        tools: [("delete_file".to_string(), ApprovalMode::ReadOnly)]
+            .into_iter()
+            .collect(),
+        paths: vec![
+            ("docs/**".to_string(), ApprovalMode::Auto),
+            ("docs/secret/*".to_string(), ApprovalMode::ReadOnly),
+        ],
+        commands: vec![("cargo *".to_string(), ApprovalMode::Auto)],
+    };
+
+    assert!(config.is_gated("exec_cmd"));
+    assert!(!config.is_gated("read_file"));
+
+    // The most specific matching pattern wins, then the tool, then the mode
+    assert_eq!(config.decide("write_file", "docs/a/b.md"), Decision::Allow);
+    assert_eq!(config.decide("write_file", "./docs/a.md"), Decision::Allow);
+    assert_eq!(
+        config.decide("edit_file", "docs/secret/key.md"),
+        Decision::Deny
+    );
+    assert_eq!(config.decide("write_file", "src/main.rs"), Decision::Ask);
+    // `..` is resolved before matching, so it cannot climb out of an allowed directory
+    assert_eq!(
+        config.decide("write_file", "docs/../src/main.rs"),
+        Decision::Ask
+    );
+    assert_eq!(
+        config.decide("delete_file", "docs/../src/main.rs"),
+        Decision::Deny
+    );
+    assert_eq!(config.decide("write_file", "../docs/a.md"), Decision::Ask);
+    assert_eq!(config.decide("delete_file", "src/main.rs"), Decision::Deny);
+    assert_eq!(config.decide("delete_file", "docs/old.md"), Decision::Allow);
+    assert_eq!(
+        config.decide("exec_cmd", "cargo test --all"),
+        Decision::Allow
+    );
+    assert_eq!(config.decide("exec_cmd", "rm -rf target"), Decision::Ask);
+
+    // The persistent shell keeps aliases between calls, so command patterns never allow it
+    assert!(config.is_gated("shell_session"));
+    assert_eq!(config.decide("shell_session", "cargo build"), Decision::Ask);
+    assert_eq!(config.decide("shell_session", "make"), Decision::Ask);
+    let read_only = ApprovalConfig {
+        commands: vec![("rm *".to_string(), ApprovalMode::ReadOnly)],
+        mode: ApprovalMode::Auto,
+        ..Default::default()
+    };
+    assert_eq!(
+        read_only.decide("shell_session", "rm -rf /"),
+        Decision::Deny
+    );
+    assert_eq!(read_only.decide("shell_session", "ls"), Decision::Allow);
+
+    // Only reading output, status and kill of `process` skip approval
+    assert!(ApprovalConfig::is_exempt(
+        "process",
+        r#"{"action": "read_output", "id": "p1"}"#
+    ));
+    assert!(!ApprovalConfig::is_exempt(
+        "process",
+        r#"{"action": "start", "command": "cargo run"}"#
+    ));
+    // Input sent to a running process is approved like a command
+    let send_input = r#"{"action": "send_input", "id": "p1", "input": "rm -rf ~\n"}"#;
+    assert!(!ApprovalConfig::is_exempt("process", send_input));
+    assert_eq!(ApprovalConfig::target("process", send_input), "rm -rf ~\n");
+    assert_eq!(
+        ApprovalConfig::target(
+            "process",
+            r#"{"action": "start", "command": "npm start", "cwd": "web"}"#
+        ),
+        "cd web && npm start"
+    );
+    assert_eq!(config.decide("process", "cargo run"), Decision::Allow);
+}
+
+#[tokio::test]
+async fn test_approval_gate_answers() {
+    let approver = ScriptedApprover::new(vec![
+        Some(ApprovalAnswer::AllowOnce),
+        Some(ApprovalAnswer::Deny(Some("not that file".to_string()))),
+        Some(ApprovalAnswer::AllowSession),
+        None,
+    ]);
+    let gate = ApprovalGate::new(ApprovalConfig::default(), approver.clone());
+    let args = r#"{"file_path":"a.txt","content":"x"}"#;
+
+    assert!(gate.check("write_file", args).await.is_ok());
+    match gate.check("write_file", args).await {
+        Err(ApprovalError::DeniedByUser {
+            tool,
+            target,
+            reason,
+        }) => {
+            assert_eq!(tool, "write_file");
+            assert_eq!(target, "a.txt");
+            assert_eq!(reason.as_deref(), Some("not that file"));
+        }
+        other => panic!("Expected a denial, got {:?}", other),
+    }
+
+    // Allowed for the session: later calls are not asked again, also after a policy change
+    assert!(gate.check("write_file", args).await.is_ok());
+    let gate = gate.with_config(ApprovalConfig::default());
+    assert!(gate.check("write_file", args).await.is_ok());
+    assert_eq!(gate.session_allowed(), vec!["write_file(a.txt)"]);
+    assert_eq!(approver.asked.lock().unwrap().len(), 3);
+
+    // The allowance covers that file only; nobody is left to ask about another one
+    let err = gate
+        .check("write_file", r#"{"file_path":"b.txt","content":"x"}"#)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApprovalError::NotInteractive { .. }));
+    assert_eq!(
+        approver.asked.lock().unwrap()[3],
+        ("write_file".to_string(), "b.txt".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_approval_session_allowance_is_per_command() {
+    let approver = ScriptedApprover::new(vec![Some(ApprovalAnswer::AllowSession), None]);
+    let gate = ApprovalGate::new(ApprovalConfig::default(), approver.clone());
+
+    assert!(gate.check("exec_cmd", r#"{"command":"ls"}"#).await.is_ok());
+    assert!(gate.check("exec_cmd", r#"{"command":"ls"}"#).await.is_ok());
+    // "Always" on `ls` does not approve a different command
+    let err = gate
+        .check("exec_cmd", r#"{"command":"rm -rf ~"}"#)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApprovalError::NotInteractive { .. }));
+    assert_eq!(approver.asked.lock().unwrap().len(), 2);
+    assert_eq!(gate.session_allowed(), vec!["exec_cmd(ls)"]);
+}
+
+#[tokio::test]
+async fn test_approval_matches_paths_relative_to_the_workspace() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+    let workspace = Workspace::new(temp_dir.path()).unwrap();
+    let config = ApprovalConfig {
+        paths: vec![("docs/**".to_string(), ApprovalMode::Auto)],
+        ..Default::default()
+    };
+    let approver = ScriptedApprover::new(vec![None, None]);
+    let gate = ApprovalGate::new(config, approver.clone()).with_workspace(workspace.clone());
+
+    let write = |path: &str| json!({ "file_path": path, "content": "x" }).to_string();
+    assert!(gate.check("write_file", &write("docs/a.md")).await.is_ok());
+    let absolute = workspace.root().join("docs/b.md");
+    assert!(gate
+        .check("write_file", &write(&absolute.to_string_lossy()))
+        .await
+        .is_ok());
+    assert!(gate
+        .check("write_file", &write("docs/../src/main.rs"))
+        .await
+        .is_err());
+    assert!(gate
+        .check("write_file", &write("../docs/a.md"))
+        .await
+        .is_err());
+    assert_eq!(approver.asked.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_approval_checks_every_file_of_a_patch() {
+    let config = ApprovalConfig {
+        mode: ApprovalMode::AutoEdit,
+        paths: vec![("docs/**".to_string(), ApprovalMode::ReadOnly)],
+        ..Default::default()
+    };
+    let patch = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n\
+                 --- a/docs/guide.md\n+++ b/docs/guide.md\n@@ -1 +1 @@\n-a\n+b\n";
+    let args = json!({ "patch": patch }).to_string();
+
+    // Without file_path the headers name the files
+    assert_eq!(
+        ApprovalConfig::targets("edit_file", &args),
+        vec!["src/lib.rs", "docs/guide.md"]
+    );
+
+    let gate = ApprovalGate::new(config, ScriptedApprover::new(vec![]));
+    let err = gate.check("edit_file", &args).await.unwrap_err();
+    assert!(matches!(err, ApprovalError::ReadOnly { .. }));
+}
+
+#[tokio::test]
+async fn test_approval_treats_patch_deletions_as_deletes() {
+    let config = ApprovalConfig {
+        mode: ApprovalMode::AutoEdit,
+        ..Default::default()
+    };
+    let edit = json!({ "patch": "--- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-a\n+b\n" }).to_string();
+    let delete = json!({ "patch": "--- a/a.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-a\n" }).to_string();
+    assert_eq!(ApprovalConfig::kind("edit_file", &edit), ToolKind::Edit);
+    assert_eq!(ApprovalConfig::kind("edit_file", &delete), ToolKind::Delete);
+
+    // auto-edit runs edits but asks before deleting, whichever tool deletes
+    let approver = ScriptedApprover::new(vec![None]);
+    let gate = ApprovalGate::new(config, approver.clone());
+    assert!(gate.check("edit_file", &edit).await.is_ok());
+    let err = gate.check("edit_file", &delete).await.unwrap_err();
+    assert!(matches!(err, ApprovalError::NotInteractive { .. }));
+    assert_eq!(approver.asked.lock().unwrap()[0].1, "a.txt");
+}
+
+#[tokio::test]
+async fn test_approval_shows_and_matches_exec_cwd_and_env() {
+    let config = ApprovalConfig {
+        commands: vec![("make*".to_string(), ApprovalMode::Auto)],
+        ..Default::default()
+    };
+    let args = json!({
+        "command": "make",
+        "cwd": "/",
+        "env": { "LD_PRELOAD": "/tmp/evil lib.so" },
+    })
+    .to_string();
+    let target = ApprovalConfig::target("exec_cmd", &args);
+    assert_eq!(target, "cd / && LD_PRELOAD='/tmp/evil lib.so' make");
+
+    // The pattern allows plain `make`, not `make` elsewhere with extra variables
+    assert_eq!(config.decide("exec_cmd", "make"), Decision::Allow);
+    let approver = ScriptedApprover::new(vec![None, None]);
+    let gate = ApprovalGate::new(config, approver.clone());
+    assert!(gate
+        .check("exec_cmd", r#"{"command":"make"}"#)
+        .await
+        .is_ok());
+    assert!(gate.check("exec_cmd", &args).await.is_err());
+    assert_eq!(approver.asked.lock().unwrap()[0].1, target);
+
+    // Input to a running shell is asked about even when it matches a pattern
+    let send_input = r#"{"action": "send_input", "id": "p1", "input": "make"}"#;
+    assert!(gate.check("process", send_input).await.is_err());
+    assert_eq!(approver.asked.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_approval_calls_without_target_are_not_exempt() {
+    let read_only = ApprovalGate::new(
+        ApprovalConfig {
+            mode: ApprovalMode::ReadOnly,
+            ..Default::default()
+        },
+        ScriptedApprover::new(vec![]),
+    );
+    for (tool, args) in [
+        ("exec_cmd", r#"{"cmd": "rm -rf ~"}"#),
+        ("process", r#"{"action": "send_input", "id": "p1"}"#),
+        ("write_file", "not json"),
+    ] {
+        let err = read_only.check(tool, args).await.unwrap_err();
+        assert!(matches!(err, ApprovalError::ReadOnly { .. }), "{}", tool);
+    }
+    let status = r#"{"action": "status"}"#;
+    assert!(read_only.check("process", status).await.is_ok());
+
+    // Even in auto mode a call that names no target is asked about
+    let approver = ScriptedApprover::new(vec![None]);
+    let auto = ApprovalGate::new(
+        ApprovalConfig {
+            mode: ApprovalMode::Auto,
+            ..Default::default()
+        },
+        approver.clone(),
+    );
+    assert!(auto.check("exec_cmd", r#"{"command": "ls"}"#).await.is_ok());
+    assert!(auto.check("edit_file", r#"{"patch": ""}"#).await.is_err());
+    assert_eq!(approver.asked.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_approval_tool_denied_by_user() {
+    let approver = ScriptedApprover::new(vec![Some(ApprovalAnswer::Deny(None))]);
+    let gate = ApprovalGate::new(ApprovalConfig::default(), approver);
+    let tool = ApprovalTool::new(write_tool(), gate.clone());
+
+    let err = tool
+        .call(r#"{"file_path":"a.txt","content":"x"}"#.to_string())
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Tool call 'write_file' on 'a.txt' was denied by the user"));
+
+    let gate = gate.with_config(ApprovalConfig {
+        mode: ApprovalMode::Auto,
+        ..Default::default()
+    });
+    let tool = ApprovalTool::new(write_tool(), gate);
+    let output = tool
+        .call(r#"{"file_path":"a.txt","content":"x"}"#.to_string())
+        .await
+        .unwrap();
+    assert!(output.contains("wrote a.txt"));
+}
+
+#[test]
+fn test_approval_config_from_lua() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    std::fs::write(
+        &config_path,
+        r#"
+kota.setup({
+  model = "gpt-4o",
+  approval = {
+    mode = "auto-edit",
+    tools = { exec_cmd = "ask" },
+    paths = { ["docs/**"] = "auto" },
+    commands = { ["cargo test*"] = "auto" },
+  },
+})
+"#,
+    )
+    .unwrap();
+
+    let config = KotaConfig::from_lua_file(&config_path).unwrap();
+    assert_eq!(config.approval.mode, ApprovalMode::AutoEdit);
+    assert_eq!(config.approval.tools["exec_cmd"], ApprovalMode::Ask);
+    assert_eq!(
+        config.approval.paths,
+        vec![("docs/**".to_string(), ApprovalMode::Auto)]
+    );
+    assert_eq!(
+        config.approval.commands,
+        vec![("cargo test*".to_string(), ApprovalMode::Auto)]
+    );
+
+    std::fs::write(
+        &config_path,
+        r#"kota.setup({ approval = { mode = "sometimes", paths = { ["src/[a"] = "auto" } } })"#,
+    )
+    .unwrap();
+    let diagnostics = KotaConfig::check_lua_file(&config_path);
+    let paths: Vec<&str> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+    assert!(paths.contains(&"approval.mode"));
+    assert!(paths.contains(&"approval.paths.src/[a"));
+}
@@ -0,0 +1,158 @@
+use kota::kota_code::checkpoint::CheckpointManager;
+use kota::kota_code::context::ContextManager;
+use kota::kota_code::tools::delete_file::DeleteFileArgs;
+use kota::kota_code::tools::write_file::WriteFileArgs;
+use kota::kota_code::tools::{WrappedDeleteFileTool, WrappedWriteFileTool};
+use rig::tool::Tool;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_snapshots_are_grouped_per_turn() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("a.txt");
+    fs::write(&file, "v1").unwrap();
+    let checkpoints = CheckpointManager::new();
+
+    checkpoints.begin_turn("first");
+    checkpoints.snapshot(&file).unwrap();
+    fs::write(&file, "v2").unwrap();
+    // 同一轮中再次修改只保留第一次的快照
+    checkpoints.snapshot(&file).unwrap();
+    fs::write(&file, "v3").unwrap();
+
+    // 没有修改文件的轮次不创建检查点
+    checkpoints.begin_turn("no changes");
+    checkpoints.begin_turn("second");
+    checkpoints.snapshot(&file).unwrap();
+    fs::write(&file, "v4").unwrap();
+
+    let list = checkpoints.list().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].prompt, "first");
+    assert_eq!(list[0].files.len(), 1);
+    assert_eq!(list[1].prompt, "second");
+
+    let undone = checkpoints.undo().unwrap().unwrap();
+    assert_eq!(undone.prompt, "second");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "v3");
+
+    checkpoints.undo().unwrap().unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "v1");
+    assert!(checkpoints.undo().unwrap().is_none());
+}
+
+#[test]
+fn test_undo_removes_created_files_and_restores_deleted_ones() {
+    let temp_dir = TempDir::new().unwrap();
+    let created = temp_dir.path().join("new/created.txt");
+    let deleted = temp_dir.path().join("deleted.txt");
+    fs::write(&deleted, "keep me").unwrap();
+    let checkpoints = CheckpointManager::new();
+
+    checkpoints.begin_turn("create and delete");
+    checkpoints.snapshot(&created).unwrap();
+    fs::create_dir_all(created.parent().unwrap()).unwrap();
+    fs::write(&created, "new").unwrap();
+    checkpoints.snapshot(&deleted).unwrap();
+    fs::remove_file(&deleted).unwrap();
+
+    checkpoints.undo().unwrap();
+    assert!(!created.exists());
+    assert_eq!(fs::read_to_string(&deleted).unwrap(), "keep me");
+}
+
+#[test]
+fn test_restore_reverts_later_turns_and_persists() {
+    let temp_dir = TempDir::new().unwrap();
+    let sessions = temp_dir.path().join("sessions");
+    let file = temp_dir.path().join("a.txt");
+    fs::write(&file, "v1").unwrap();
+
+    let checkpoints = CheckpointManager::open(&sessions, "session").unwrap();
+    for (turn, content) in ["v2", "v3", "v4"].iter().enumerate() {
+        checkpoints.begin_turn(&format!("turn {}", turn));
+        checkpoints.snapshot(&file).unwrap();
+        fs::write(&file, content).unwrap();
+    }
+    let ids: Vec<usize> = checkpoints.list().unwrap().iter().map(|c| c.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+
+    // 重新打开后检查点仍然存在
+    let reopened = CheckpointManager::open(&sessions, "session").unwrap();
+    assert_eq!(reopened.list().unwrap().len(), 3);
+
+    let restored = reopened.restore(2).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "v2");
+    assert_eq!(reopened.list().unwrap().len(), 1);
+    assert!(reopened.restore(3).is_err());
+
+    // 新的检查点不复用已删除的 ID
+    reopened.begin_turn("turn 4");
+    reopened.snapshot(&file).unwrap();
+    assert_eq!(reopened.list().unwrap().last().unwrap().id, 4);
+
+    // 检查点目录不会被当作会话
+    let context = ContextManager::new(&sessions, "session".to_string()).unwrap();
+    assert!(context.list_sessions().unwrap().is_empty());
+
+    assert!(CheckpointManager::delete_session(&sessions, "session").unwrap());
+    let deleted = CheckpointManager::open(&sessions, "session").unwrap();
+    assert!(deleted.list().unwrap().is_empty());
+}
+
+#[test]
+fn test_session_ids_cannot_escape_the_sessions_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let sessions = temp_dir.path().join("sessions");
+    fs::create_dir_all(sessions.join("checkpoints")).unwrap();
+    let kept = temp_dir.path().join("keep.txt");
+    fs::write(&kept, "important").unwrap();
+
+    for session_id in ["", ".", "..", "../..", "a/b", "a\\b"] {
+        assert!(CheckpointManager::delete_session(&sessions, session_id).is_err());
+        assert!(CheckpointManager::open(&sessions, session_id).is_err());
+        assert!(ContextManager::new(&sessions, session_id.to_string()).is_err());
+    }
+
+    // 指向外部的符号链接不会被递归删除
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(temp_dir.path(), sessions.join("checkpoints/link")).unwrap();
+        assert!(CheckpointManager::delete_session(&sessions, "link").is_err());
+    }
+    assert!(sessions.join("checkpoints").exists());
+    assert_eq!(fs::read_to_string(&kept).unwrap(), "important");
+}
+
+#[tokio::test]
+async fn test_file_tools_snapshot_before_changing_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("a.txt");
+    fs::write(&file, "original").unwrap();
+    let checkpoints = CheckpointManager::new();
+    let write_file = WrappedWriteFileTool::new().with_checkpoints(checkpoints.clone());
+    let delete_file = WrappedDeleteFileTool::new().with_checkpoints(checkpoints.clone());
+
+    checkpoints.begin_turn("rewrite");
+    write_file
+        .call(WriteFileArgs {
+            file_path: file.to_string_lossy().to_string(),
+            content: "changed".to_string(),
+        })
+        .await
+        .unwrap();
+    checkpoints.begin_turn("delete");
+    delete_file
+        .call(DeleteFileArgs {
+            file_path: file.to_string_lossy().to_string(),
+        })
+        .await
+        .unwrap();
+
+    checkpoints.undo().unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "changed");
+    checkpoints.undo().unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "original");
+}
@@ -0,0 +1,84 @@
+use kota::kota_cli::{CliArgs, CliCommand, OutputFormat};
+
+#[test]
+fn test_cli_args_defaults() {
+    let args = CliArgs::parse_from(["kota"]).unwrap();
+    assert_eq!(args, CliArgs::default());
+    assert_eq!(args.output, OutputFormat::Text);
+}
+
+#[test]
+fn test_cli_args_one_shot() {
+    let args = CliArgs::parse_from(["kota", "-p", "fix the build", "--output", "json"]).unwrap();
+    assert_eq!(args.prompt.as_deref(), Some("fix the build"));
+    assert_eq!(args.output, OutputFormat::Json);
+
+    let args = CliArgs::parse_from(["kota", "--prompt=-", "--output=text"]).unwrap();
+    assert_eq!(args.prompt.as_deref(), Some("-"));
+    assert_eq!(args.output, OutputFormat::Text);
+}
+
+#[test]
+fn test_cli_args_errors() {
+    assert!(CliArgs::parse_from(["kota", "-p"]).is_err());
+    assert!(CliArgs::parse_from(["kota", "--output", "yaml"]).is_err());
+    assert!(CliArgs::parse_from(["kota", "--bogus"]).is_err());
+}
+
+#[test]
+fn test_cli_args_overrides() {
+    let args = CliArgs::parse_from([
+        "kota",
+        "--config",
+        "ci/config.lua",
+        "-m",
+        "gpt-4o-mini",
+        "--session=nightly",
+        "--sessions-dir",
+        "/tmp/sessions",
+        "--no-tools",
+        "--cwd",
+        "../project",
+    ])
+    .unwrap();
+    assert_eq!(
+        args.config.as_deref(),
+        Some(std::path::Path::new("ci/config.lua"))
+    );
+    assert_eq!(args.session.as_deref(), Some("nightly"));
+    assert_eq!(
+        args.sessions_dir.as_deref(),
+        Some(std::path::Path::new("/tmp/sessions"))
+    );
+    assert_eq!(
+        args.cwd.as_deref(),
+        Some(std::path::Path::new("../project"))
+    );
+
+    let mut config = kota::KotaConfig::default();
+    args.apply_to(&mut config);
+    assert_eq!(config.model, "gpt-4o-mini");
+    assert!(config.disable_all_tools);
+
+    assert!(CliArgs::parse_from(["kota", "--resume"]).unwrap().resume);
+    assert!(CliArgs::parse_from(["kota", "--resume", "--session", "a"]).is_err());
+}
+
+#[test]
+fn test_parse_config_check_command() {
+    let args = CliArgs::parse_from(["kota", "config", "check", "-c", "ci.lua"]).unwrap();
+    assert_eq!(args.command, Some(CliCommand::ConfigCheck));
+    assert_eq!(args.config.as_deref(), Some(std::path::Path::new("ci.lua")));
+
+    assert!(CliArgs::parse_from(["kota", "config"]).is_err());
+    assert!(CliArgs::parse_from(["kota", "--approval", "sometimes"]).is_err());
+
+    let args = CliArgs::parse_from(["kota", "--approval=read-only"]).unwrap();
+    let mut config = kota::KotaConfig::default();
+    args.apply_to(&mut config);
+    assert_eq!(
+        config.approval.mode,
+        kota::kota_code::runtime::ApprovalMode::ReadOnly
+    );
+    assert!(CliArgs::parse_from(["kota", "config", "lint"]).is_err());
+}
@@ -35,3 +35,168 @@ fn test_context_manager_max_messages() {
 
     assert_eq!(manager.get_messages().len(), 2);
 }
+
+#[test]
+fn test_context_manager_round_trips_tool_calls() {
+    use rig::completion::message::{
+        AssistantContent, Image, ImageMediaType, Reasoning, ToolCall, ToolFunction, UserContent,
+    };
+    use rig::OneOrMany;
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = ContextManager::new(temp_dir.path(), "tools".to_string()).unwrap();
+
+    let messages = vec![
+        Message::User {
+            content: OneOrMany::many(vec![
+                UserContent::text("What is in this screenshot?"),
+                UserContent::Image(Image {
+                    data: rig::completion::message::DocumentSourceKind::Base64(
+                        "aGVsbG8=".to_string(),
+                    ),
+                    media_type: Some(ImageMediaType::PNG),
+                    ..Default::default()
+                }),
+            ])
+            .unwrap(),
+        },
+        Message::Assistant {
+            id: Some("msg_1".to_string()),
+            content: OneOrMany::many(vec![
+                AssistantContent::Reasoning(
+                    Reasoning::new("Need to read the file").with_signature(Some("sig".into())),
+                ),
+                AssistantContent::ToolCall(
+                    ToolCall::new(
+                        "call_1".to_string(),
+                        ToolFunction::new(
+                            "read_file".to_string(),
+                            serde_json::json!({ "file_path": "src/main.rs" }),
+                        ),
+                    )
+                    .with_call_id("fc_1".to_string()),
+                ),
+            ])
+            .unwrap(),
+        },
+        Message::User {
+            content: OneOrMany::one(UserContent::tool_result_with_call_id(
+                "call_1",
+                "fc_1".to_string(),
+                OneOrMany::one(rig::completion::message::ToolResultContent::text(
+                    "fn main() {}",
+                )),
+            )),
+        },
+        Message::assistant("It is an empty main function."),
+    ];
+    for message in &messages {
+        manager.add_message(message.clone());
+    }
+    manager.save().unwrap();
+
+    let mut loaded = ContextManager::new(temp_dir.path(), "tools".to_string()).unwrap();
+    assert!(loaded.load().unwrap());
+    assert_eq!(loaded.get_messages(), messages.as_slice());
+}
+
+#[test]
+fn test_context_manager_migrates_v1_sessions() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("old.json"),
+        r#"{
+  "metadata": {
+    "session_id": "old",
+    "created_at": "2025-01-01T00:00:00+00:00",
+    "last_updated": "2025-01-01T00:00:00+00:00",
+    "message_count": 2
+  },
+  "messages": [
+    { "role": "user", "content": "Hello" },
+    { "role": "assistant", "content": "Hi there!" }
+  ]
+}"#,
+    )
+    .unwrap();
+
+    let mut manager = ContextManager::new(temp_dir.path(), "old".to_string()).unwrap();
+    assert_eq!(manager.list_sessions().unwrap().len(), 1);
+    assert!(manager.load().unwrap());
+    assert_eq!(
+        manager.get_messages(),
+        &[Message::user("Hello"), Message::assistant("Hi there!")]
+    );
+
+    // Saving rewrites the file in the current format
+    manager.save().unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(temp_dir.path().join("old.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        json["version"],
+        kota::kota_code::context::SESSION_FORMAT_VERSION
+    );
+    assert_eq!(json["messages"][0]["content"][0]["text"], "Hello");
+}
+
+#[test]
+fn test_context_manager_token_budget_compaction() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = ContextManager::new(temp_dir.path(), "budget".to_string())
+        .unwrap()
+        .with_max_messages(2)
+        .with_token_budget(100);
+
+    for i in 0..4 {
+        manager.add_message(Message::user(format!("question {} {}", i, "x".repeat(80))));
+        manager.add_message(Message::assistant(format!(
+            "answer {} {}",
+            i,
+            "y".repeat(80)
+        )));
+    }
+
+    // In budget mode messages are not dropped by count
+    assert_eq!(manager.get_messages().len(), 8);
+    assert!(manager.estimated_tokens() > 100);
+
+    // The span always ends right before a user prompt
+    let count = manager.compaction_span().unwrap();
+    assert!(count > 0 && count < 8);
+    assert_eq!(count % 2, 0);
+    assert!(manager
+        .compaction_transcript(count)
+        .starts_with("USER: question 0"));
+
+    manager.apply_summary(count, "The user asked numbered questions.".to_string());
+    assert_eq!(manager.get_messages().len(), 8 - count);
+    assert_eq!(manager.summary().unwrap().summarized_messages, count);
+
+    let history = manager.history();
+    assert_eq!(history.len(), manager.get_messages().len() + 1);
+    assert!(kota::SerializableMessage::from(&history[0])
+        .content
+        .contains("The user asked numbered questions."));
+
+    // The summary is stored in the session file and restored on load
+    manager.save().unwrap();
+    let mut loaded = ContextManager::new(temp_dir.path(), "budget".to_string()).unwrap();
+    assert!(loaded.load().unwrap());
+    assert_eq!(loaded.summary(), manager.summary());
+    assert_eq!(loaded.get_messages(), manager.get_messages());
+}
+
+#[test]
+fn test_context_manager_within_budget_is_not_compacted() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = ContextManager::new(temp_dir.path(), "small".to_string())
+        .unwrap()
+        .with_token_budget(10_000);
+    manager.add_message(Message::user("Hello"));
+    manager.add_message(Message::assistant("Hi there!"));
+    assert_eq!(manager.compaction_span(), None);
+
+    let unlimited = ContextManager::new(temp_dir.path(), "none".to_string()).unwrap();
+    assert_eq!(unlimited.compaction_span(), None);
+}
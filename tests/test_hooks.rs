@@ -0,0 +1,168 @@
+use kota::kota_code::runtime::dyn_tools_loader::dyn_tool::LuaDynTool;
+use kota::kota_code::runtime::{BeforeExecute, HookedTool, LoggedTool, ToolCallLog, ToolHooks};
+use rig::tool::ToolDyn;
+use serde_json::json;
+use std::sync::Arc;
+
+fn dump_lua(code: &str) -> Vec<u8> {
+    let lua = mlua::Lua::new();
+    let func: mlua::Function = lua.load(code).eval().unwrap();
+    func.dump(false)
+}
+
+fn echo_tool() -> Box<dyn ToolDyn> {
+    Box::new(LuaDynTool::new(
+        "echo".to_string(),
+        "Echo the arguments back".to_string(),
+        json!({ "type": "object" }),
+        dump_lua("function(args) return args end"),
+    ))
+}
+
+#[test]
+fn test_before_execute_veto_and_rewrite() {
+    let hooks = ToolHooks {
+        before_execute: Some(dump_lua(
+            r#"
+            function(tool, args)
+                if tool == "exec_cmd" then
+                    return false, "shell access is disabled"
+                end
+                if tool == "read_file" then
+                    args.file_path = "safe/" .. args.file_path
+                    return args
+                end
+            end
+        "#,
+        )),
+        after_execute: None,
+    };
+
+    let action = hooks
+        .before_execute("exec_cmd", r#"{"command":"rm -rf /"}"#)
+        .unwrap();
+    assert_eq!(
+        action,
+        BeforeExecute::Veto("shell access is disabled".to_string())
+    );
+
+    let action = hooks
+        .before_execute("read_file", r#"{"file_path":"a.txt"}"#)
+        .unwrap();
+    match action {
+        BeforeExecute::Continue(args) => {
+            let args: serde_json::Value = serde_json::from_str(&args).unwrap();
+            assert_eq!(args["file_path"], "safe/a.txt");
+        }
+        _ => panic!("Expected rewritten arguments"),
+    }
+
+    let action = hooks.before_execute("write_file", r#"{"x":1}"#).unwrap();
+    assert_eq!(action, BeforeExecute::Continue(r#"{"x":1}"#.to_string()));
+}
+
+#[tokio::test]
+async fn test_hooked_tool_vetoes_call() {
+    let hooks = ToolHooks {
+        before_execute: Some(dump_lua("function(tool, args) return false end")),
+        after_execute: None,
+    };
+    let tool = HookedTool::new(echo_tool(), Arc::new(hooks));
+
+    let result = tool.call(r#"{"secret":"value"}"#.to_string()).await;
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("blocked by before_execute hook"));
+}
+
+#[tokio::test]
+async fn test_hooked_tool_redacts_result() {
+    let hooks = ToolHooks {
+        before_execute: None,
+        after_execute: Some(dump_lua(
+            r#"
+            function(result, tool, args)
+                result.secret = "[redacted]"
+                return result
+            end
+        "#,
+        )),
+    };
+    let tool = HookedTool::new(echo_tool(), Arc::new(hooks));
+    assert_eq!(tool.name(), "echo");
+
+    let result = tool
+        .call(r#"{"secret":"value","visible":1}"#.to_string())
+        .await
+        .unwrap();
+    let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(result["secret"], "[redacted]");
+    assert_eq!(result["visible"], 1);
+}
+
+#[tokio::test]
+async fn test_hooked_tool_rewrites_errors() {
+    let failing = || {
+        Box::new(LuaDynTool::new(
+            "fail".to_string(),
+            "Always fails".to_string(),
+            json!({ "type": "object" }),
+            dump_lua(r#"function(args) error("token sk-123 rejected") end"#),
+        )) as Box<dyn ToolDyn>
+    };
+    let hooks = ToolHooks {
+        before_execute: None,
+        after_execute: Some(dump_lua(
+            r#"
+            function(result, tool, args, err)
+                if err then
+                    return (err:gsub("sk%-%w+", "[redacted]"))
+                end
+            end
+        "#,
+        )),
+    };
+    let tool = HookedTool::new(failing(), Arc::new(hooks));
+    let err = tool.call("{}".to_string()).await.unwrap_err().to_string();
+    assert!(err.contains("token [redacted] rejected"), "{}", err);
+    assert!(!err.contains("sk-123"));
+
+    // A hook that does not expect errors keeps the tool's error visible
+    let hooks = ToolHooks {
+        before_execute: None,
+        after_execute: Some(dump_lua("function(result) return result.value end")),
+    };
+    let tool = HookedTool::new(failing(), Arc::new(hooks));
+    let err = tool.call("{}".to_string()).await.unwrap_err().to_string();
+    assert!(err.contains("after_execute hook failed"), "{}", err);
+    assert!(err.contains("sk-123 rejected"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_logged_tool_records_calls() {
+    let hooks = ToolHooks {
+        before_execute: Some(dump_lua(
+            r#"function(tool, args) if args.blocked then return false, "nope" end end"#,
+        )),
+        after_execute: None,
+    };
+    let log = ToolCallLog::new();
+    let tool = LoggedTool::new(
+        Box::new(HookedTool::new(echo_tool(), Arc::new(hooks))),
+        log.clone(),
+    );
+
+    tool.call(r#"{"message":"hi"}"#.to_string()).await.unwrap();
+    assert!(tool.call(r#"{"blocked":true}"#.to_string()).await.is_err());
+
+    let records = log.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].name, "echo");
+    assert_eq!(records[0].arguments, json!({ "message": "hi" }));
+    assert!(records[0].success);
+    assert!(!records[1].success);
+    assert!(records[1].error.as_deref().unwrap().contains("nope"));
+    assert!(log.has_failures());
+
+    assert_eq!(log.take().len(), 2);
+    assert!(log.records().is_empty());
+}
@@ -0,0 +1,87 @@
+use kota::kota_cli::{CliArgs, ConfigWatcher, KotaCli};
+use kota::{ContextManager, KotaConfig, McpManager, Provider, SkillManager};
+use rig::completion::Message;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const INITIAL_CONFIG: &str = r#"
+kota.setup({
+  provider = "openai",
+  model = "gpt-4o",
+  api_key = "test-key",
+  commands = { fix = "fix it" },
+})
+"#;
+
+const UPDATED_CONFIG: &str = r#"
+kota.setup({
+  model = "deepseek-chat",
+  api_key = "test-key",
+  commands = { fix = "fix it", review = "review it" },
+})
+"#;
+
+// Changes the process working directory, so this file holds a single test
+#[tokio::test]
+async fn test_reload_keeps_session_and_watcher_detects_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+    let config_path = temp_dir.path().join("config.lua");
+    fs::write(&config_path, INITIAL_CONFIG).unwrap();
+
+    let config = KotaConfig::load_layered(Some(&config_path)).unwrap();
+    let mut context =
+        ContextManager::new(temp_dir.path().join("sessions"), "reload".to_string()).unwrap();
+    context.add_message(Message::user("hello"));
+
+    let cli_args = CliArgs {
+        config: Some(config_path.clone()),
+        ..Default::default()
+    };
+    let mut cli = KotaCli::new(
+        &config,
+        context,
+        SkillManager::new(),
+        None,
+        McpManager::new(),
+    )
+    .await
+    .unwrap()
+    .with_cli_args(cli_args);
+    assert_eq!(cli.provider, Provider::OpenAI);
+
+    fs::write(&config_path, UPDATED_CONFIG).unwrap();
+    cli.reload().await.unwrap();
+
+    assert_eq!(cli.model_name, "deepseek-chat");
+    assert_eq!(cli.provider, Provider::DeepSeek);
+    let registry = cli.command_registry.as_ref().unwrap();
+    assert!(registry.has_command("review"));
+    let context = cli.agent_instance.context().unwrap();
+    assert_eq!(context.session_id(), "reload");
+    assert_eq!(context.get_messages().len(), 1);
+
+    // An invalid config is rejected and the current setup is kept
+    fs::write(&config_path, r#"kota.setup({ model = 42 })"#).unwrap();
+    assert!(cli.reload().await.is_err());
+    assert_eq!(cli.model_name, "deepseek-chat");
+    assert!(cli.agent_instance.context().is_some());
+
+    // The watcher flags changes to the config file and the skills directory
+    let watcher = ConfigWatcher::start(Some(config_path.clone()));
+    assert!(!watcher.take_changed());
+    fs::write(&config_path, INITIAL_CONFIG).unwrap();
+    std::thread::sleep(Duration::from_millis(2500));
+    assert!(watcher.take_changed());
+    assert!(!watcher.take_changed());
+
+    fs::create_dir_all(".kota/skills/new-skill").unwrap();
+    fs::write(
+        ".kota/skills/new-skill/SKILL.md",
+        "---\nname: new-skill\ndescription: test\n---\n\nDo things.",
+    )
+    .unwrap();
+    std::thread::sleep(Duration::from_millis(2500));
+    assert!(watcher.take_changed());
+}
@@ -0,0 +1,59 @@
+use kota::kota_code::tools::{
+    WrappedDeleteFileTool, WrappedExecuteBashCommandTool, WrappedReadFileTool,
+};
+use kota::{ToolFilter, ToolRegistry};
+
+fn registry() -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    registry.add(Box::new(WrappedReadFileTool::new()));
+    registry.add(Box::new(WrappedDeleteFileTool::new()));
+    registry.add(Box::new(WrappedExecuteBashCommandTool::new()));
+    registry
+}
+
+#[test]
+fn test_tool_filter_deny_list() {
+    let mut registry = registry();
+    let filter = ToolFilter::new(
+        vec![],
+        vec!["delete_file".to_string(), "exec_cmd".to_string()],
+    );
+
+    registry.apply_filter(&filter);
+
+    assert_eq!(registry.names(), vec!["read_file"]);
+}
+
+#[test]
+fn test_tool_filter_allow_list_with_deny_precedence() {
+    let mut registry = registry();
+    let filter = ToolFilter::new(
+        vec!["read_file".to_string(), "exec_cmd".to_string()],
+        vec!["exec_cmd".to_string()],
+    );
+
+    registry.apply_filter(&filter);
+
+    assert_eq!(registry.names(), vec!["read_file"]);
+}
+
+#[test]
+fn test_tool_filter_unknown_names() {
+    let registry = registry();
+    let filter = ToolFilter::new(
+        vec!["read_file".to_string(), "grep_search".to_string()],
+        vec!["rm_rf".to_string()],
+    );
+
+    assert_eq!(
+        filter.unknown_names(&registry.names()),
+        vec!["grep_search", "rm_rf"]
+    );
+}
+
+#[test]
+fn test_tool_filter_none() {
+    let mut registry = registry();
+    registry.apply_filter(&ToolFilter::none());
+    assert!(registry.is_empty());
+}
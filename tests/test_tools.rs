@@ -18,7 +18,15 @@ pub fn create_test_file(dir: &Path, filename: &str, content: &str) -> String {
 // Test modules for all tools
 #[path = "tools/test_edit_file.rs"]
 mod test_edit_file;
+#[path = "tools/test_execute_bash_command.rs"]
+mod test_execute_bash_command;
+#[path = "tools/test_process.rs"]
+mod test_process;
 #[path = "tools/test_read_file.rs"]
 mod test_read_file;
+#[path = "tools/test_shell_session.rs"]
+mod test_shell_session;
+#[path = "tools/test_workspace.rs"]
+mod test_workspace;
 #[path = "tools/test_write_file.rs"]
 mod test_write_file;
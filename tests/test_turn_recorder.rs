@@ -0,0 +1,158 @@
+use kota::kota_code::agent::TurnRecorder;
+use kota::kota_code::context::ContextManager;
+use rig::agent::{MultiTurnStreamItem, Text};
+use rig::completion::message::{
+    AssistantContent, ToolCall, ToolFunction, ToolResult, ToolResultContent, UserContent,
+};
+use rig::completion::{Message, Usage};
+use rig::streaming::{StreamedAssistantContent, StreamedUserContent};
+use rig::OneOrMany;
+use tempfile::TempDir;
+
+fn text(text: &str) -> MultiTurnStreamItem<()> {
+    MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(Text {
+        text: text.to_string(),
+    }))
+}
+
+fn tool_call(id: &str, name: &str) -> MultiTurnStreamItem<()> {
+    MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall {
+        tool_call: ToolCall::new(
+            id.to_string(),
+            ToolFunction::new(name.to_string(), serde_json::json!({ "file_path": "a.rs" })),
+        ),
+        internal_call_id: id.to_string(),
+    })
+}
+
+fn tool_result(id: &str, output: &str) -> MultiTurnStreamItem<()> {
+    MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult {
+        tool_result: ToolResult {
+            id: id.to_string(),
+            call_id: None,
+            content: OneOrMany::one(ToolResultContent::text(output)),
+        },
+        internal_call_id: id.to_string(),
+    })
+}
+
+#[test]
+fn test_turn_recorder_keeps_tool_loop() {
+    let mut recorder = TurnRecorder::new();
+    for item in [
+        text("Let me read it."),
+        tool_call("call_1", "read_file"),
+        tool_result("call_1", "fn a() {}"),
+        tool_call("call_2", "edit_file"),
+        tool_result("call_2", "ok"),
+        text("Done."),
+        MultiTurnStreamItem::final_response("Done.", Usage::new()),
+    ] {
+        recorder.record(&item);
+    }
+
+    let messages = recorder.finish();
+    assert_eq!(messages.len(), 5);
+
+    let Message::Assistant { content, .. } = &messages[0] else {
+        panic!("expected assistant message");
+    };
+    assert_eq!(content.len(), 2);
+    assert!(matches!(content.first(), AssistantContent::Text(t) if t.text == "Let me read it."));
+    assert!(
+        matches!(content.iter().nth(1), Some(AssistantContent::ToolCall(c)) if c.function.name == "read_file")
+    );
+
+    let Message::User { content } = &messages[1] else {
+        panic!("expected tool result");
+    };
+    assert!(matches!(content.first(), UserContent::ToolResult(r) if r.id == "call_1"));
+
+    assert!(matches!(&messages[2], Message::Assistant { .. }));
+    assert!(matches!(&messages[3], Message::User { .. }));
+    assert_eq!(messages[4], Message::assistant("Done."));
+}
+
+#[test]
+fn test_context_trim_does_not_orphan_tool_results() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = ContextManager::new(temp_dir.path(), "trim".to_string())
+        .unwrap()
+        .with_max_messages(4);
+
+    let mut recorder = TurnRecorder::new();
+    for item in [
+        tool_call("call_1", "read_file"),
+        tool_result("call_1", "fn a() {}"),
+        text("It defines a."),
+    ] {
+        recorder.record(&item);
+    }
+
+    manager.add_message(Message::user("What is in a.rs?"));
+    for message in recorder.finish() {
+        manager.add_message(message);
+    }
+    manager.add_message(Message::user("Thanks"));
+
+    // The oldest user prompt was dropped, so its tool call and result go with it
+    assert_eq!(manager.get_messages(), &[Message::user("Thanks")]);
+}
+
+#[test]
+fn test_context_trim_keeps_a_long_current_turn() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = ContextManager::new(temp_dir.path(), "long".to_string())
+        .unwrap()
+        .with_max_messages(4);
+
+    manager.add_message(Message::user("Earlier question"));
+    manager.add_message(Message::assistant("Earlier answer"));
+    manager.add_message(Message::user("Read every file"));
+    let mut recorder = TurnRecorder::new();
+    for i in 0..5 {
+        let id = format!("call_{}", i);
+        recorder.record(&tool_call(&id, "read_file"));
+        recorder.record(&tool_result(&id, "contents"));
+    }
+    for message in recorder.finish() {
+        manager.add_message(message);
+    }
+
+    // The earlier turn is dropped, the current prompt and all its tool messages stay
+    let messages = manager.get_messages();
+    assert!(messages.len() > 4);
+    assert_eq!(messages[0], Message::user("Read every file"));
+    assert!(messages[1..]
+        .iter()
+        .all(|message| *message != Message::user("Earlier question")));
+}
+
+#[test]
+fn test_turn_recorder_interrupted_keeps_completed_tool_calls() {
+    let mut recorder = TurnRecorder::new();
+    for item in [
+        tool_call("call_1", "edit_file"),
+        tool_call("call_2", "exec_cmd"),
+        tool_result("call_1", "ok"),
+    ] {
+        recorder.record(&item);
+    }
+
+    // The stream failed before call_2 returned, so only call_1 and its result remain
+    let messages = recorder.finish_interrupted();
+    assert_eq!(messages.len(), 2);
+    let Message::Assistant { content, .. } = &messages[0] else {
+        panic!("expected assistant message");
+    };
+    assert_eq!(content.len(), 1);
+    assert!(matches!(content.first(), AssistantContent::ToolCall(c) if c.id == "call_1"));
+    assert!(
+        matches!(&messages[1], Message::User { content } if matches!(content.first(), UserContent::ToolResult(r) if r.id == "call_1"))
+    );
+
+    // Nothing completed: no messages besides the prompt the caller keeps
+    let mut recorder = TurnRecorder::new();
+    recorder.record(&tool_call("call_1", "read_file"));
+    assert!(recorder.finish_interrupted().is_empty());
+}
@@ -12,6 +12,7 @@ async fn test_edit_file_simple_replacement() {
     let args = EditFileArgs {
         file_path: file_path.clone(),
         patch: "--- a/test.txt\n+++ a/test.txt\n@@ -1,3 +1,3 @@\n line 1\n-line 2\n+modified line 2\n line 3\n".to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -22,7 +23,7 @@ async fn test_edit_file_simple_replacement() {
     assert_eq!(result.file_path, file_path);
 
     let content = fs::read_to_string(&file_path).unwrap();
-    assert_eq!(content, "line 1\nmodified line 2\nline 3");
+    assert_eq!(content, "line 1\nmodified line 2\nline 3\n");
 }
 
 #[tokio::test]
@@ -36,6 +37,7 @@ async fn test_edit_file_add_lines() {
         patch:
             "--- a/test.txt\n+++ b/test.txt\n@@ -1,2 +1,4 @@\n line 1\n line 2\n+line 3\n+line 4\n"
                 .to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -45,7 +47,7 @@ async fn test_edit_file_add_lines() {
     assert_eq!(result.lines_removed, 0);
 
     let content = fs::read_to_string(&file_path).unwrap();
-    assert_eq!(content, "line 1\nline 2\nline 3\nline 4");
+    assert_eq!(content, "line 1\nline 2\nline 3\nline 4\n");
 }
 
 #[tokio::test]
@@ -63,6 +65,7 @@ async fn test_edit_file_remove_lines() {
         patch:
             "--- a/test.txt\n+++ b/test.txt\n@@ -1,4 +1,2 @@\n line 1\n-line 2\n-line 3\n line 4\n"
                 .to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -72,7 +75,7 @@ async fn test_edit_file_remove_lines() {
     assert_eq!(result.lines_removed, 2);
 
     let content = fs::read_to_string(&file_path).unwrap();
-    assert_eq!(content, "line 1\nline 4");
+    assert_eq!(content, "line 1\nline 4\n");
 }
 
 #[tokio::test]
@@ -88,6 +91,7 @@ async fn test_edit_file_complex_patch() {
     let args = EditFileArgs {
         file_path: file_path.clone(),
         patch: "--- a/code.rs\n+++ b/code.rs\n@@ -1,3 +1,4 @@\n fn main() {\n+    // Added comment\n     println!(\"Hello\");\n+    println!(\"World\");\n }".to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -99,7 +103,7 @@ async fn test_edit_file_complex_patch() {
     let content = fs::read_to_string(&file_path).unwrap();
     assert_eq!(
         content,
-        "fn main() {\n    // Added comment\n    println!(\"Hello\");\n    println!(\"World\");\n}"
+        "fn main() {\n    // Added comment\n    println!(\"Hello\");\n    println!(\"World\");\n}\n"
     );
 }
 
@@ -110,6 +114,7 @@ async fn test_edit_file_nonexistent_file() {
         file_path: "nonexistent.txt".to_string(),
         patch: "--- a/nonexistent.txt\n+++ b/nonexistent.txt\n@@ -1,1 +1,1 @@\n-old\n+new\n"
             .to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await;
@@ -126,6 +131,7 @@ async fn test_edit_file_directory_instead_of_file() {
     let args = EditFileArgs {
         file_path: dir_path.to_string_lossy().to_string(),
         patch: "@@ -1,1 +1,1 @@\n-old\n+new\n".to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await;
@@ -141,6 +147,7 @@ async fn test_edit_file_invalid_patch_format() {
     let args = EditFileArgs {
         file_path: file_path.clone(),
         patch: "invalid patch format".to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await;
@@ -156,6 +163,7 @@ async fn test_edit_file_empty_file() {
     let args = EditFileArgs {
         file_path: file_path.clone(),
         patch: "--- a/empty.txt\n+++ b/empty.txt\n@@ -0,0 +1,1 @@\n+new line\n".to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -165,7 +173,7 @@ async fn test_edit_file_empty_file() {
     assert_eq!(result.lines_removed, 0);
 
     let content = fs::read_to_string(&file_path).unwrap();
-    assert_eq!(content, "new line");
+    assert_eq!(content, "new line\n");
 }
 
 #[tokio::test]
@@ -178,6 +186,7 @@ async fn test_edit_file_single_line_replacement() {
         file_path: file_path.clone(),
         patch: "--- a/single.txt\n+++ b/single.txt\n@@ -1,1 +1,1 @@\n-old content\n+new content\n"
             .to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -187,7 +196,7 @@ async fn test_edit_file_single_line_replacement() {
     assert_eq!(result.lines_removed, 1);
 
     let content = fs::read_to_string(&file_path).unwrap();
-    assert_eq!(content, "new content");
+    assert_eq!(content, "new content\n");
 }
 
 #[tokio::test]
@@ -203,6 +212,7 @@ async fn test_edit_file_multiline_context() {
     let args = EditFileArgs {
         file_path: file_path.clone(),
         patch: "--- a/context.txt\n+++ b/context.txt\n@@ -2,3 +2,3 @@\n line 2\n-line 3\n+modified line 3\n line 4\n".to_string(),
+        ..Default::default()
     };
 
     let result = tool.call(args).await.unwrap();
@@ -224,3 +234,398 @@ async fn test_edit_file_tool_definition() {
     assert!(definition.description.contains("unified diff patch"));
     assert!(definition.parameters.get("properties").is_some());
 }
+
+#[tokio::test]
+async fn test_edit_file_locates_hunk_with_wrong_line_numbers() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(
+        temp_dir.path(),
+        "shifted.txt",
+        "header\nheader\nheader\nalpha\nbeta\ngamma\n",
+    );
+
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: file_path.clone(),
+        patch: "@@ -1,3 +1,3 @@\n alpha\n-beta\n+BETA\n gamma\n".to_string(),
+        ..Default::default()
+    };
+
+    let result = tool.call(args).await.unwrap();
+
+    assert_eq!(result.files[0].hunks[0].line, Some(4));
+    assert_eq!(result.files[0].hunks[0].offset, 3);
+    assert!(result.message.contains("offset +3"));
+    let content = fs::read_to_string(&file_path).unwrap();
+    assert_eq!(content, "header\nheader\nheader\nalpha\nBETA\ngamma\n");
+}
+
+#[tokio::test]
+async fn test_edit_file_tolerates_whitespace_and_fuzz() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(
+        temp_dir.path(),
+        "code.rs",
+        "fn main() {\n\tlet x = 1;  \n\tprintln!(\"{}\", x);\n}\n",
+    );
+
+    // Spaces instead of a tab, no trailing spaces, and a first context line that does not exist
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: file_path.clone(),
+        patch: "--- a/code.rs\n+++ b/code.rs\n@@ -1,4 +1,4 @@\n // main\n     let x = 1;\n-    println!(\"{}\", x);\n+    println!(\"x = {}\", x);\n }"
+            .to_string(),
+        ..Default::default()
+    };
+
+    let result = tool.call(args).await.unwrap();
+
+    let hunk = &result.files[0].hunks[0];
+    assert!(hunk.ignored_whitespace);
+    assert_eq!(hunk.fuzz, 1);
+    // Context lines keep the file's whitespace
+    let content = fs::read_to_string(&file_path).unwrap();
+    assert_eq!(
+        content,
+        "fn main() {\n\tlet x = 1;  \n    println!(\"x = {}\", x);\n}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_file_failed_hunk_changes_nothing() {
+    let temp_dir = create_temp_dir();
+    let original = "one\ntwo\nthree\nfour\nfive\n";
+    let file_path = create_test_file(temp_dir.path(), "fail.txt", original);
+
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: file_path.clone(),
+        patch: "@@ -1,2 +1,2 @@\n-one\n+ONE\n two\n@@ -3,3 +3,3 @@\n three\n-fore\n+FOUR\n fiv\n"
+            .to_string(),
+        ..Default::default()
+    };
+
+    let error = tool.call(args).await.unwrap_err().to_string();
+
+    assert!(error.contains("1 of 2 hunks failed"), "{}", error);
+    assert!(error.contains("hunk 1 @@ -1,2 +1,2 @@: applied at line 1"));
+    assert!(error.contains("closest match at line 3 (1 of 3 lines match)"));
+    assert!(error.contains("    | three"));
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), original);
+}
+
+#[tokio::test]
+async fn test_edit_file_multi_file_patch() {
+    let temp_dir = create_temp_dir();
+    let first = create_test_file(temp_dir.path(), "first.txt", "a\nb\n");
+    let second = create_test_file(temp_dir.path(), "second.txt", "c\nd\n");
+    let created = temp_dir.path().join("new/created.txt");
+    let created = created.to_string_lossy().to_string();
+
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: String::new(),
+        patch: format!(
+            "diff --git a/first.txt b/first.txt\n--- {first}\n+++ {first}\n@@ -1,2 +1,2 @@\n a\n-b\n+B\n\
+             --- {second}\n+++ {second}\n@@ -1,2 +1,3 @@\n c\n+c2\n d\n\
+             --- /dev/null\n+++ {created}\n@@ -0,0 +1 @@\n+hello\n"
+        ),
+        ..Default::default()
+    };
+
+    let result = tool.call(args).await.unwrap();
+
+    assert_eq!(result.files.len(), 3);
+    assert!(result.files[2].created);
+    assert_eq!((result.lines_added, result.lines_removed), (3, 1));
+    assert_eq!(fs::read_to_string(&first).unwrap(), "a\nB\n");
+    assert_eq!(fs::read_to_string(&second).unwrap(), "c\nc2\nd\n");
+    assert_eq!(fs::read_to_string(&created).unwrap(), "hello\n");
+
+    // A failing file leaves the other files untouched too
+    let args = EditFileArgs {
+        file_path: String::new(),
+        patch: format!(
+            "--- {first}\n+++ {first}\n@@ -1 +1 @@\n-a\n+A\n--- {second}\n+++ {second}\n@@ -1 +1 @@\n-missing\n+x\n"
+        ),
+        ..Default::default()
+    };
+    let error = tool.call(args).await.unwrap_err().to_string();
+    assert!(error.contains("no files were changed"));
+    assert_eq!(fs::read_to_string(&first).unwrap(), "a\nB\n");
+}
+
+#[tokio::test]
+async fn test_edit_file_sections_for_one_file_compose() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(temp_dir.path(), "test.txt", "a\nb\nc\n");
+
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: String::new(),
+        patch: format!(
+            "--- {0}\n+++ {0}\n@@ -1 +1 @@\n-a\n+A\n--- {0}\n+++ {0}\n@@ -3 +3 @@\n-c\n+C\n",
+            file_path
+        ),
+        ..Default::default()
+    };
+
+    let result = tool.call(args).await.unwrap();
+
+    // The second section applies on top of the first instead of overwriting it
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "A\nb\nC\n");
+    assert_eq!(result.files.len(), 1);
+    assert_eq!(result.files[0].hunks.len(), 2);
+    assert_eq!(result.files[0].hunks[1].hunk, 2);
+    assert_eq!((result.lines_added, result.lines_removed), (2, 2));
+}
+
+#[tokio::test]
+async fn test_edit_file_ambiguous_hunk_without_line_numbers_fails() {
+    let temp_dir = create_temp_dir();
+    let content = "fn a() {\n    todo!()\n}\n\nfn b() {\n    todo!()\n}\n";
+    let file_path = create_test_file(temp_dir.path(), "test.rs", content);
+
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: file_path.clone(),
+        patch: "--- a/test.rs\n+++ b/test.rs\n@@ @@\n-    todo!()\n+    42\n }\n".to_string(),
+        ..Default::default()
+    };
+
+    let error = tool.call(args).await.unwrap_err().to_string();
+    assert!(error.contains("matches at lines 2, 6"), "{}", error);
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), content);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_edit_file_write_failure_changes_nothing() {
+    let temp_dir = create_temp_dir();
+    let first = create_test_file(temp_dir.path(), "first.txt", "a\n");
+    // A directory cannot be created under a regular file
+    let blocked = temp_dir.path().join("first.txt/created.txt");
+    let blocked = blocked.to_string_lossy().to_string();
+
+    let tool = EditFileTool;
+    let args = EditFileArgs {
+        file_path: String::new(),
+        patch: format!(
+            "--- {first}\n+++ {first}\n@@ -1 +1 @@\n-a\n+A\n--- /dev/null\n+++ {blocked}\n@@ -0,0 +1 @@\n+hello\n"
+        ),
+        ..Default::default()
+    };
+
+    assert!(tool.call(args).await.is_err());
+    assert_eq!(fs::read_to_string(&first).unwrap(), "a\n");
+    let leftovers: Vec<_> = fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(leftovers, ["first.txt"]);
+}
+
+#[tokio::test]
+async fn test_edit_file_preserves_line_endings() {
+    let temp_dir = create_temp_dir();
+    let crlf = create_test_file(temp_dir.path(), "crlf.txt", "one\r\ntwo\r\n");
+    let no_newline = create_test_file(temp_dir.path(), "no_newline.txt", "one\ntwo");
+
+    let tool = EditFileTool;
+    tool.call(EditFileArgs {
+        file_path: crlf.clone(),
+        patch: "@@ -1,2 +1,2 @@\n one\n-two\n+TWO\n".to_string(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    assert_eq!(fs::read_to_string(&crlf).unwrap(), "one\r\nTWO\r\n");
+
+    tool.call(EditFileArgs {
+        file_path: no_newline.clone(),
+        patch: "@@ -1,2 +1,3 @@\n one\n-two\n\\ No newline at end of file\n+two\n+three\n"
+            .to_string(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&no_newline).unwrap(),
+        "one\ntwo\nthree\n"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_file_stats_and_diff_reflect_applied_change() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(temp_dir.path(), "test.txt", "a\nb\nc\nd\n");
+
+    let tool = EditFileTool;
+    let result = tool
+        .call(EditFileArgs {
+            file_path: file_path.clone(),
+            patch: "@@ -1,4 +1,4 @@\n-a\n-b\n+a\n+B\n c\n d\n".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // The patch rewrites `a` unchanged; only `b` really changed
+    assert_eq!((result.lines_added, result.lines_removed), (1, 1));
+    assert_eq!(
+        result.files[0].diff,
+        "@@ -1,4 +1,4 @@\n a\n-b\n+B\n c\n d\n"
+    );
+}
+
+fn replace_args(file_path: &str, old_string: &str, new_string: &str) -> EditFileArgs {
+    EditFileArgs {
+        file_path: file_path.to_string(),
+        old_string: Some(old_string.to_string()),
+        new_string: Some(new_string.to_string()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_edit_file_replace_unique_match() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(
+        temp_dir.path(),
+        "main.rs",
+        "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n",
+    );
+
+    let tool = EditFileTool;
+    let result = tool
+        .call(replace_args(
+            &file_path,
+            "    let x = 1;\n",
+            "    let x = 1;\n    let y = 2;\n",
+        ))
+        .await
+        .unwrap();
+
+    assert!(result.success);
+    assert_eq!(result.files[0].replacements, Some(1));
+    assert_eq!(result.lines_added, 1);
+    assert_eq!(result.lines_removed, 0);
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        "fn main() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x);\n}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_file_replace_ambiguous_match_lists_candidates() {
+    let temp_dir = create_temp_dir();
+    let content = "let a = 0;\nlet b = 0;\nlet c = 1;\n";
+    let file_path = create_test_file(temp_dir.path(), "test.rs", content);
+
+    let tool = EditFileTool;
+    let error = tool
+        .call(replace_args(&file_path, "= 0;", "= 2;"))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("matches 2 times"), "{}", error);
+    assert!(error.contains("line 1: let a = 0;"), "{}", error);
+    assert!(error.contains("line 2: let b = 0;"), "{}", error);
+    assert!(error.contains("replace_all"), "{}", error);
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), content);
+}
+
+#[tokio::test]
+async fn test_edit_file_replace_all_and_expected_occurrences() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(temp_dir.path(), "test.rs", "foo();\nfoo();\nbar();\n");
+
+    let tool = EditFileTool;
+    let error = tool
+        .call(EditFileArgs {
+            expected_occurrences: Some(3),
+            ..replace_args(&file_path, "foo", "baz")
+        })
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("matches 2 times"), "{}", error);
+    assert!(error.contains("3 expected"), "{}", error);
+
+    let result = tool
+        .call(EditFileArgs {
+            expected_occurrences: Some(2),
+            ..replace_args(&file_path, "foo", "baz")
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.files[0].replacements, Some(2));
+
+    tool.call(EditFileArgs {
+        replace_all: true,
+        ..replace_args(&file_path, "();", "(1);")
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        "baz(1);\nbaz(1);\nbar(1);\n"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_file_replace_missing_text_points_at_near_matches() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(
+        temp_dir.path(),
+        "test.py",
+        "def f():\n    if x:\n        return 1\n",
+    );
+
+    let tool = EditFileTool;
+    let error = tool
+        .call(replace_args(
+            &file_path,
+            "if x:\n    return 1",
+            "if y:\n    return 2",
+        ))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("not found"), "{}", error);
+    assert!(
+        error.contains("line 2 when whitespace is ignored"),
+        "{}",
+        error
+    );
+
+    let error = tool
+        .call(replace_args(&file_path, "def f():\n    if z:", "def g():"))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("closest match at line 1 (1 of 2 lines match)"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_edit_file_replace_rejects_invalid_arguments() {
+    let temp_dir = create_temp_dir();
+    let file_path = create_test_file(temp_dir.path(), "test.txt", "a\n");
+
+    let tool = EditFileTool;
+    let both = EditFileArgs {
+        patch: "@@ -1 +1 @@\n-a\n+b\n".to_string(),
+        ..replace_args(&file_path, "a", "b")
+    };
+    assert!(tool.call(both).await.is_err());
+    assert!(tool.call(replace_args(&file_path, "a", "a")).await.is_err());
+    assert!(tool.call(replace_args(&file_path, "", "b")).await.is_err());
+    assert!(tool.call(replace_args("", "a", "b")).await.is_err());
+    assert!(tool.call(EditFileArgs::default()).await.is_err());
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "a\n");
+}
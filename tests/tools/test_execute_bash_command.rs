@@ -0,0 +1,234 @@
+use kota::kota_code::tools::execute_bash_command::{
+    ExecuteBashCommandArgs, ExecuteBashCommandTool,
+};
+use kota::kota_code::tools::{CommandInterrupt, ExecOptions, FileToolError};
+use rig::tool::Tool;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn args(command: &str, timeout_secs: Option<u64>) -> ExecuteBashCommandArgs {
+    ExecuteBashCommandArgs {
+        command: command.to_string(),
+        timeout_secs,
+        cwd: None,
+        env: HashMap::new(),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_exit_code_and_output() {
+    let tool = ExecuteBashCommandTool::new();
+    let output = tool
+        .call(args("echo out; echo err >&2; exit 3", None))
+        .await
+        .unwrap();
+
+    assert!(!output.success);
+    assert_eq!(output.exit_code, Some(3));
+    assert_eq!(output.stdout, "out\n");
+    assert_eq!(output.stderr, "err\n");
+    assert!(!output.timed_out && !output.cancelled && !output.truncated);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_stdin_is_closed() {
+    let tool = ExecuteBashCommandTool::new();
+    let output = tool
+        .call(args("read line; echo \"read: $?\"", Some(5)))
+        .await
+        .unwrap();
+
+    assert!(!output.timed_out);
+    assert_eq!(output.stdout, "read: 1\n");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_exec_timeout_kills_process_group() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let pid_file = temp_dir.path().join("pid");
+    let tool = ExecuteBashCommandTool::new();
+
+    let start = Instant::now();
+    let command = format!(
+        "echo started; sleep 60 & echo $! > {}; wait",
+        pid_file.display()
+    );
+    let output = tool.call(args(&command, Some(1))).await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(output.timed_out);
+    assert!(!output.success);
+    assert_eq!(output.exit_code, None);
+    assert_eq!(output.stdout, "started\n");
+    assert!(output.stderr.contains("timed out after 1s"));
+
+    // The background `sleep` was in the same process group and is gone too
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+    let mut alive = true;
+    for _ in 0..20 {
+        alive = std::path::Path::new(&format!("/proc/{}", pid.trim())).exists()
+            && !std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+                .unwrap_or_default()
+                .contains(") Z");
+        if !alive {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(
+        !alive,
+        "background process {} survived the timeout",
+        pid.trim()
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_default_timeout_from_options() {
+    let tool = ExecuteBashCommandTool::new().with_options(ExecOptions {
+        timeout_secs: 1,
+        ..ExecOptions::default()
+    });
+    let output = tool.call(args("sleep 30", None)).await.unwrap();
+    assert!(output.timed_out);
+
+    let result = tool.call(args("true", Some(0))).await;
+    assert!(result.is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_output_truncated_head_and_tail() {
+    let tool = ExecuteBashCommandTool::new().with_options(ExecOptions {
+        max_output_bytes: 1000,
+        ..ExecOptions::default()
+    });
+    let output = tool.call(args("seq 1 100000", None)).await.unwrap();
+
+    assert!(output.success);
+    assert!(output.truncated);
+    assert!(output.stdout.starts_with("1\n2\n3\n"));
+    assert!(output.stdout.ends_with("99999\n100000\n"));
+    assert!(output.stdout.contains("bytes truncated"));
+    assert!(output.stdout.len() < 1100);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_interrupt_cancels_command() {
+    let interrupt = CommandInterrupt::new();
+    assert!(!interrupt.interrupt());
+
+    let tool = ExecuteBashCommandTool::new().with_interrupt(interrupt.clone());
+    let handle = tokio::spawn(async move { tool.call(args("sleep 30", None)).await });
+
+    for _ in 0..50 {
+        if interrupt.is_running() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(interrupt.interrupt());
+
+    let output = tokio::time::timeout(Duration::from_secs(10), handle)
+        .await
+        .expect("command was not cancelled")
+        .unwrap()
+        .unwrap();
+    assert!(output.cancelled);
+    assert!(!output.success);
+    assert!(!interrupt.is_running());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_cwd_argument() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    let sub = temp_dir.path().join("sub").canonicalize().unwrap();
+
+    let tool = ExecuteBashCommandTool::new();
+    let mut call = args("pwd", None);
+    call.cwd = Some(sub.to_string_lossy().to_string());
+    let output = tool.call(call).await.unwrap();
+    assert_eq!(output.stdout.trim(), sub.to_string_lossy());
+    assert_eq!(output.cwd, sub.to_string_lossy());
+
+    // Without `cwd` the effective directory is still reported
+    let output = tool.call(args("true", None)).await.unwrap();
+    assert_eq!(
+        output.cwd,
+        std::env::current_dir()
+            .unwrap()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+    );
+
+    let mut call = args("true", None);
+    call.cwd = Some(
+        temp_dir
+            .path()
+            .join("missing")
+            .to_string_lossy()
+            .to_string(),
+    );
+    assert!(matches!(
+        tool.call(call).await,
+        Err(FileToolError::FileNotFound(_))
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_env_argument() {
+    let tool = ExecuteBashCommandTool::new();
+    let mut call = args("echo \"$KOTA_TEST_GREETING\"", None);
+    call.env
+        .insert("KOTA_TEST_GREETING".to_string(), "hello".to_string());
+    let output = tool.call(call).await.unwrap();
+    assert_eq!(output.stdout, "hello\n");
+
+    let mut call = args("true", None);
+    call.env.insert("BAD=NAME".to_string(), "x".to_string());
+    assert!(matches!(
+        tool.call(call).await,
+        Err(FileToolError::InvalidInput(_))
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_env_allow_and_deny_lists() {
+    std::env::set_var("KOTA_TEST_API_KEY", "secret");
+    std::env::set_var("KOTA_TEST_VISIBLE", "visible");
+    let script = "echo \"key=$KOTA_TEST_API_KEY visible=$KOTA_TEST_VISIBLE\"";
+
+    // API keys are not inherited by default
+    let output = ExecuteBashCommandTool::new()
+        .call(args(script, None))
+        .await
+        .unwrap();
+    assert_eq!(output.stdout, "key= visible=visible\n");
+
+    let mut options = ExecOptions::default();
+    options.env_deny.push("KOTA_TEST_VISIBLE".to_string());
+    let tool = ExecuteBashCommandTool::new().with_options(options);
+    let output = tool.call(args(script, None)).await.unwrap();
+    assert_eq!(output.stdout, "key= visible=\n");
+
+    let tool = ExecuteBashCommandTool::new().with_options(ExecOptions {
+        env_allow: vec!["PATH".to_string(), "KOTA_TEST_*".to_string()],
+        env_deny: vec!["*_API_KEY".to_string()],
+        ..ExecOptions::default()
+    });
+    let mut call = args(&format!("{}; echo \"home=$HOME\"", script), None);
+    // Variables passed by the call are set even if the lists would drop them
+    call.env
+        .insert("KOTA_TEST_API_KEY".to_string(), "explicit".to_string());
+    let output = tool.call(call).await.unwrap();
+    assert_eq!(output.stdout, "key=explicit visible=visible\nhome=\n");
+}
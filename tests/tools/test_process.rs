@@ -0,0 +1,129 @@
+use kota::kota_code::tools::process::{ProcessArgs, ProcessTool};
+use kota::kota_code::tools::{ExecOptions, FileToolError, ProcessManager};
+use rig::tool::Tool;
+use std::time::Duration;
+
+fn start(command: &str) -> ProcessArgs {
+    ProcessArgs::Start {
+        command: command.to_string(),
+        cwd: None,
+    }
+}
+
+fn read(id: &str, wait_secs: u64) -> ProcessArgs {
+    ProcessArgs::ReadOutput {
+        id: id.to_string(),
+        wait_secs: Some(wait_secs),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_process_start_read_and_kill() {
+    let manager = ProcessManager::new();
+    let tool = ProcessTool::new(manager.clone());
+
+    let started = tool.call(start("echo ready; sleep 30")).await.unwrap();
+    let id = started.processes[0].id.clone();
+    assert_eq!(id, "p1");
+    assert!(started.processes[0].running);
+
+    let output = tool.call(read(&id, 10)).await.unwrap();
+    assert_eq!(output.output.as_deref(), Some("ready\n"));
+    assert!(output.processes[0].running);
+
+    // Output is incremental: nothing new since the last read
+    let output = tool.call(read(&id, 0)).await.unwrap();
+    assert_eq!(output.output.as_deref(), Some(""));
+
+    let killed = tool
+        .call(ProcessArgs::Kill { id: id.clone() })
+        .await
+        .unwrap();
+    assert!(!killed.processes[0].running);
+    assert_eq!(manager.running_count().await, 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_process_send_input_and_exit_code() {
+    let tool = ProcessTool::new(ProcessManager::new());
+    let id = tool
+        .call(start("read line; echo \"got $line\"; exit 4"))
+        .await
+        .unwrap()
+        .processes[0]
+        .id
+        .clone();
+
+    tool.call(ProcessArgs::SendInput {
+        id: id.clone(),
+        input: "hello\n".to_string(),
+    })
+    .await
+    .unwrap();
+
+    let mut output = String::new();
+    for _ in 0..50 {
+        let result = tool.call(read(&id, 1)).await.unwrap();
+        output.push_str(result.output.as_deref().unwrap_or_default());
+        if !result.processes[0].running {
+            assert_eq!(result.processes[0].exit_code, Some(4));
+            break;
+        }
+    }
+    assert_eq!(output, "got hello\n");
+
+    let result = tool
+        .call(ProcessArgs::SendInput {
+            id: id.clone(),
+            input: "again\n".to_string(),
+        })
+        .await;
+    assert!(matches!(result, Err(FileToolError::InvalidInput(_))));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_process_status_and_unknown_id() {
+    let manager = ProcessManager::new();
+    let tool = ProcessTool::new(manager.clone());
+    tool.call(start("sleep 30")).await.unwrap();
+    tool.call(start("sleep 30")).await.unwrap();
+
+    let status = tool.call(ProcessArgs::Status { id: None }).await.unwrap();
+    let ids: Vec<&str> = status.processes.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(ids, vec!["p1", "p2"]);
+
+    let result = tool
+        .call(ProcessArgs::Status {
+            id: Some("p9".to_string()),
+        })
+        .await;
+    assert!(matches!(result, Err(FileToolError::InvalidInput(_))));
+
+    assert_eq!(manager.running_count().await, 2);
+    manager.kill_all().await;
+    assert_eq!(manager.running_count().await, 0);
+    let status = tool.call(ProcessArgs::Status { id: None }).await.unwrap();
+    assert!(status.processes.is_empty());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_process_unread_output_is_bounded() {
+    let tool = ProcessTool::new(ProcessManager::new()).with_options(ExecOptions {
+        max_output_bytes: 100,
+        ..ExecOptions::default()
+    });
+    let id = tool.call(start("seq 1 10000")).await.unwrap().processes[0]
+        .id
+        .clone();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let output = tool.call(read(&id, 5)).await.unwrap();
+    let text = output.output.unwrap();
+    assert!(text.starts_with('['), "unexpected output: {}", text);
+    assert!(text.contains("earlier bytes dropped"));
+    assert!(text.ends_with("9999\n10000\n"));
+}
@@ -1,7 +1,8 @@
 use super::{create_temp_dir, create_test_file};
 use kota::kota_code::tools::read_file::{ReadFileArgs, ReadFileTool};
-use kota::kota_code::tools::FileToolError;
+use kota::kota_code::tools::{FileToolError, WrappedReadFileTool};
 use rig::tool::Tool;
+use std::fs;
 
 #[tokio::test]
 async fn test_read_existing_file() {
@@ -12,6 +13,7 @@ async fn test_read_existing_file() {
     let tool = ReadFileTool;
     let args = ReadFileArgs {
         file_path: file_path.clone(),
+        ..Default::default()
     };
 
     let result: Result<_, FileToolError> = tool.call(args).await;
@@ -19,10 +21,16 @@ async fn test_read_existing_file() {
     assert!(result.is_ok());
     let output = result.unwrap();
     assert!(output.success);
-    assert_eq!(output.content, content);
+    assert_eq!(
+        output.content,
+        "     1\tHello, world!\n     2\tThis is a test file.\n"
+    );
+    assert_eq!((output.start_line, output.end_line), (1, 2));
+    assert_eq!(output.total_lines, Some(2));
+    assert!(!output.truncated && !output.binary && !output.lossy);
     assert_eq!(output.file_path, file_path);
     assert_eq!(output.size_bytes, content.len() as u64);
-    assert!(output.message.contains("Successfully read"));
+    assert!(output.message.contains("Read lines 1-2 of 2"));
 }
 
 #[tokio::test]
@@ -30,6 +38,7 @@ async fn test_read_nonexistent_file() {
     let tool = ReadFileTool;
     let args = ReadFileArgs {
         file_path: "nonexistent_file.txt".to_string(),
+        ..Default::default()
     };
 
     let result: Result<_, FileToolError> = tool.call(args).await;
@@ -42,3 +51,130 @@ async fn test_read_nonexistent_file() {
         _ => panic!("Expected FileNotFound error"),
     }
 }
+
+#[tokio::test]
+async fn test_read_line_range() {
+    let temp_dir = create_temp_dir();
+    let content: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+    let file_path = create_test_file(temp_dir.path(), "test.txt", &content);
+
+    let tool = ReadFileTool;
+    let output = tool
+        .call(ReadFileArgs {
+            file_path: file_path.clone(),
+            offset: Some(4),
+            limit: Some(2),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.content, "     4\tline 4\n     5\tline 5\n");
+    assert_eq!((output.start_line, output.end_line), (4, 5));
+    // The read stops before the end, so the total is not counted
+    assert_eq!(output.total_lines, None);
+    assert!(output.message.contains("offset=6"), "{}", output.message);
+
+    let output = tool
+        .call(ReadFileArgs {
+            file_path: file_path.clone(),
+            offset: Some(9),
+            limit: Some(2),
+        })
+        .await
+        .unwrap();
+    assert_eq!((output.start_line, output.end_line), (9, 10));
+    assert_eq!(output.total_lines, Some(10));
+    assert!(!output.message.contains("offset="), "{}", output.message);
+
+    let result = tool
+        .call(ReadFileArgs {
+            file_path,
+            offset: Some(11),
+            limit: None,
+        })
+        .await;
+    assert!(matches!(result, Err(FileToolError::InvalidInput(_))));
+}
+
+#[tokio::test]
+async fn test_read_truncates_at_max_bytes() {
+    let temp_dir = create_temp_dir();
+    let content: String = (1..=10_000).map(|i| format!("log entry {}\n", i)).collect();
+    let file_path = create_test_file(temp_dir.path(), "big.log", &content);
+
+    let tool = ReadFileTool;
+    let output = tool
+        .call(ReadFileArgs {
+            file_path,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(output.truncated);
+    assert!(output.content.len() < content.len());
+    assert_eq!(output.total_lines, None);
+    let notice = format!("pass offset={} to read on", output.end_line + 1);
+    assert!(output.content.ends_with(&format!("{}\n", notice)));
+    assert!(output.message.contains(&notice));
+}
+
+#[tokio::test]
+async fn test_read_binary_and_non_utf8_files() {
+    let temp_dir = create_temp_dir();
+    let binary = temp_dir.path().join("image.png");
+    fs::write(&binary, [0x89, b'P', b'N', b'G', 0, 0, 1, 2]).unwrap();
+    let latin1 = temp_dir.path().join("latin1.txt");
+    fs::write(&latin1, b"caf\xe9\nok\n").unwrap();
+
+    let tool = ReadFileTool;
+    let output = tool
+        .call(ReadFileArgs {
+            file_path: binary.to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(output.binary);
+    assert!(output.content.is_empty());
+    assert_eq!(output.size_bytes, 8);
+
+    let output = tool
+        .call(ReadFileArgs {
+            file_path: latin1.to_string_lossy().to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(output.lossy);
+    assert_eq!(output.content, "     1\tcaf\u{fffd}\n     2\tok\n");
+}
+
+#[tokio::test]
+async fn test_read_cuts_a_long_line_at_a_character_boundary() {
+    let temp_dir = create_temp_dir();
+    let content = format!("{}\r\nsecond\r\n", "é".repeat(1000));
+    let file_path = create_test_file(temp_dir.path(), "long.txt", &content);
+
+    let tool = WrappedReadFileTool::new().with_max_bytes(101);
+    let output = tool
+        .call(ReadFileArgs {
+            file_path: file_path.clone(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(output.truncated);
+    assert!(!output.lossy);
+    assert_eq!(output.end_line, 1);
+    assert!(output.content.starts_with("     1\téé"));
+
+    let output = tool
+        .call(ReadFileArgs {
+            file_path,
+            offset: Some(2),
+            limit: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.content, "     2\tsecond\n");
+    assert_eq!(output.total_lines, Some(2));
+}
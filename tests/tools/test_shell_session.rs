@@ -0,0 +1,106 @@
+use kota::kota_code::tools::shell_session::{ShellSessionArgs, ShellSessionTool};
+use kota::kota_code::tools::ExecOptions;
+use rig::tool::Tool;
+
+fn args(command: &str) -> ShellSessionArgs {
+    ShellSessionArgs {
+        command: command.to_string(),
+        timeout_secs: None,
+        restart: false,
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shell_session_keeps_state() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path().canonicalize().unwrap();
+    let tool = ShellSessionTool::new();
+
+    let output = tool
+        .call(args(&format!("cd '{}'", dir.display())))
+        .await
+        .unwrap();
+    assert!(output.success);
+    assert!(output.new_session);
+    assert_eq!(output.cwd, dir.to_string_lossy());
+
+    tool.call(args("export GREETING=hi; shout() { echo \"$1!\"; }"))
+        .await
+        .unwrap();
+    let output = tool.call(args("pwd; shout \"$GREETING\"")).await.unwrap();
+    assert!(!output.new_session);
+    assert_eq!(output.output, format!("{}\nhi!\n", dir.display()));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shell_session_exit_codes_and_framing() {
+    let tool = ShellSessionTool::new();
+
+    let output = tool.call(args("printf abc")).await.unwrap();
+    assert_eq!(output.output, "abc");
+    assert_eq!(output.exit_code, Some(0));
+
+    let output = tool
+        .call(args("echo out; echo err >&2; false"))
+        .await
+        .unwrap();
+    assert!(!output.success);
+    assert_eq!(output.exit_code, Some(1));
+    assert_eq!(output.output, "out\nerr\n");
+
+    // Stdin is closed, so `read` cannot swallow the shell's own input
+    let output = tool
+        .call(args("read line; echo \"read: $?\""))
+        .await
+        .unwrap();
+    assert_eq!(output.output, "read: 1\n");
+
+    // A syntax error fails the command but keeps the session
+    let output = tool.call(args("if then")).await.unwrap();
+    assert_eq!(output.exit_code, Some(2));
+    let output = tool.call(args("echo 'still here'")).await.unwrap();
+    assert!(!output.new_session);
+    assert_eq!(output.output, "still here\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shell_session_exit_starts_new_session() {
+    let tool = ShellSessionTool::new();
+    tool.call(args("MARKER=set")).await.unwrap();
+
+    let output = tool.call(args("exit 3")).await.unwrap();
+    assert_eq!(output.exit_code, Some(3));
+
+    let output = tool.call(args("echo \"marker=$MARKER\"")).await.unwrap();
+    assert!(output.new_session);
+    assert_eq!(output.output, "marker=\n");
+
+    tool.call(args("MARKER=again")).await.unwrap();
+    let mut call = args("echo \"marker=$MARKER\"");
+    call.restart = true;
+    let output = tool.call(call).await.unwrap();
+    assert!(output.new_session);
+    assert_eq!(output.output, "marker=\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shell_session_timeout_resets_session() {
+    let tool = ShellSessionTool::new().with_options(ExecOptions {
+        timeout_secs: 1,
+        ..ExecOptions::default()
+    });
+    tool.call(args("MARKER=set")).await.unwrap();
+
+    let output = tool.call(args("echo started; sleep 30")).await.unwrap();
+    assert!(output.timed_out);
+    assert_eq!(output.exit_code, None);
+    assert!(output.output.starts_with("started\n"));
+
+    let output = tool.call(args("echo \"marker=$MARKER\"")).await.unwrap();
+    assert!(output.new_session);
+    assert_eq!(output.output, "marker=\n");
+}
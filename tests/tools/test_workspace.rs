@@ -0,0 +1,210 @@
+use super::{create_temp_dir, create_test_file};
+use kota::kota_code::tools::execute_bash_command::ExecuteBashCommandArgs;
+use kota::kota_code::tools::process::ProcessArgs;
+use kota::kota_code::tools::read_file::{ReadFileArgs, WrappedReadFileTool};
+use kota::kota_code::tools::write_file::{WrappedWriteFileTool, WriteFileArgs};
+use kota::kota_code::tools::{
+    Access, FileToolError, ProcessManager, Workspace, WrappedExecuteBashCommandTool,
+    WrappedProcessTool,
+};
+use rig::tool::Tool;
+use std::fs;
+
+#[test]
+fn test_workspace_resolves_relative_paths() {
+    let temp_dir = create_temp_dir();
+    let workspace = Workspace::new(temp_dir.path()).unwrap();
+
+    let resolved = workspace.resolve("src/new/lib.rs", Access::Write).unwrap();
+    assert_eq!(resolved, workspace.root().join("src/new/lib.rs"));
+
+    // `..` that stays inside the root is fine
+    let resolved = workspace.resolve("src/../README.md", Access::Read).unwrap();
+    assert_eq!(resolved, workspace.root().join("README.md"));
+}
+
+#[test]
+fn test_workspace_rejects_paths_outside_root() {
+    let temp_dir = create_temp_dir();
+    fs::create_dir(temp_dir.path().join("project")).unwrap();
+    let workspace = Workspace::new(temp_dir.path().join("project")).unwrap();
+
+    let result = workspace.resolve("../secret.txt", Access::Read);
+    assert!(matches!(
+        result,
+        Err(FileToolError::OutsideWorkspace { .. })
+    ));
+
+    let outside = temp_dir.path().join("secret.txt");
+    let result = workspace.resolve(&outside.to_string_lossy(), Access::Write);
+    assert!(matches!(
+        result,
+        Err(FileToolError::OutsideWorkspace { .. })
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspace_detects_symlink_escape() {
+    let temp_dir = create_temp_dir();
+    let outside = create_temp_dir();
+    create_test_file(outside.path(), "secret.txt", "token");
+    std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+    let workspace = Workspace::new(temp_dir.path()).unwrap();
+
+    for path in ["link/secret.txt", "link/new.txt"] {
+        match workspace.resolve(path, Access::Read) {
+            Err(FileToolError::OutsideWorkspace { reason, .. }) => {
+                assert!(reason.contains("symlink"), "unexpected reason: {}", reason)
+            }
+            other => panic!("expected OutsideWorkspace for {}, got {:?}", path, other),
+        }
+    }
+}
+
+#[test]
+fn test_workspace_protected_paths() {
+    let temp_dir = create_temp_dir();
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    create_test_file(temp_dir.path(), ".env", "KEY=value");
+    let workspace = Workspace::new(temp_dir.path())
+        .unwrap()
+        .with_protected(vec!["secrets/**".to_string()])
+        .unwrap();
+
+    for path in [
+        ".git/config",
+        ".env",
+        "secrets/key.pem",
+        "crates/x/.env",
+        "vendor/lib/.git",
+        "vendor/lib/.git/HEAD",
+    ] {
+        let result = workspace.resolve(path, Access::Write);
+        assert!(
+            matches!(result, Err(FileToolError::OutsideWorkspace { .. })),
+            "{} should be protected",
+            path
+        );
+    }
+
+    // Protected paths can still be read
+    assert!(workspace.resolve(".env", Access::Read).is_ok());
+    assert!(workspace.resolve("src/.env.example", Access::Write).is_ok());
+}
+
+#[test]
+fn test_workspace_invalid_protected_glob() {
+    let temp_dir = create_temp_dir();
+    let result = Workspace::new(temp_dir.path())
+        .unwrap()
+        .with_protected(vec!["[".to_string()]);
+    assert!(matches!(result, Err(FileToolError::InvalidInput(_))));
+}
+
+#[tokio::test]
+async fn test_wrapped_tools_confined_to_workspace() {
+    let temp_dir = create_temp_dir();
+    let outside = create_temp_dir();
+    let workspace = Workspace::new(temp_dir.path()).unwrap();
+
+    let write = WrappedWriteFileTool::new().with_workspace(workspace.clone());
+    let result = write
+        .call(WriteFileArgs {
+            file_path: "notes/todo.txt".to_string(),
+            content: "ship it".to_string(),
+        })
+        .await;
+    assert!(result.is_ok());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("notes/todo.txt")).unwrap(),
+        "ship it"
+    );
+
+    let target = outside.path().join("escape.txt");
+    let result = write
+        .call(WriteFileArgs {
+            file_path: target.to_string_lossy().to_string(),
+            content: "nope".to_string(),
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(FileToolError::OutsideWorkspace { .. })
+    ));
+    assert!(!target.exists());
+
+    let read = WrappedReadFileTool::new().with_workspace(workspace);
+    let output = read
+        .call(ReadFileArgs {
+            file_path: "notes/todo.txt".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.content, "     1\tship it\n");
+}
+
+#[tokio::test]
+async fn test_exec_cwd_confined_to_workspace() {
+    let temp_dir = create_temp_dir();
+    fs::create_dir(temp_dir.path().join("project")).unwrap();
+    fs::create_dir(temp_dir.path().join("project/sub")).unwrap();
+    let workspace = Workspace::new(temp_dir.path().join("project")).unwrap();
+    let exec = WrappedExecuteBashCommandTool::new().with_workspace(workspace.clone());
+
+    let args = |cwd: &str| ExecuteBashCommandArgs {
+        command: "pwd".to_string(),
+        timeout_secs: None,
+        cwd: Some(cwd.to_string()),
+        env: Default::default(),
+    };
+    let output = exec.call(args("sub")).await.unwrap();
+    assert_eq!(output.cwd, workspace.root().join("sub").to_string_lossy());
+
+    // Without a cwd the command runs in the workspace root, not kota's directory
+    let output = exec
+        .call(ExecuteBashCommandArgs {
+            cwd: None,
+            ..args("")
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.cwd, workspace.root().to_string_lossy());
+    assert_eq!(output.stdout.trim_end(), workspace.root().to_string_lossy());
+
+    for cwd in ["..", "/"] {
+        let result = exec.call(args(cwd)).await;
+        assert!(matches!(
+            result,
+            Err(FileToolError::OutsideWorkspace { .. })
+        ));
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_process_cwd_confined_to_workspace() {
+    let temp_dir = create_temp_dir();
+    fs::create_dir(temp_dir.path().join("project")).unwrap();
+    let workspace = Workspace::new(temp_dir.path().join("project")).unwrap();
+    let manager = ProcessManager::new();
+    let process = WrappedProcessTool::new(manager.clone()).with_workspace(workspace.clone());
+
+    let start = |cwd: Option<&str>| ProcessArgs::Start {
+        command: "true".to_string(),
+        cwd: cwd.map(str::to_string),
+    };
+    // Without a cwd the process starts in the workspace root
+    let output = process.call(start(None)).await.unwrap();
+    assert_eq!(output.processes[0].cwd, workspace.root().to_string_lossy());
+
+    for cwd in ["..", "/"] {
+        let result = process.call(start(Some(cwd))).await;
+        assert!(matches!(
+            result,
+            Err(FileToolError::OutsideWorkspace { .. })
+        ));
+    }
+    manager.kill_all().await;
+}
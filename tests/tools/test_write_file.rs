@@ -28,6 +28,9 @@ async fn test_write_new_file() {
     assert_eq!(output.file_path, file_path);
     assert_eq!(output.bytes_written, content.len() as u64);
     assert!(output.message.contains("Successfully wrote"));
+    assert!(output.created);
+    assert_eq!(output.lines_added, Some(2));
+    assert!(output.diff.is_empty());
 
     // Verify file was actually written
     let written_content = fs::read_to_string(&file_path).unwrap();
@@ -59,8 +62,35 @@ async fn test_overwrite_existing_file() {
     let output = result.unwrap();
     assert!(output.success);
     assert_eq!(output.bytes_written, new_content.len() as u64);
+    assert!(!output.created);
+    assert_eq!(
+        (output.lines_added, output.lines_removed),
+        (Some(1), Some(1))
+    );
+    assert!(output.diff.contains("-Original content"));
+    assert!(output.diff.contains("+New content that replaces the old"));
 
     // Verify file was overwritten
     let written_content = fs::read_to_string(&file_path).unwrap();
     assert_eq!(written_content, new_content);
 }
+
+#[tokio::test]
+async fn test_overwrite_binary_file_without_diff() {
+    let temp_dir = create_temp_dir();
+    let file_path = temp_dir.path().join("image.png");
+    fs::write(&file_path, [0x89, b'P', b'N', b'G', 0, 0, 0xff]).unwrap();
+
+    let output = WriteFileTool
+        .call(WriteFileArgs {
+            file_path: file_path.to_string_lossy().to_string(),
+            content: "now text".to_string(),
+        })
+        .await
+        .unwrap();
+    assert!(output.success);
+    assert!(!output.created);
+    assert_eq!((output.lines_added, output.lines_removed), (None, None));
+    assert!(output.diff.is_empty());
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "now text");
+}